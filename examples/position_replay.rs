@@ -0,0 +1,49 @@
+// Replay server for testing the live position feed without a GPS receiver
+//
+// Serves the lines of a recorded NMEA or gpsd log to every client that connects, one line
+// per interval, starting over at the end:
+//
+//     cargo run --example position_replay -- track.nmea 10110 250
+//     cargo run -- --nmea 127.0.0.1:10110
+//
+// gpsd logs work the same way with --gpsd; the WATCH command the app sends is ignored.
+
+use std::io::Write;
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some(path) = args.first() else {
+        eprintln!("Usage: position_replay <log file> [port] [milliseconds per line]");
+        std::process::exit(1);
+    };
+    let port: u16 = args.get(1).and_then(|port| port.parse().ok()).unwrap_or(10110);
+    let interval = Duration::from_millis(args.get(2).and_then(|ms| ms.parse().ok()).unwrap_or(250));
+
+    let text = std::fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("Failed to read {}: {}", path, e);
+        std::process::exit(1);
+    });
+    let lines: Vec<String> = text.lines().filter(|line| !line.trim().is_empty()).map(str::to_string).collect();
+
+    let listener = TcpListener::bind(("127.0.0.1", port)).expect("Failed to listen");
+    println!("Replaying {} lines of {} on 127.0.0.1:{}", lines.len(), path, port);
+    for stream in listener.incoming().flatten() {
+        let lines = lines.clone();
+        thread::spawn(move || replay(stream, &lines, interval));
+    }
+}
+
+fn replay(mut stream: TcpStream, lines: &[String], interval: Duration) {
+    let peer = stream.peer_addr().map(|address| address.to_string()).unwrap_or_default();
+    println!("{} connected", peer);
+    for line in lines.iter().cycle() {
+        if writeln!(stream, "{}\r", line).is_err() {
+            break;
+        }
+        thread::sleep(interval);
+    }
+    println!("{} disconnected", peer);
+}
@@ -1,4 +1,5 @@
 use bevy::prelude::*;
+use bevy::math::DVec3;
 
 /// Marker component for the UI text that displays the current zoom level
 #[derive(Component)]
@@ -10,13 +11,98 @@ pub struct TileCountText;
 #[derive(Component)]
 pub struct FpsCounterText;
 
+/// Marker component for the UI text that lists the map layers
+#[derive(Component)]
+pub struct LayersText;
+
+/// Marker component for the UI panel that lists the bookmarks
+#[derive(Component)]
+pub struct BookmarkPanel;
+
+/// UI button that flies to the bookmark at this index
+#[derive(Component)]
+pub struct BookmarkButton(pub usize);
+
+/// Marker component for the UI text that shows the live position and its accuracy
+#[derive(Component)]
+pub struct LivePositionText;
+
+/// Marker component for the UI panel with the GPX track timeline
+#[derive(Component)]
+pub struct TrackTimelinePanel;
+
+/// Marker component for the GPX track timeline bar, which seeks to the clicked time
+#[derive(Component)]
+pub struct TrackTimeline;
+
+/// Marker component for the played part of the track timeline
+#[derive(Component)]
+pub struct TrackTimelineFill;
+
+/// Marker component for the UI text that shows the track playback state
+#[derive(Component)]
+pub struct TrackTimelineText;
+
+/// Marker component for the popup that shows what is at the clicked point of the map
+#[derive(Component)]
+pub struct InspectPopup;
+
+/// UI button that copies the inspected point to the clipboard
+#[derive(Component)]
+pub struct InspectCopyButton;
+
+/// UI button that closes the inspect popup
+#[derive(Component)]
+pub struct InspectCloseButton;
+
 #[derive(Component)]
 pub struct TileCoords {
-    pub x: u32,
+    pub x: i32, // Unwrapped column - tiles repeat east and west of the antimeridian
     pub y: u32,
     pub zoom: u32,
     pub last_used: f32,
 }
 
 #[derive(Component)]
-pub struct BackgroundTile; 
\ No newline at end of file
+pub struct BackgroundTile;
+
+/// Bytes a tile occupies on the GPU, used for the residency budget
+#[derive(Component, Clone, Copy, Debug)]
+pub struct TileMemory {
+    pub texture_bytes: usize,
+    pub mesh_bytes: usize,
+}
+
+impl TileMemory {
+    pub fn total(&self) -> usize {
+        self.texture_bytes + self.mesh_bytes
+    }
+}
+
+/// Where a tile is in its lifecycle, from the request until it is despawned
+#[derive(Component, Clone, Debug, PartialEq)]
+pub enum TileState {
+    /// Registered and waiting for the loader to pick it up
+    Requested,
+    /// Bytes are being read from the cache or the network
+    Downloading,
+    /// Bytes arrived and the image is being decoded
+    Decoding,
+    /// The image is on screen
+    Ready,
+    /// Loading failed; a fallback is shown until a retry succeeds
+    Failed { reason: String, retries: u32 },
+    /// Out of use and about to be despawned
+    Evicting,
+}
+
+/// Precise f64 world-space position; the `Transform` is derived from it via `FloatingOrigin`
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct WorldPosition(pub DVec3);
+
+/// Marks a tile whose mesh is currently bent towards the globe, and by how much
+#[derive(Component)]
+pub struct MorphedTile {
+    pub factor: f32,
+    pub anchor: DVec3,
+}
@@ -0,0 +1,70 @@
+use bevy::prelude::*;
+use crate::resources::{TileKey, CameraPose};
+
+/// A tile was registered and its image requested
+#[derive(Event)]
+pub struct TileRequested {
+    pub entity: Entity,
+    pub key: TileKey,
+}
+
+/// A tile image arrived and the tile is now on screen
+#[derive(Event)]
+pub struct TileLoaded {
+    pub entity: Entity,
+    pub key: TileKey,
+}
+
+/// Loading a tile failed; `retries` counts the attempts made before this one
+#[derive(Event)]
+pub struct TileFailed {
+    pub entity: Entity,
+    pub key: TileKey,
+    pub reason: String,
+    pub retries: u32,
+}
+
+/// A tile went out of use and is being despawned
+#[derive(Event)]
+pub struct TileEvicted {
+    pub entity: Entity,
+    pub key: TileKey,
+}
+
+/// Request to fly the camera to a geographic position
+#[derive(Event, Clone, Copy, Debug)]
+pub struct FlyToRequested {
+    /// WGS84 degrees
+    pub lat: f64,
+    pub lon: f64,
+    /// Camera altitude in metres above ground at the destination
+    pub altitude: f64,
+    /// Heading and pitch in radians at the destination, the current ones when not given
+    pub heading: Option<f32>,
+    pub pitch: Option<f32>,
+}
+
+impl FlyToRequested {
+    /// Fly to a camera pose, ending with its heading and pitch
+    pub fn to_pose(pose: &CameraPose) -> Self {
+        Self {
+            lat: pose.lat,
+            lon: pose.lon,
+            altitude: pose.altitude,
+            heading: Some(pose.yaw()),
+            pitch: Some(pose.pitch_radians()),
+        }
+    }
+}
+
+/// The camera reached the destination of a fly-to
+#[derive(Event, Clone, Debug)]
+pub struct FlyToArrived {
+    pub lat: f64,
+    pub lon: f64,
+    pub altitude: f64,
+}
+
+/// Request to copy the inspected point of the map to the clipboard
+#[derive(Event, Clone, Debug)]
+pub struct CopyInspectionRequested;
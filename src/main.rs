@@ -6,6 +6,7 @@ mod systems;
 mod plugins;
 mod utils;
 mod osm;
+mod events;
 
 fn main() {
     App::new()
@@ -6,6 +6,10 @@ use std::time::Duration;
 use reqwest::Client;
 use image::DynamicImage;
 use crate::osm::tile::OSMTile;
+use crate::resources::MapLayer;
+
+// Layer that the tiles cached before there were layers belong to
+const LEGACY_CACHE_LAYER: &str = "osm";
 
 // Initialize the tile cache system
 pub fn init_tile_cache() -> io::Result<()> {
@@ -14,23 +18,48 @@ pub fn init_tile_cache() -> io::Result<()> {
         fs::create_dir_all(cache_dir)?;
         info!("Created tile cache directory: {}", cache_dir.display());
     }
+    migrate_legacy_cache(cache_dir);
     Ok(())
 }
 
-// Try to load a tile from the cache
-pub fn load_tile_from_cache(tile: &OSMTile) -> Option<DynamicImage> {
-    let cache_path = tile.get_cache_path();
+// Move tiles from the single-source layout, tile_cache/z/x/y.png, to the layer they came
+// from, tile_cache/osm/z/x/y.png, so they aren't downloaded again
+fn migrate_legacy_cache(cache_dir: &Path) {
+    let Ok(entries) = fs::read_dir(cache_dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        // Zoom level directories right under the cache root are from the old layout
+        let name = entry.file_name();
+        if name.to_str().and_then(|name| name.parse::<u32>().ok()).is_none() {
+            continue;
+        }
+
+        let layer_dir = cache_dir.join(LEGACY_CACHE_LAYER);
+        let target = layer_dir.join(&name);
+        if target.exists() {
+            warn!("Leaving old cached tiles in {}: {} already exists", entry.path().display(), target.display());
+            continue;
+        }
+        match fs::create_dir_all(&layer_dir).and_then(|_| fs::rename(entry.path(), &target)) {
+            Ok(_) => info!("Moved old cached tiles from {} to {}", entry.path().display(), target.display()),
+            Err(e) => warn!("Failed to move old cached tiles from {}: {}", entry.path().display(), e),
+        }
+    }
+}
+
+// Try to read the raw bytes of a tile from the cache
+pub fn load_tile_bytes_from_cache(tile: &OSMTile, layer: &MapLayer) -> Option<Vec<u8>> {
+    let cache_path = tile.get_cache_path(layer);
 
     if cache_path.exists() {
-        match image::open(&cache_path) {
-            Ok(img) => {
-                info!("Loaded tile {},{},{} from cache", tile.x, tile.y, tile.z);
-                return Some(img);
+        match fs::read(&cache_path) {
+            Ok(bytes) => {
+                info!("Loaded {} tile {},{},{} from cache", layer.name, tile.wrapped_x(), tile.y, tile.z);
+                return Some(bytes);
             },
             Err(e) => {
-                warn!("Failed to load cached tile: {}", e);
-                // Try to remove corrupt cache file
-                let _ = fs::remove_file(&cache_path);
+                warn!("Failed to read cached tile: {}", e);
             }
         }
     }
@@ -38,24 +67,25 @@ pub fn load_tile_from_cache(tile: &OSMTile) -> Option<DynamicImage> {
     None
 }
 
-// Save a tile to the cache
-pub fn save_tile_to_cache(tile: &OSMTile, image: &DynamicImage) {
-    let cache_path = tile.get_cache_path();
+// Save the raw bytes of a downloaded tile to the cache
+pub fn save_tile_bytes_to_cache(tile: &OSMTile, layer: &MapLayer, bytes: &[u8]) {
+    let cache_path = tile.get_cache_path(layer);
 
-    match image.save(&cache_path) {
-        Ok(_) => info!("Saved tile {},{},{} to cache", tile.x, tile.y, tile.z),
+    match fs::write(&cache_path, bytes) {
+        Ok(_) => info!("Saved {} tile {},{},{} to cache", layer.name, tile.wrapped_x(), tile.y, tile.z),
         Err(e) => warn!("Failed to cache tile: {}", e),
     }
 }
 
-pub async fn load_tile_image(tile: &OSMTile) -> Result<DynamicImage, anyhow::Error> {
+// Fetch the encoded image of a tile in a layer, from the cache or else from the network
+pub async fn fetch_tile_bytes(tile: &OSMTile, layer: &MapLayer) -> Result<Vec<u8>, anyhow::Error> {
     // First try loading from cache
-    if let Some(cached_image) = load_tile_from_cache(tile) {
-        return Ok(cached_image);
+    if let Some(cached_bytes) = load_tile_bytes_from_cache(tile, layer) {
+        return Ok(cached_bytes);
     }
 
     // If not in cache, fetch from network
-    info!("Tile not in cache, fetching from network: {},{},{}", tile.x, tile.y, tile.z);
+    info!("Tile not in cache, fetching from network: {},{},{}", tile.wrapped_x(), tile.y, tile.z);
 
     // Create a client with proper user agent and timeout
     let client = Client::builder()
@@ -63,25 +93,37 @@ pub async fn load_tile_image(tile: &OSMTile) -> Result<DynamicImage, anyhow::Err
         .user_agent("bevy_osm_viewer/0.1.0 (github.com/user/bevy_osm_viewer)")
         .build()?;
 
-    let url = tile.get_url();
-    info!("Requesting OSM tile URL: {}", url);
+    let url = tile.get_url(layer);
+    info!("Requesting {} tile URL: {}", layer.name, url);
 
     // Attempt to load the tile with better error handling
     let response = client.get(&url).send().await?;
 
     if !response.status().is_success() {
-        error!("Failed to load tile {},{} - HTTP status: {}", tile.x, tile.y, response.status());
+        error!("Failed to load tile {},{} - HTTP status: {}", tile.wrapped_x(), tile.y, response.status());
         return Err(anyhow::anyhow!("HTTP error: {}", response.status()));
     }
 
     let bytes = response.bytes().await?;
-    info!("Received {} bytes for tile {},{}", bytes.len(), tile.x, tile.y);
-
-    let image = image::load_from_memory(&bytes)?;
-    info!("Image loaded: {}x{}", image.width(), image.height());
+    info!("Received {} bytes for tile {},{}", bytes.len(), tile.wrapped_x(), tile.y);
 
     // Save to cache
-    save_tile_to_cache(tile, &image);
+    save_tile_bytes_to_cache(tile, layer, &bytes);
+
+    Ok(bytes.to_vec())
+}
 
-    Ok(image)
-} 
\ No newline at end of file
+// Decode the encoded image of a tile in a layer
+pub fn decode_tile_image(tile: &OSMTile, layer: &MapLayer, bytes: &[u8]) -> Result<DynamicImage, anyhow::Error> {
+    match image::load_from_memory(bytes) {
+        Ok(image) => {
+            info!("Image loaded: {}x{}", image.width(), image.height());
+            Ok(image)
+        },
+        Err(e) => {
+            // Remove the corrupt cache file so the next attempt downloads the tile again
+            let _ = fs::remove_file(tile.get_cache_path(layer));
+            Err(e.into())
+        }
+    }
+}
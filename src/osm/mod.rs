@@ -1,7 +1,9 @@
 mod tile;
 mod cache;
 mod rendering;
+mod texture;
 
 pub use tile::OSMTile;
-pub use cache::{init_tile_cache, load_tile_image};
-pub use rendering::{create_tile_mesh, create_fallback_tile_mesh}; 
\ No newline at end of file
+pub use cache::{init_tile_cache, fetch_tile_bytes};
+pub use rendering::{create_tile_mesh, create_fallback_tile_mesh};
+pub use texture::decode_tile_texture; 
\ No newline at end of file
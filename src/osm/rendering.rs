@@ -1,10 +1,16 @@
 use bevy::prelude::*;
 use bevy::render::render_asset::RenderAssetUsages;
-use image::DynamicImage;
 use bevy::color::LinearRgba;
 use crate::osm::tile::OSMTile;
-use crate::resources::constants::DEFAULT_ZOOM_LEVEL;
-use crate::components::{TileCoords, BackgroundTile};
+use crate::utils::projection::tile_size_world;
+use crate::utils::coordinate_conversion::tile_to_world;
+use crate::components::{WorldPosition, MorphedTile, TileMemory};
+use crate::resources::FloatingOrigin;
+use crate::resources::constants::TILE_MESH_SUBDIVISIONS;
+use bevy::math::DVec3;
+use bevy::ecs::system::EntityCommands;
+use bevy::render::primitives::Aabb;
+use bevy::picking::mesh_picking::ray_cast::RayCastBackfaces;
 
 // Bundle for the tile entity to ensure all components are added atomically
 #[derive(Bundle)]
@@ -16,18 +22,9 @@ struct TileBundle {
     name: Name,
 }
 
-// Create a tile mesh with the loaded image
-pub fn create_tile_mesh(
-    commands: &mut Commands,
-    meshes: &mut Assets<Mesh>,
-    materials: &mut Assets<StandardMaterial>,
-    images: &mut Assets<Image>,
-    tile: &OSMTile,
-    image: DynamicImage,
-    current_time: f32,
-    is_background: bool,
-) -> Entity {
-    // Create a custom mesh for a horizontal tile (XZ plane with Y as up)
+// Create a flat tile mesh subdivided into a grid of quads
+// The grid lets the globe mode bend tiles onto the ellipsoid
+pub fn create_tile_grid_mesh(subdivisions: u32) -> Mesh {
     let mut mesh = Mesh::new(
         bevy::render::mesh::PrimitiveTopology::TriangleList,
         RenderAssetUsages::default(),
@@ -43,28 +40,67 @@ pub fn create_tile_mesh(
     // - Y is up (height)
 
     // Create vertices at exact [0,1] range to ensure perfect alignment
-    let vertices: [[f32; 8]; 4] = [
-        // positions (XYZ)               normals (XYZ)       UV coords
-        [0.0, 0.0, 0.0,    0.0, 1.0, 0.0,          0.0, 0.0], // northwest corner
-        [1.0, 0.0, 0.0,    0.0, 1.0, 0.0,          1.0, 0.0], // northeast corner
-        [1.0, 0.0, 1.0,    0.0, 1.0, 0.0,          1.0, 1.0], // southeast corner
-        [0.0, 0.0, 1.0,    0.0, 1.0, 0.0,          0.0, 1.0], // southwest corner
-    ];
-
-    let positions: Vec<[f32; 3]> = vertices.iter().map(|v| [v[0], v[1], v[2]]).collect();
-    let normals: Vec<[f32; 3]> = vertices.iter().map(|v| [v[3], v[4], v[5]]).collect();
-    let uvs: Vec<[f32; 2]> = vertices.iter().map(|v| [v[6], v[7]]).collect();
-    let indices = vec![0, 1, 2, 0, 2, 3]; // triangulate the quad
+    let segments = subdivisions.max(1);
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut uvs: Vec<[f32; 2]> = Vec::new();
+    for row in 0..=segments {
+        for column in 0..=segments {
+            let u = column as f32 / segments as f32;
+            let v = row as f32 / segments as f32;
+            positions.push([u, 0.0, v]);
+            uvs.push([u, v]);
+        }
+    }
+    let normals = vec![[0.0, 1.0, 0.0]; positions.len()];
+
+    // Triangulate each quad of the grid
+    let stride = segments + 1;
+    let mut indices = Vec::with_capacity((segments * segments * 6) as usize);
+    for row in 0..segments {
+        for column in 0..segments {
+            let north_west = row * stride + column;
+            let north_east = north_west + 1;
+            let south_west = north_west + stride;
+            let south_east = south_west + 1;
+            indices.extend_from_slice(&[north_west, north_east, south_east, north_west, south_east, south_west]);
+        }
+    }
 
     mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
     mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
     mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
     mesh.insert_indices(bevy::render::mesh::Indices::U32(indices));
+    mesh
+}
 
-    // Check if we need to flip the image vertically to match the UV coordinates
-    // OSM tiles have (0,0) at the top-left
-    let flipped_image = image::DynamicImage::ImageRgba8(image.to_rgba8());
-    let texture = Image::from_dynamic(flipped_image, true, RenderAssetUsages::default());
+// Size of a mesh's vertex and index buffers in bytes
+fn mesh_bytes(mesh: &Mesh) -> usize {
+    let vertex_bytes = mesh.count_vertices() * mesh.get_vertex_size() as usize;
+    let index_bytes = mesh.indices().map_or(0, |indices| match indices {
+        bevy::render::mesh::Indices::U16(indices) => indices.len() * 2,
+        bevy::render::mesh::Indices::U32(indices) => indices.len() * 4,
+    });
+    vertex_bytes + index_bytes
+}
+
+// Give a tile entity its mesh with the prepared texture
+#[allow(clippy::too_many_arguments)]
+pub fn create_tile_mesh(
+    entity: &mut EntityCommands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    images: &mut Assets<Image>,
+    tile: &OSMTile,
+    texture: Image,
+    is_background: bool,
+    floating_origin: &FloatingOrigin,
+) {
+    // Create a subdivided mesh for a horizontal tile (XZ plane with Y as up)
+    let mesh = create_tile_grid_mesh(TILE_MESH_SUBDIVISIONS);
+    let memory = TileMemory {
+        texture_bytes: texture.data.len(),
+        mesh_bytes: mesh_bytes(&mesh),
+    };
     let texture_handle = images.add(texture);
 
     // Create a material with the texture
@@ -80,9 +116,9 @@ pub fn create_tile_mesh(
         ..default()
     });
 
-    // Calculate zoom level difference to determine scaling and positioning
-    let zoom_difference = tile.z as i32 - DEFAULT_ZOOM_LEVEL as i32;
-    let scale_factor = 2_f32.powi(-zoom_difference); // Inverse because higher zoom = smaller tile
+    // Calculate tile size to determine scaling and positioning
+    // Tiles are WORLD_SIZE / 2^zoom metres wide in our Web Mercator world
+    let scale_factor = tile_size_world(tile.z);
 
     // Create mesh and material handles
     let mesh_handle = meshes.add(mesh);
@@ -93,76 +129,54 @@ pub fn create_tile_mesh(
     // Use a small offset that won't be noticeable visually but will fix z-fighting
     let y_offset = if is_background {
         // Background tiles should always be below focus tiles
-        -0.01
+        -0.5
     } else {
         // Higher zoom levels should be on top
-        0.005 * (tile.z as f32 / 19.0) // Normalize to a small range
+        0.25 * (tile.z as f32 / 19.0) // Normalize to a small range (in metres)
     };
 
-    // Create transform
-    let transform = Transform::from_xyz(
-        tile.x as f32 * scale_factor,       // Scale X coordinate
-        y_offset,                          // Small Y offset based on zoom to prevent z-fighting
-        tile.y as f32 * scale_factor        // Scale Z coordinate
-    )
-    .with_scale(Vec3::new(scale_factor, 1.0, scale_factor)); // Scale the tile size
-
-    // Spawn entity with everything at once
-    let mut entity_builder = commands.spawn((
-        Mesh3d(mesh_handle),
-        MeshMaterial3d(material_handle),
-        transform,
-        GlobalTransform::default(),
-        Name::new(format!("Tile {},{}, zoom {}", tile.x, tile.y, tile.z)),
-        TileCoords {
-            x: tile.x,
-            y: tile.y,
-            zoom: tile.z,
-            last_used: current_time,
-        },
-    ));
-    
-    // Add background component if this is a background tile
-    if is_background {
-        entity_builder.insert(BackgroundTile);
-    }
-    
-    entity_builder.id()
+    // Keep the tile corner in f64 world space and render it relative to the floating origin
+    let (corner_x, corner_z) = tile_to_world(tile.x, tile.y, tile.z);
+    let world_position = DVec3::new(
+        corner_x,                        // Northwest corner X
+        y_offset as f64,                 // Small Y offset based on zoom to prevent z-fighting
+        corner_z                         // Northwest corner Z
+    );
+    let transform = Transform::from_translation(floating_origin.world_to_render(world_position))
+        .with_scale(Vec3::new(scale_factor as f32, 1.0, scale_factor as f32)); // Scale the tile size
+
+    // Add everything to the tile entity at once, replacing an earlier fallback mesh
+    // A morphed or bounded earlier mesh no longer applies, so drop that state too
+    entity
+        .insert((
+            Mesh3d(mesh_handle),
+            MeshMaterial3d(material_handle),
+            transform,
+            GlobalTransform::default(),
+            WorldPosition(world_position),
+            Name::new(format!("Tile {},{}, zoom {}", tile.x, tile.y, tile.z)),
+            memory,
+            // The grid winds clockwise seen from above, so rays from above would be culled
+            RayCastBackfaces,
+        ))
+        .remove::<(MorphedTile, Aabb)>();
 }
 
-// Create a fallback tile mesh for when the image can't be loaded
+// Give a tile entity a fallback mesh for when the image can't be loaded
 pub fn create_fallback_tile_mesh(
-    commands: &mut Commands,
+    entity: &mut EntityCommands,
     meshes: &mut Assets<Mesh>,
     materials: &mut Assets<StandardMaterial>,
     tile: &OSMTile,
-    current_time: f32,
     is_background: bool,
-) -> Entity {
-    // Create a custom mesh for a horizontal tile (XZ plane with Y as up)
-    let mut mesh = Mesh::new(
-        bevy::render::mesh::PrimitiveTopology::TriangleList,
-        RenderAssetUsages::default(),
-    );
-
-    // Match the new vertex positioning from create_tile_mesh
-    let vertices: [[f32; 8]; 4] = [
-        // positions (XYZ)               normals (XYZ)       UV coords
-        [0.0, 0.0, 0.0,    0.0, 1.0, 0.0,          0.0, 0.0], // northwest corner
-        [1.0, 0.0, 0.0,    0.0, 1.0, 0.0,          1.0, 0.0], // northeast corner
-        [1.0, 0.0, 1.0,    0.0, 1.0, 0.0,          1.0, 1.0], // southeast corner
-        [0.0, 0.0, 1.0,    0.0, 1.0, 0.0,          0.0, 1.0], // southwest corner
-    ];
-
-    let positions: Vec<[f32; 3]> = vertices.iter().map(|v| [v[0], v[1], v[2]]).collect();
-    let normals: Vec<[f32; 3]> = vertices.iter().map(|v| [v[3], v[4], v[5]]).collect();
-    let uvs: Vec<[f32; 2]> = vertices.iter().map(|v| [v[6], v[7]]).collect();
-    let indices = vec![0, 1, 2, 0, 2, 3]; // triangulate the quad
-
-    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
-    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
-    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
-    mesh.insert_indices(bevy::render::mesh::Indices::U32(indices));
+    floating_origin: &FloatingOrigin,
+) {
+    // Match the subdivided grid from create_tile_mesh
+    let mesh = create_tile_grid_mesh(TILE_MESH_SUBDIVISIONS);
+    let memory = TileMemory {
+        texture_bytes: 0,
+        mesh_bytes: mesh_bytes(&mesh),
+    };
 
     // Create a checkered pattern material to indicate missing tile
     let material = materials.add(StandardMaterial {
@@ -175,9 +189,9 @@ pub fn create_fallback_tile_mesh(
         ..default()
     });
 
-    // Calculate zoom level difference to determine scaling and positioning
-    let zoom_difference = tile.z as i32 - DEFAULT_ZOOM_LEVEL as i32;
-    let scale_factor = 2_f32.powi(-zoom_difference); // Inverse because higher zoom = smaller tile
+    // Calculate tile size to determine scaling and positioning
+    // Tiles are WORLD_SIZE / 2^zoom metres wide in our Web Mercator world
+    let scale_factor = tile_size_world(tile.z);
 
     // Create mesh and material handles
     let mesh_handle = meshes.add(mesh);
@@ -188,41 +202,37 @@ pub fn create_fallback_tile_mesh(
     // Use a small offset that won't be noticeable visually but will fix z-fighting
     let y_offset = if is_background {
         // Background tiles should always be below focus tiles
-        -0.01
+        -0.5
     } else {
         // Higher zoom levels should be on top
-        0.005 * (tile.z as f32 / 19.0) // Normalize to a small range
+        0.25 * (tile.z as f32 / 19.0) // Normalize to a small range (in metres)
     };
 
-    // Create transform
-    let transform = Transform::from_xyz(
-        tile.x as f32 * scale_factor,     // Scale X coordinate
-        y_offset,                        // Small Y offset based on zoom to prevent z-fighting
-        tile.y as f32 * scale_factor      // Scale Z coordinate
-    )
-    .with_scale(Vec3::new(scale_factor, 1.0, scale_factor)); // Scale the tile size
-
-    // Spawn entity with everything at once
-    let mut entity_builder = commands.spawn((
-        Mesh3d(mesh_handle),
-        MeshMaterial3d(material_handle),
-        transform,
-        GlobalTransform::default(),
-        Name::new(format!("Fallback Tile {},{}, zoom {}", tile.x, tile.y, tile.z)),
-        TileCoords {
-            x: tile.x,
-            y: tile.y,
-            zoom: tile.z,
-            last_used: current_time,
-        },
-    ));
-    
-    // Add background component if this is a background tile
-    if is_background {
-        entity_builder.insert(BackgroundTile);
-    }
-    
-    entity_builder.id()
+    // Keep the tile corner in f64 world space and render it relative to the floating origin
+    let (corner_x, corner_z) = tile_to_world(tile.x, tile.y, tile.z);
+    let world_position = DVec3::new(
+        corner_x,                        // Northwest corner X
+        y_offset as f64,                 // Small Y offset based on zoom to prevent z-fighting
+        corner_z                         // Northwest corner Z
+    );
+    let transform = Transform::from_translation(floating_origin.world_to_render(world_position))
+        .with_scale(Vec3::new(scale_factor as f32, 1.0, scale_factor as f32)); // Scale the tile size
+
+    // Add everything to the tile entity at once, replacing an earlier fallback mesh
+    // A morphed or bounded earlier mesh no longer applies, so drop that state too
+    entity
+        .insert((
+            Mesh3d(mesh_handle),
+            MeshMaterial3d(material_handle),
+            transform,
+            GlobalTransform::default(),
+            WorldPosition(world_position),
+            Name::new(format!("Fallback Tile {},{}, zoom {}", tile.x, tile.y, tile.z)),
+            memory,
+            // The grid winds clockwise seen from above, so rays from above would be culled
+            RayCastBackfaces,
+        ))
+        .remove::<(MorphedTile, Aabb)>();
 }
 
 // Create a material with special highlighting for persistent islands
@@ -0,0 +1,493 @@
+use bevy::prelude::*;
+use bevy::image::CompressedImageFormats;
+use bevy::render::render_asset::RenderAssetUsages;
+use image::{imageops, DynamicImage, RgbaImage};
+use crate::osm::tile::OSMTile;
+use crate::osm::cache::decode_tile_image;
+use crate::resources::MapLayer;
+
+// Size of an empty tile, when no layer has tiles at a zoom level
+const TILE_SIZE: u32 = 256;
+
+// Convert a decoded tile image into uncompressed RGBA8 texture data
+pub fn create_tile_texture(image: DynamicImage) -> Image {
+    // OSM tiles have (0,0) at the top-left, which already matches the UV coordinates
+    let rgba_image = DynamicImage::ImageRgba8(image.into_rgba8());
+    // Only the GPU needs the pixels, so don't keep a copy in main memory
+    Image::from_dynamic(rgba_image, true, RenderAssetUsages::RENDER_WORLD)
+}
+
+// Turn the encoded tiles of all layers into one GPU-ready texture
+// This is the expensive part of applying a tile, so the loader tasks run it off the main thread.
+// Layers come bottom first; ones that fail to decode are left out, and the error of the
+// last of them is returned with the texture so the tile can be retried. With the
+// `compressed-textures` feature, a lone KTX2 tile is used as it is and the result is
+// compressed to BC1, or else ASTC 4x4, when the GPU supports it; everything else ends up as RGBA8.
+#[cfg_attr(not(feature = "compressed-textures"), allow(unused_variables))]
+pub fn decode_tile_texture(
+    tile: &OSMTile,
+    layers: &[(MapLayer, Vec<u8>)],
+    formats: CompressedImageFormats,
+) -> Result<(Image, Option<anyhow::Error>), anyhow::Error> {
+    #[cfg(feature = "compressed-textures")]
+    if let [(layer, bytes)] = layers {
+        if layer.opacity >= 1.0 && compressed::is_ktx2(bytes) {
+            return compressed::load_ktx2_texture(tile, layer, bytes, formats).map(|texture| (texture, None));
+        }
+    }
+
+    let mut images = Vec::new();
+    let mut last_error = None;
+    for (layer, bytes) in layers {
+        match decode_tile_image(tile, layer, bytes) {
+            Ok(image) => images.push((layer.opacity, image)),
+            Err(e) => {
+                warn!("Leaving out {} layer of tile {},{},{}: {}", layer.name, tile.x, tile.y, tile.z, e);
+                last_error = Some(e);
+            }
+        }
+    }
+
+    let image = if images.is_empty() {
+        if let Some(e) = last_error.take() {
+            return Err(e);
+        }
+        // Nothing to draw at this zoom level - a transparent tile
+        DynamicImage::ImageRgba8(RgbaImage::new(TILE_SIZE, TILE_SIZE))
+    } else if images.len() == 1 && images[0].0 >= 1.0 {
+        // A single opaque layer needs no blending
+        images.remove(0).1
+    } else {
+        DynamicImage::ImageRgba8(composite_layers(&images))
+    };
+
+    #[cfg(feature = "compressed-textures")]
+    if let Some(texture) = compressed::create_compressed_texture(&image, formats) {
+        return Ok((texture, last_error));
+    }
+
+    Ok((create_tile_texture(image), last_error))
+}
+
+// Blend layer images bottom to top with the "over" operator, scaled by each layer's opacity
+// Layers with a different tile size are resized to the size of the bottom one
+fn composite_layers(images: &[(f32, DynamicImage)]) -> RgbaImage {
+    let (width, height) = images.first().map(|(_, image)| (image.width(), image.height())).unwrap_or((TILE_SIZE, TILE_SIZE));
+    let mut canvas = RgbaImage::new(width, height);
+
+    for (opacity, image) in images {
+        let mut layer = image.to_rgba8();
+        if layer.dimensions() != (width, height) {
+            layer = imageops::resize(&layer, width, height, imageops::FilterType::Triangle);
+        }
+        for (target, source) in canvas.pixels_mut().zip(layer.pixels()) {
+            let alpha = source[3] as f32 / 255.0 * opacity;
+            let below = target[3] as f32 / 255.0 * (1.0 - alpha);
+            let total = alpha + below;
+            if total <= 0.0 {
+                continue;
+            }
+            for c in 0..3 {
+                target[c] = ((source[c] as f32 * alpha + target[c] as f32 * below) / total).round() as u8;
+            }
+            target[3] = (total * 255.0).round() as u8;
+        }
+    }
+
+    canvas
+}
+
+#[cfg(feature = "compressed-textures")]
+mod compressed {
+    use bevy::prelude::*;
+    use bevy::image::{CompressedImageFormats, ImageSampler, ImageType};
+    use bevy::render::render_asset::RenderAssetUsages;
+    use bevy::render::render_resource::{AstcBlock, AstcChannel, Extent3d, TextureDimension, TextureFormat};
+    use image::{DynamicImage, RgbaImage};
+    use std::fs;
+    use crate::osm::tile::OSMTile;
+    use crate::resources::MapLayer;
+
+    // File identifier at the start of every KTX2 file
+    const KTX2_MAGIC: [u8; 12] = [0xAB, b'K', b'T', b'X', b' ', b'2', b'0', 0xBB, b'\r', b'\n', 0x1A, b'\n'];
+    // Offset of the vkFormat field in the KTX2 header
+    const KTX2_VK_FORMAT_OFFSET: usize = 12;
+    // Both formats store 4x4 pixel blocks, BC1 in 8 bytes and ASTC in 16
+    const BLOCK_SIZE: u32 = 4;
+    const BC1_BLOCK_BYTES: usize = 8;
+    const ASTC_BLOCK_BYTES: usize = 16;
+
+    pub fn is_ktx2(bytes: &[u8]) -> bool {
+        bytes.starts_with(&KTX2_MAGIC)
+    }
+
+    // Basis Universal payloads (ETC1S and UASTC) leave vkFormat undefined, as they are only
+    // turned into a GPU format when transcoded
+    fn is_basis_universal(bytes: &[u8]) -> bool {
+        bytes
+            .get(KTX2_VK_FORMAT_OFFSET..KTX2_VK_FORMAT_OFFSET + 4)
+            .is_some_and(|format| format == [0; 4])
+    }
+
+    // Load a tile that a source already provides as a KTX2 texture in a GPU format
+    // Basis Universal payloads are rejected: transcoding them needs bevy's `basis-universal`
+    // feature, which this crate leaves out to avoid building the C++ transcoder
+    pub fn load_ktx2_texture(
+        tile: &OSMTile,
+        layer: &MapLayer,
+        bytes: &[u8],
+        formats: CompressedImageFormats,
+    ) -> Result<Image, anyhow::Error> {
+        if is_basis_universal(bytes) {
+            return Err(anyhow::anyhow!(
+                "{} layer serves Basis Universal KTX2 tiles, which need transcoding this build doesn't support",
+                layer.name
+            ));
+        }
+
+        Image::from_buffer(
+            bytes,
+            ImageType::Extension("ktx2"),
+            formats,
+            true,
+            ImageSampler::Default,
+            RenderAssetUsages::RENDER_WORLD,
+        )
+        .map_err(|e| {
+            // Remove the unusable cache file so the next attempt downloads the tile again
+            let _ = fs::remove_file(tile.get_cache_path(layer));
+            e.into()
+        })
+    }
+
+    // Compress an opaque tile to the best block format the GPU supports: BC1 on desktop GPUs,
+    // an eighth of the size of RGBA8, or ASTC 4x4 on mobile ones, a quarter of it
+    // Returns None when neither is supported, or for images the encoders can't hold:
+    // translucent ones, or sizes that aren't whole blocks
+    pub fn create_compressed_texture(image: &DynamicImage, formats: CompressedImageFormats) -> Option<Image> {
+        if formats.contains(CompressedImageFormats::BC) {
+            compress_blocks(image, TextureFormat::Bc1RgbaUnormSrgb, encode_block)
+        } else if formats.contains(CompressedImageFormats::ASTC_LDR) {
+            let format = TextureFormat::Astc {
+                block: AstcBlock::B4x4,
+                channel: AstcChannel::UnormSrgb,
+            };
+            compress_blocks(image, format, encode_astc_block)
+        } else {
+            None
+        }
+    }
+
+    fn compress_blocks<const N: usize>(
+        image: &DynamicImage,
+        format: TextureFormat,
+        encode: fn(&RgbaImage, u32, u32) -> [u8; N],
+    ) -> Option<Image> {
+        let rgba = image.to_rgba8();
+        let (width, height) = rgba.dimensions();
+        if width % BLOCK_SIZE != 0 || height % BLOCK_SIZE != 0 || rgba.pixels().any(|pixel| pixel[3] < 255) {
+            return None;
+        }
+
+        let mut data = Vec::with_capacity((width / BLOCK_SIZE * height / BLOCK_SIZE) as usize * N);
+        for block_y in (0..height).step_by(BLOCK_SIZE as usize) {
+            for block_x in (0..width).step_by(BLOCK_SIZE as usize) {
+                data.extend_from_slice(&encode(&rgba, block_x, block_y));
+            }
+        }
+
+        let mut texture = Image {
+            data,
+            asset_usage: RenderAssetUsages::RENDER_WORLD,
+            ..default()
+        };
+        texture.texture_descriptor.size = Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        texture.texture_descriptor.dimension = TextureDimension::D2;
+        texture.texture_descriptor.format = format;
+        Some(texture)
+    }
+
+    // The RGB colours of a 4x4 block, row by row
+    fn block_pixels(image: &RgbaImage, block_x: u32, block_y: u32) -> [[f32; 3]; 16] {
+        let mut pixels = [[0.0f32; 3]; 16];
+        for (i, pixel) in pixels.iter_mut().enumerate() {
+            let [r, g, b, _] = image.get_pixel(block_x + i as u32 % BLOCK_SIZE, block_y + i as u32 / BLOCK_SIZE).0;
+            *pixel = [r as f32, g as f32, b as f32];
+        }
+        pixels
+    }
+
+    // Nearest of the palette colours to a pixel
+    fn nearest_index(pixel: &[f32; 3], palette: &[[f32; 3]]) -> usize {
+        (0..palette.len())
+            .min_by(|&a, &b| distance_squared(pixel, &palette[a]).total_cmp(&distance_squared(pixel, &palette[b])))
+            .unwrap_or(0)
+    }
+
+    // End points of the line through colour space that a block's colours are fitted to
+    fn end_points(pixels: &[[f32; 3]; 16]) -> ([f32; 3], [f32; 3]) {
+        // End points on the diagonal of the colour bounding box that follows the colours
+        // They are not pulled in: map tiles are mostly flat fills and hard edges, whose
+        // colours must stay exact at the ends of the palette
+        let mut min = [255.0f32; 3];
+        let mut max = [0.0f32; 3];
+        for pixel in pixels {
+            for c in 0..3 {
+                min[c] = min[c].min(pixel[c]);
+                max[c] = max[c].max(pixel[c]);
+            }
+        }
+        let widest = (0..3).max_by(|&a, &b| (max[a] - min[a]).total_cmp(&(max[b] - min[b]))).unwrap_or(0);
+        let mean: Vec<f32> = (0..3).map(|c| pixels.iter().map(|p| p[c]).sum::<f32>() / 16.0).collect();
+        let mut start = max;
+        let mut end = min;
+        for c in 0..3 {
+            // Channels that fall while the widest one rises run the other way along the diagonal
+            let covariance: f32 = pixels.iter().map(|p| (p[c] - mean[c]) * (p[widest] - mean[widest])).sum();
+            if covariance < 0.0 {
+                std::mem::swap(&mut start[c], &mut end[c]);
+            }
+        }
+        (start, end)
+    }
+
+    // Encode one 4x4 block in BC1: two RGB565 end points and a 2 bit palette index per pixel
+    fn encode_block(image: &RgbaImage, block_x: u32, block_y: u32) -> [u8; BC1_BLOCK_BYTES] {
+        let pixels = block_pixels(image, block_x, block_y);
+        let (start, end) = end_points(&pixels);
+
+        let mut color0 = to_rgb565(start);
+        let mut color1 = to_rgb565(end);
+        // The first end point must be the larger one for the four colour mode
+        if color0 < color1 {
+            std::mem::swap(&mut color0, &mut color1);
+        }
+
+        let mut indices = 0u32;
+        if color0 != color1 {
+            let (p0, p1) = (from_rgb565(color0), from_rgb565(color1));
+            let palette = [
+                p0,
+                p1,
+                [0, 1, 2].map(|c| (2.0 * p0[c] + p1[c]) / 3.0),
+                [0, 1, 2].map(|c| (p0[c] + 2.0 * p1[c]) / 3.0),
+            ];
+            for (i, pixel) in pixels.iter().enumerate() {
+                indices |= (nearest_index(pixel, &palette) as u32) << (2 * i);
+            }
+        }
+
+        let mut block = [0u8; BC1_BLOCK_BYTES];
+        block[0..2].copy_from_slice(&color0.to_le_bytes());
+        block[2..4].copy_from_slice(&color1.to_le_bytes());
+        block[4..8].copy_from_slice(&indices.to_le_bytes());
+        block
+    }
+
+    // Encode one 4x4 block in ASTC with the simplest layout that fits: one partition, a 4x4
+    // grid of 2 bit weights (block mode 0x042) and an RGB end point pair at 8 bits per channel
+    // (colour endpoint mode 8). The weights are stored bit-reversed from the top of the block.
+    fn encode_astc_block(image: &RgbaImage, block_x: u32, block_y: u32) -> [u8; ASTC_BLOCK_BYTES] {
+        const BLOCK_MODE: u128 = 0x042;
+        const ENDPOINT_MODE_RGB: u128 = 8;
+        // Weights 0..=3 stand for these fractions of 64 along the line between the end points
+        const WEIGHTS: [f32; 4] = [0.0, 21.0, 43.0, 64.0];
+
+        let pixels = block_pixels(image, block_x, block_y);
+        let (start, end) = end_points(&pixels);
+        let mut e0 = start.map(|c| c.clamp(0.0, 255.0).round() as u8);
+        let mut e1 = end.map(|c| c.clamp(0.0, 255.0).round() as u8);
+        // Decoders swap and blue-contract the end points when the second is the darker one,
+        // so put the darker one first
+        let sum = |e: [u8; 3]| e.iter().map(|&c| c as u32).sum::<u32>();
+        if sum(e1) < sum(e0) {
+            std::mem::swap(&mut e0, &mut e1);
+        }
+
+        let (p0, p1) = (e0.map(|c| c as f32), e1.map(|c| c as f32));
+        let palette = WEIGHTS.map(|w| [0, 1, 2].map(|c| (p0[c] * (64.0 - w) + p1[c] * w) / 64.0));
+
+        let mut block = BLOCK_MODE | ENDPOINT_MODE_RGB << 13;
+        // End point values follow the 17 configuration bits, as r0 r1 g0 g1 b0 b1
+        for (i, value) in [e0[0], e1[0], e0[1], e1[1], e0[2], e1[2]].into_iter().enumerate() {
+            block |= (value as u128) << (17 + 8 * i);
+        }
+        for (i, pixel) in pixels.iter().enumerate() {
+            let weight = nearest_index(pixel, &palette) as u128;
+            block |= (weight & 1) << (127 - 2 * i);
+            block |= (weight >> 1) << (126 - 2 * i);
+        }
+        block.to_le_bytes()
+    }
+
+    fn to_rgb565([r, g, b]: [f32; 3]) -> u16 {
+        let r = (r.clamp(0.0, 255.0) * 31.0 / 255.0).round() as u16;
+        let g = (g.clamp(0.0, 255.0) * 63.0 / 255.0).round() as u16;
+        let b = (b.clamp(0.0, 255.0) * 31.0 / 255.0).round() as u16;
+        (r << 11) | (g << 5) | b
+    }
+
+    fn from_rgb565(color: u16) -> [f32; 3] {
+        [
+            ((color >> 11) & 0x1F) as f32 * 255.0 / 31.0,
+            ((color >> 5) & 0x3F) as f32 * 255.0 / 63.0,
+            (color & 0x1F) as f32 * 255.0 / 31.0,
+        ]
+    }
+
+    fn distance_squared(a: &[f32; 3], b: &[f32; 3]) -> f32 {
+        (0..3).map(|c| (a[c] - b[c]) * (a[c] - b[c])).sum()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use image::Rgba;
+
+        // Largest error per channel of a 5 or 6 bit channel after rounding
+        const RGB565_TOLERANCE: f32 = 8.0;
+
+        fn decode_bc1(block: [u8; BC1_BLOCK_BYTES]) -> [[f32; 3]; 16] {
+            let color0 = u16::from_le_bytes([block[0], block[1]]);
+            let color1 = u16::from_le_bytes([block[2], block[3]]);
+            let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+            let (p0, p1) = (from_rgb565(color0), from_rgb565(color1));
+            let palette = if color0 > color1 {
+                [
+                    p0,
+                    p1,
+                    [0, 1, 2].map(|c| (2.0 * p0[c] + p1[c]) / 3.0),
+                    [0, 1, 2].map(|c| (p0[c] + 2.0 * p1[c]) / 3.0),
+                ]
+            } else {
+                [p0, p1, [0, 1, 2].map(|c| (p0[c] + p1[c]) / 2.0), [0.0; 3]]
+            };
+            std::array::from_fn(|i| palette[(indices >> (2 * i) & 3) as usize])
+        }
+
+        // Decodes only the layout encode_astc_block writes
+        fn decode_astc(block: [u8; ASTC_BLOCK_BYTES]) -> [[f32; 3]; 16] {
+            let block = u128::from_le_bytes(block);
+            assert_eq!(block & 0x7FF, 0x042, "block mode");
+            assert_eq!(block >> 11 & 0x3, 0, "partition count");
+            assert_eq!(block >> 13 & 0xF, 8, "colour endpoint mode");
+            let value = |i: usize| (block >> (17 + 8 * i) & 0xFF) as f32;
+            let e0 = [value(0), value(2), value(4)];
+            let e1 = [value(1), value(3), value(5)];
+            assert!(e1.iter().sum::<f32>() >= e0.iter().sum::<f32>(), "end points would be swapped");
+            std::array::from_fn(|i| {
+                let weight = (block >> (127 - 2 * i) & 1) | (block >> (126 - 2 * i) & 1) << 1;
+                let w = [0.0, 21.0, 43.0, 64.0][weight as usize];
+                [0, 1, 2].map(|c| (e0[c] * (64.0 - w) + e1[c] * w) / 64.0)
+            })
+        }
+
+        fn block_image(color: impl Fn(u32, u32) -> [u8; 3]) -> RgbaImage {
+            RgbaImage::from_fn(BLOCK_SIZE, BLOCK_SIZE, |x, y| {
+                let [r, g, b] = color(x, y);
+                Rgba([r, g, b, 255])
+            })
+        }
+
+        fn assert_close(decoded: [[f32; 3]; 16], image: &RgbaImage, tolerance: f32) {
+            for (i, pixel) in decoded.iter().enumerate() {
+                let expected = image.get_pixel(i as u32 % BLOCK_SIZE, i as u32 / BLOCK_SIZE).0;
+                for c in 0..3 {
+                    assert!(
+                        (pixel[c] - expected[c] as f32).abs() <= tolerance,
+                        "pixel {} channel {}: {} instead of {}", i, c, pixel[c], expected[c]
+                    );
+                }
+            }
+        }
+
+        fn two_colours() -> RgbaImage {
+            block_image(|x, _| if x < 2 { [230, 40, 10] } else { [20, 90, 250] })
+        }
+
+        #[test]
+        fn bc1_keeps_a_solid_block() {
+            let image = block_image(|_, _| [200, 100, 50]);
+            assert_close(decode_bc1(encode_block(&image, 0, 0)), &image, RGB565_TOLERANCE);
+        }
+
+        #[test]
+        fn bc1_keeps_a_two_colour_block() {
+            let image = two_colours();
+            assert_close(decode_bc1(encode_block(&image, 0, 0)), &image, RGB565_TOLERANCE);
+        }
+
+        #[test]
+        fn astc_keeps_a_solid_block() {
+            let image = block_image(|_, _| [200, 100, 50]);
+            assert_close(decode_astc(encode_astc_block(&image, 0, 0)), &image, 1.0);
+        }
+
+        #[test]
+        fn astc_keeps_a_two_colour_block() {
+            let image = two_colours();
+            assert_close(decode_astc(encode_astc_block(&image, 0, 0)), &image, 1.0);
+        }
+
+        #[test]
+        fn basis_universal_payloads_are_recognised() {
+            let mut header = KTX2_MAGIC.to_vec();
+            header.extend_from_slice(&0u32.to_le_bytes());
+            assert!(is_basis_universal(&header));
+
+            // VK_FORMAT_BC1_RGB_SRGB_BLOCK
+            header[KTX2_VK_FORMAT_OFFSET..].copy_from_slice(&132u32.to_le_bytes());
+            assert!(!is_basis_universal(&header));
+            assert!(!is_basis_universal(&KTX2_MAGIC));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    fn filled(size: u32, color: [u8; 4]) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(size, size, Rgba(color)))
+    }
+
+    #[test]
+    fn an_opaque_layer_covers_the_ones_below() {
+        let canvas = composite_layers(&[(1.0, filled(4, [10, 20, 30, 255])), (1.0, filled(4, [200, 100, 0, 255]))]);
+        assert!(canvas.pixels().all(|pixel| pixel.0 == [200, 100, 0, 255]));
+    }
+
+    #[test]
+    fn layer_opacity_blends_with_the_layer_below() {
+        let canvas = composite_layers(&[(1.0, filled(4, [0, 0, 0, 255])), (0.5, filled(4, [200, 100, 50, 255]))]);
+        assert!(canvas.pixels().all(|pixel| pixel.0 == [100, 50, 25, 255]));
+    }
+
+    #[test]
+    fn transparent_pixels_show_the_layer_below() {
+        let mut overlay = RgbaImage::from_pixel(4, 4, Rgba([0, 0, 0, 0]));
+        overlay.put_pixel(1, 2, Rgba([255, 0, 0, 255]));
+        let canvas = composite_layers(&[(1.0, filled(4, [0, 0, 255, 255])), (1.0, DynamicImage::ImageRgba8(overlay))]);
+
+        assert_eq!(canvas.get_pixel(1, 2).0, [255, 0, 0, 255]);
+        assert_eq!(canvas.get_pixel(0, 0).0, [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn a_translucent_stack_stays_translucent() {
+        let canvas = composite_layers(&[(0.5, filled(4, [0, 0, 255, 255]))]);
+        assert!(canvas.pixels().all(|pixel| pixel.0 == [0, 0, 255, 128]));
+    }
+
+    #[test]
+    fn layers_are_resized_to_the_bottom_layer() {
+        let canvas = composite_layers(&[(1.0, filled(8, [0, 0, 0, 255])), (1.0, filled(4, [9, 9, 9, 255]))]);
+        assert_eq!(canvas.dimensions(), (8, 8));
+        assert!(canvas.pixels().all(|pixel| pixel.0 == [9, 9, 9, 255]));
+    }
+}
@@ -1,6 +1,8 @@
 use bevy::prelude::*;
 use std::path::{Path, PathBuf};
 use std::fs;
+use crate::utils::coordinate_conversion::wrap_tile_x;
+use crate::resources::MapLayer;
 
 // Constants for the OSM tile system
 #[allow(dead_code)]
@@ -8,38 +10,45 @@ const TILE_SIZE: usize = 256; // Standard OSM tile size in pixels
 const CACHE_DIR: &str = "tile_cache"; // Directory for caching tiles
 
 pub struct OSMTile {
-    pub x: u32,
+    pub x: i32, // Unwrapped column - may point into a neighbouring copy of the world
     pub y: u32,
     pub z: u32,
 }
 
 impl OSMTile {
-    pub fn new(x: u32, y: u32, z: u32) -> Self {
+    pub fn new(x: i32, y: u32, z: u32) -> Self {
         Self { x, y, z }
     }
 
-    pub fn get_url(&self) -> String {
-        // Use the standard OSM tile server
-        // The URL format is zoom/x/y where:
-        // - x increases from west to east (0 to 2^zoom-1)
-        // - y increases from north to south (0 to 2^zoom-1)
-        format!(
-            "https://a.tile.openstreetmap.org/{}/{}/{}.png",
-            self.z, self.x, self.y
-        )
+    // Column wrapped into 0..2^zoom, used for requests and cache keys
+    pub fn wrapped_x(&self) -> u32 {
+        wrap_tile_x(self.x, self.z)
     }
 
-    // Get cache file path for this tile
-    pub fn get_cache_path(&self) -> PathBuf {
+    // URL of this tile in a layer's tile source
+    // The template uses the usual zoom/x/y placeholders where:
+    // - x increases from west to east (0 to 2^zoom-1)
+    // - y increases from north to south (0 to 2^zoom-1)
+    pub fn get_url(&self, layer: &MapLayer) -> String {
+        layer
+            .url_template
+            .replace("{z}", &self.z.to_string())
+            .replace("{x}", &self.wrapped_x().to_string())
+            .replace("{y}", &self.y.to_string())
+    }
+
+    // Get cache file path for this tile in a layer, named after the file type the layer serves
+    pub fn get_cache_path(&self, layer: &MapLayer) -> PathBuf {
         let cache_path = Path::new(CACHE_DIR)
+            .join(&layer.name)
             .join(self.z.to_string())
-            .join(self.x.to_string());
+            .join(self.wrapped_x().to_string());
 
         fs::create_dir_all(&cache_path).unwrap_or_else(|e| {
             warn!("Failed to create cache directory: {}", e);
         });
 
-        cache_path.join(format!("{}.png", self.y))
+        cache_path.join(format!("{}.{}", self.y, layer.file_extension()))
     }
 }
 
@@ -0,0 +1,27 @@
+use bevy::prelude::*;
+use crate::systems::bookmarks::{load_bookmarks, bookmark_actions, import_dropped_bookmarks, save_bookmarks};
+use crate::systems::ui::{setup_bookmark_panel, update_bookmark_panel, bookmark_button_clicks};
+use crate::systems::fly_to::start_fly_to;
+
+/// Plugin for saving, sharing and flying to camera bookmarks
+pub struct BookmarksPlugin;
+
+impl Plugin for BookmarksPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Startup, (load_bookmarks, setup_bookmark_panel))
+            .add_systems(Update, (
+                bookmark_actions.before(start_fly_to),
+                bookmark_button_clicks.before(start_fly_to),
+                import_dropped_bookmarks,
+                save_bookmarks
+                    .after(bookmark_actions)
+                    .after(bookmark_button_clicks)
+                    .after(import_dropped_bookmarks),
+                update_bookmark_panel
+                    .after(bookmark_actions)
+                    .after(bookmark_button_clicks)
+                    .after(import_dropped_bookmarks),
+            ));
+    }
+}
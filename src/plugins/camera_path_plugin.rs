@@ -0,0 +1,30 @@
+use bevy::prelude::*;
+use crate::systems::camera_path::{load_camera_path, camera_path_actions, edit_camera_path, record_camera_path, play_camera_path};
+use crate::systems::camera::mouse_look_system;
+use crate::systems::origin::update_floating_origin;
+
+/// Plugin for recording and replaying camera paths
+pub struct CameraPathPlugin;
+
+impl Plugin for CameraPathPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Startup, load_camera_path)
+            .add_systems(Update, (
+                camera_path_actions,
+                // Moves the camera onto selected keyframes, so before the origin follows it
+                edit_camera_path
+                    .after(camera_path_actions)
+                    .after(mouse_look_system)
+                    .before(update_floating_origin),
+                play_camera_path
+                    .after(camera_path_actions)
+                    .after(mouse_look_system)
+                    .before(update_floating_origin),
+                // Record the pose the frame ends with, after every controller has moved the camera
+                record_camera_path
+                    .after(camera_path_actions)
+                    .after(update_floating_origin),
+            ));
+    }
+}
@@ -1,8 +1,14 @@
 use bevy::prelude::*;
+use crate::resources::{CameraMode, WalkState, FlyToState};
+use crate::events::{FlyToRequested, FlyToArrived};
 use crate::systems::{
     camera::{mouse_look_system, camera_movement},
+    map_camera::{toggle_camera_mode, map_camera_movement},
+    fly_to::{fly_home, fly_to_at_launch, start_fly_to, update_fly_to, log_fly_to_arrivals},
     window::{grab_mouse, toggle_cursor_grab},
     debug::{debug_info, toggle_debug_mode},
+    origin::update_floating_origin,
+    setup::setup,
 };
 
 /// Plugin for camera movement and control
@@ -11,10 +17,22 @@ pub struct CameraPlugin;
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
         app
-            .add_systems(Startup, grab_mouse)
+            .insert_resource(CameraMode::default())
+            .insert_resource(WalkState::default())
+            .insert_resource(FlyToState::default())
+            .add_event::<FlyToRequested>()
+            .add_event::<FlyToArrived>()
+            .add_systems(Startup, (grab_mouse, fly_to_at_launch.after(setup)))
             .add_systems(Update, (
                 mouse_look_system,
-                camera_movement,
+                toggle_camera_mode,
+                camera_movement.after(toggle_camera_mode),
+                map_camera_movement.after(mouse_look_system).after(toggle_camera_mode),
+                fly_home,
+                start_fly_to.after(fly_home),
+                update_fly_to.after(start_fly_to).after(mouse_look_system),
+                log_fly_to_arrivals.after(update_fly_to),
+                update_floating_origin.after(camera_movement).after(map_camera_movement).after(update_fly_to),
                 toggle_cursor_grab,
                 debug_info,
                 toggle_debug_mode,
@@ -1,6 +1,10 @@
 use bevy::prelude::*;
-use crate::systems::setup::{setup, init_resources};
-use crate::resources::{MouseLookState, DebugSettings};
+use bevy::input::InputSystem;
+use crate::systems::setup::{setup, init_resources, detect_texture_formats};
+use crate::systems::session::{restore_session, save_session_on_exit};
+use crate::systems::input::update_action_state;
+use crate::resources::{MouseLookState, DebugSettings, FloatingOrigin, TileRegistry, ActionState};
+use crate::utils::config::load_launch_options;
 
 /// Core plugin that handles the basic app setup
 pub struct CorePlugin;
@@ -9,12 +13,29 @@ impl Plugin for CorePlugin {
     fn build(&self, app: &mut App) {
         // Initialize resources
         let (osm_data, tokio_runtime) = init_resources();
+        let launch_options = load_launch_options();
         
         app
             .insert_resource(osm_data)
+            .insert_resource(TileRegistry::default())
             .insert_resource(tokio_runtime)
             .insert_resource(MouseLookState::default())
             .insert_resource(DebugSettings::default())
-            .add_systems(Startup, setup);
+            .insert_resource(FloatingOrigin::default())
+            .insert_resource(launch_options.start_view)
+            .insert_resource(launch_options.session_settings)
+            .insert_resource(launch_options.bookmarks)
+            .insert_resource(launch_options.camera_path)
+            .insert_resource(launch_options.camera_path_player)
+            .insert_resource(launch_options.track)
+            .insert_resource(launch_options.track_playback)
+            .insert_resource(launch_options.position_feed)
+            .insert_resource(launch_options.input_bindings)
+            .insert_resource(launch_options.control_settings)
+            .insert_resource(ActionState::default())
+            .add_systems(Startup, (restore_session, setup.after(restore_session), detect_texture_formats))
+            // Actions are resolved once per frame, before any system reads them
+            .add_systems(PreUpdate, update_action_state.after(InputSystem))
+            .add_systems(Last, save_session_on_exit);
     }
 } 
\ No newline at end of file
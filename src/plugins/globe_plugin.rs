@@ -0,0 +1,29 @@
+use bevy::prelude::*;
+use crate::resources::{GlobeSettings, GlobeMorph, OrbitCamera};
+use crate::systems::globe::{toggle_globe_mode, update_globe_morph, orbit_camera_movement, morph_tile_meshes};
+use crate::systems::camera::mouse_look_system;
+use crate::systems::origin::update_floating_origin;
+use crate::systems::tiles::apply_pending_tiles;
+
+/// Plugin for the globe rendering mode at low zoom levels
+pub struct GlobePlugin;
+
+impl Plugin for GlobePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(GlobeSettings::default())
+            .insert_resource(GlobeMorph::default())
+            .insert_resource(OrbitCamera::default())
+            .add_systems(Update, (
+                toggle_globe_mode,
+                orbit_camera_movement
+                    .after(mouse_look_system)
+                    .before(update_floating_origin),
+                update_globe_morph.after(update_floating_origin),
+                // Bend newly spawned tiles in the same frame
+                morph_tile_meshes
+                    .after(update_globe_morph)
+                    .after(apply_pending_tiles),
+            ));
+    }
+}
@@ -1,11 +1,26 @@
 use bevy::prelude::*;
-use crate::systems::interaction::interact_with_map;
+use crate::resources::MapInspection;
+use crate::events::CopyInspectionRequested;
+use crate::systems::interaction::{interact_with_map, inspection_actions, copy_inspection};
+use crate::systems::ui::{setup_inspect_popup, update_inspect_popup, inspect_popup_clicks};
+use crate::systems::origin::update_floating_origin;
 
 /// Plugin for map interaction
 pub struct InteractionPlugin;
 
 impl Plugin for InteractionPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, interact_with_map);
+        app
+            .insert_resource(MapInspection::default())
+            .add_event::<CopyInspectionRequested>()
+            .add_systems(Startup, setup_inspect_popup)
+            .add_systems(Update, (
+                // Pick before the floating origin moves, while it still matches the drawn frame
+                interact_with_map.before(update_floating_origin),
+                inspection_actions,
+                inspect_popup_clicks,
+                copy_inspection.after(inspection_actions).after(inspect_popup_clicks),
+                update_inspect_popup.after(interact_with_map).after(inspect_popup_clicks),
+            ));
     }
-} 
\ No newline at end of file
+}
@@ -3,6 +3,11 @@ pub mod tiles_plugin;
 pub mod camera_plugin;
 pub mod interaction_plugin;
 pub mod ui_plugin;
+pub mod globe_plugin;
+pub mod bookmarks_plugin;
+pub mod camera_path_plugin;
+pub mod track_plugin;
+pub mod position_feed_plugin;
 
 use bevy::prelude::*;
 use bevy::app::PluginGroupBuilder;
@@ -12,6 +17,11 @@ pub use tiles_plugin::TilesPlugin;
 pub use camera_plugin::CameraPlugin;
 pub use interaction_plugin::InteractionPlugin;
 pub use ui_plugin::UIPlugin;
+pub use globe_plugin::GlobePlugin;
+pub use bookmarks_plugin::BookmarksPlugin;
+pub use camera_path_plugin::CameraPathPlugin;
+pub use track_plugin::TrackPlugin;
+pub use position_feed_plugin::PositionFeedPlugin;
 
 /// Consolidated plugin struct that groups all application plugins
 pub struct AppPlugins;
@@ -24,5 +34,10 @@ impl PluginGroup for AppPlugins {
             .add(TilesPlugin)
             .add(InteractionPlugin)
             .add(UIPlugin)
+            .add(GlobePlugin)
+            .add(BookmarksPlugin)
+            .add(CameraPathPlugin)
+            .add(TrackPlugin)
+            .add(PositionFeedPlugin)
     }
 } 
\ No newline at end of file
@@ -0,0 +1,36 @@
+use bevy::prelude::*;
+use crate::resources::LivePosition;
+use crate::systems::position_feed::{
+    start_position_feed, apply_position_reports, smooth_live_position, position_feed_actions, follow_live_position,
+    draw_live_position,
+};
+use crate::systems::ui::{setup_live_position_text, update_live_position_text};
+use crate::systems::camera::mouse_look_system;
+use crate::systems::camera_path::play_camera_path;
+use crate::systems::track::follow_track_camera;
+use crate::systems::origin::update_floating_origin;
+
+/// Plugin for following a live device position from an NMEA or gpsd source
+pub struct PositionFeedPlugin;
+
+impl Plugin for PositionFeedPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(LivePosition::default())
+            .add_systems(Startup, (start_position_feed, setup_live_position_text))
+            .add_systems(Update, (
+                apply_position_reports,
+                smooth_live_position.after(apply_position_reports),
+                position_feed_actions,
+                follow_live_position
+                    .after(smooth_live_position)
+                    .after(position_feed_actions)
+                    .after(mouse_look_system)
+                    .after(play_camera_path)
+                    .after(follow_track_camera)
+                    .before(update_floating_origin),
+                draw_live_position.after(apply_position_reports).after(update_floating_origin),
+                update_live_position_text.after(smooth_live_position),
+            ));
+    }
+}
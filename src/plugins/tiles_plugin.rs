@@ -2,22 +2,64 @@ use bevy::prelude::*;
 use crate::systems::tiles::{
     process_tiles,
     apply_pending_tiles,
+    retry_failed_tiles,
     update_visible_tiles,
-    cleanup_old_tiles,
-    auto_detect_zoom_level,
+    enforce_tile_residency,
+    despawn_evicted_tiles,
+    update_zoom_level,
 };
+use crate::systems::origin::update_floating_origin;
+use crate::systems::debug::log_tile_events;
+use crate::systems::prefetch::{track_camera_motion, maintain_prefetch_queue, prefetch_tiles};
+use crate::systems::horizon::update_horizon_fog;
+use crate::systems::layers::{toggle_map_layers, reload_tiles_on_layer_change};
+use crate::systems::globe::update_globe_morph;
+use crate::resources::{TileSelectionSettings, PrefetchSettings, PrefetchQueue, CameraMotion, TileResidency, ZoomPolicy, HorizonSettings, TileUploadSettings, MapLayers};
+use crate::events::{TileRequested, TileLoaded, TileFailed, TileEvicted};
 
 /// Plugin for managing OSM tiles
 pub struct TilesPlugin;
 
 impl Plugin for TilesPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, (
-            process_tiles,
-            apply_pending_tiles,
-            update_visible_tiles,
-            cleanup_old_tiles,
-            auto_detect_zoom_level,
-        ));
+        app
+            .insert_resource(TileSelectionSettings::default())
+            .insert_resource(PrefetchSettings::default())
+            .insert_resource(PrefetchQueue::default())
+            .insert_resource(CameraMotion::default())
+            .insert_resource(TileResidency::default())
+            .insert_resource(ZoomPolicy::default())
+            .insert_resource(HorizonSettings::default())
+            .insert_resource(TileUploadSettings::default())
+            .insert_resource(MapLayers::default())
+            // Tile lifecycle events for overlays, stats and tests
+            .add_event::<TileRequested>()
+            .add_event::<TileLoaded>()
+            .add_event::<TileFailed>()
+            .add_event::<TileEvicted>()
+            .add_systems(Update, (
+                // Settle the base zoom level before anything selects tiles with it
+                update_zoom_level.after(update_floating_origin),
+                process_tiles.after(update_zoom_level),
+                track_camera_motion.after(update_floating_origin),
+                // Tiles for the current view are requested first
+                maintain_prefetch_queue.after(process_tiles),
+                prefetch_tiles.after(maintain_prefetch_queue).after(track_camera_motion),
+                // Spawn after any rebase so new tiles use this frame's origin
+                apply_pending_tiles.after(update_floating_origin),
+                retry_failed_tiles,
+                update_visible_tiles,
+                // Runs on this frame's visibility, so tiles in view are never evicted
+                enforce_tile_residency.after(update_visible_tiles),
+                despawn_evicted_tiles.after(enforce_tile_residency),
+                log_tile_events,
+                update_horizon_fog.after(update_globe_morph),
+                toggle_map_layers,
+                // Starts the new layer generation before anything else requests tiles this frame
+                reload_tiles_on_layer_change
+                    .after(toggle_map_layers)
+                    .before(process_tiles)
+                    .before(retry_failed_tiles),
+            ));
     }
-} 
\ No newline at end of file
+}
@@ -0,0 +1,30 @@
+use bevy::prelude::*;
+use crate::systems::track::{load_track, import_dropped_track, track_actions, advance_track_playback, follow_track_camera, draw_track};
+use crate::systems::ui::{setup_track_timeline, update_track_timeline, track_timeline_seek};
+use crate::systems::camera::mouse_look_system;
+use crate::systems::camera_path::play_camera_path;
+use crate::systems::origin::update_floating_origin;
+
+/// Plugin for showing GPX tracks and replaying them with a follow camera
+pub struct TrackPlugin;
+
+impl Plugin for TrackPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Startup, (load_track, setup_track_timeline))
+            .add_systems(Update, (
+                import_dropped_track,
+                track_actions.after(import_dropped_track),
+                track_timeline_seek.after(import_dropped_track),
+                advance_track_playback.after(track_actions).after(track_timeline_seek),
+                follow_track_camera
+                    .after(advance_track_playback)
+                    .after(mouse_look_system)
+                    .after(play_camera_path)
+                    .before(update_floating_origin),
+                // Draw with the floating origin of this frame, so the line sticks to the tiles
+                draw_track.after(advance_track_playback).after(update_floating_origin),
+                update_track_timeline.after(advance_track_playback),
+            ));
+    }
+}
@@ -1,6 +1,6 @@
 use bevy::prelude::*;
 use bevy::diagnostic::FrameTimeDiagnosticsPlugin;
-use crate::systems::ui::{setup_ui, update_zoom_level_text, update_tile_count_text, update_fps_counter};
+use crate::systems::ui::{setup_ui, update_zoom_level_text, update_tile_count_text, update_fps_counter, update_layers_text};
 
 /// Plugin for managing UI elements like text displays
 pub struct UIPlugin;
@@ -16,6 +16,7 @@ impl Plugin for UIPlugin {
                 update_zoom_level_text,
                 update_tile_count_text,
                 update_fps_counter,
+                update_layers_text,
             ));
     }
 } 
\ No newline at end of file
@@ -0,0 +1,83 @@
+use bevy::prelude::*;
+use std::path::PathBuf;
+use crate::resources::CameraPose;
+
+/// A named camera pose to come back to
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bookmark {
+    pub name: String,
+    pub pose: CameraPose,
+}
+
+/// The user's bookmarks, kept in a GeoJSON file so they can be shared
+#[derive(Resource, Debug)]
+pub struct Bookmarks {
+    pub bookmarks: Vec<Bookmark>,
+    /// Bookmark last flown to or saved, for stepping through the list
+    pub selected: Option<usize>,
+    pub path: PathBuf,
+    /// Where the export action writes a copy to share
+    pub export_path: PathBuf,
+}
+
+impl Default for Bookmarks {
+    fn default() -> Self {
+        Self {
+            bookmarks: Vec::new(),
+            selected: None,
+            path: PathBuf::from("bookmarks.geojson"),
+            export_path: PathBuf::from("bookmarks_export.geojson"),
+        }
+    }
+}
+
+impl Bookmarks {
+    /// Add a bookmark under a name that isn't taken yet, and select it
+    pub fn add(&mut self, pose: CameraPose) -> &Bookmark {
+        let name = (self.bookmarks.len() + 1..)
+            .map(|number| format!("Bookmark {}", number))
+            .find(|name| self.bookmarks.iter().all(|bookmark| &bookmark.name != name))
+            .unwrap_or_default();
+        self.bookmarks.push(Bookmark { name, pose });
+        self.selected = Some(self.bookmarks.len() - 1);
+        &self.bookmarks[self.bookmarks.len() - 1]
+    }
+
+    /// Add bookmarks from elsewhere, skipping ones already present; returns how many were new
+    pub fn merge(&mut self, bookmarks: Vec<Bookmark>) -> usize {
+        let before = self.bookmarks.len();
+        for bookmark in bookmarks {
+            if !self.bookmarks.contains(&bookmark) {
+                self.bookmarks.push(bookmark);
+            }
+        }
+        self.bookmarks.len() - before
+    }
+
+    /// Select the bookmark `offset` places from the selected one, wrapping around
+    pub fn step(&mut self, offset: isize) -> Option<&Bookmark> {
+        if self.bookmarks.is_empty() {
+            return None;
+        }
+        let count = self.bookmarks.len() as isize;
+        let index = match self.selected {
+            Some(selected) => (selected as isize + offset).rem_euclid(count),
+            // Nothing selected yet: forward starts at the first, backward at the last
+            None if offset > 0 => 0,
+            None => count - 1,
+        } as usize;
+        self.selected = Some(index);
+        self.bookmarks.get(index)
+    }
+
+    pub fn remove_selected(&mut self) -> Option<Bookmark> {
+        let index = self.selected.filter(|&index| index < self.bookmarks.len())?;
+        let removed = self.bookmarks.remove(index);
+        self.selected = if self.bookmarks.is_empty() {
+            None
+        } else {
+            Some(index.min(self.bookmarks.len() - 1))
+        };
+        Some(removed)
+    }
+}
@@ -0,0 +1,43 @@
+use bevy::prelude::*;
+use bevy::math::DVec3;
+
+// Time constant of the velocity smoothing, in seconds
+const MOTION_SMOOTHING: f32 = 0.25;
+
+/// Smoothed camera velocity, used to predict where the camera is heading
+#[derive(Resource, Default)]
+pub struct CameraMotion {
+    /// Linear velocity in world units per second
+    pub velocity: DVec3,
+    /// Rotation rate as a scaled axis, in radians per second
+    pub angular_velocity: Vec3,
+    previous: Option<(DVec3, Quat)>,
+}
+
+impl CameraMotion {
+    /// Feed the camera pose of this frame
+    pub fn update(&mut self, position: DVec3, rotation: Quat, delta_secs: f32) {
+        if let Some((previous_position, previous_rotation)) = self.previous {
+            if delta_secs > 0.0 {
+                let velocity = (position - previous_position) / delta_secs as f64;
+                let angular_velocity = (rotation * previous_rotation.inverse()).to_scaled_axis() / delta_secs;
+
+                // Exponential smoothing, independent of the frame rate
+                let blend = 1.0 - (-delta_secs / MOTION_SMOOTHING).exp();
+                self.velocity = self.velocity.lerp(velocity, blend as f64);
+                self.angular_velocity = self.angular_velocity.lerp(angular_velocity, blend);
+            }
+        }
+        self.previous = Some((position, rotation));
+    }
+
+    /// World position after `seconds` at the current velocity
+    pub fn predict_position(&self, position: DVec3, seconds: f32) -> DVec3 {
+        position + self.velocity * seconds as f64
+    }
+
+    /// Rotation after `seconds` at the current rotation rate
+    pub fn predict_rotation(&self, rotation: Quat, seconds: f32) -> Quat {
+        Quat::from_scaled_axis(self.angular_velocity * seconds) * rotation
+    }
+}
@@ -0,0 +1,221 @@
+use bevy::prelude::*;
+use bevy::math::DVec3;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use crate::utils::spline::{catmull_rom_position, catmull_rom_rotation};
+
+// Shortest time between two authored keyframes, in seconds
+const MIN_KEYFRAME_GAP: f64 = 0.1;
+
+/// Camera pose at a moment of a camera path
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct CameraKeyframe {
+    /// Seconds from the start of the path
+    pub time: f64,
+    /// World position (Mercator metres), kept in f64 so paths replay exactly anywhere on Earth
+    pub position: [f64; 3],
+    /// Camera rotation as a quaternion (x, y, z, w)
+    pub rotation: [f32; 4],
+}
+
+impl CameraKeyframe {
+    pub fn new(time: f64, position: DVec3, rotation: Quat) -> Self {
+        Self {
+            time,
+            position: position.to_array(),
+            rotation: rotation.to_array(),
+        }
+    }
+
+    pub fn position(&self) -> DVec3 {
+        DVec3::from_array(self.position)
+    }
+
+    pub fn rotation(&self) -> Quat {
+        Quat::from_array(self.rotation).normalize()
+    }
+}
+
+/// How a camera path is played back
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Interpolation {
+    /// One keyframe per rendered frame, as recorded; the same poses whatever the frame rate
+    #[default]
+    Exact,
+    /// A smooth Catmull-Rom spline through authored keyframes, played back in real time
+    CatmullRom,
+}
+
+/// A recorded or authored camera path, stored as JSON
+#[derive(Resource, Serialize, Deserialize, Debug, Default)]
+pub struct CameraPath {
+    pub interpolation: Interpolation,
+    pub keyframes: Vec<CameraKeyframe>,
+    #[serde(skip)]
+    pub path: PathBuf,
+}
+
+impl CameraPath {
+    pub fn duration(&self) -> f64 {
+        self.keyframes.last().map_or(0.0, |keyframe| keyframe.time)
+    }
+
+    /// World position and rotation on a spline through the keyframes at `time` seconds
+    pub fn sample(&self, time: f64) -> Option<(DVec3, Quat)> {
+        let positions: Vec<(f64, DVec3)> = self.keyframes.iter().map(|k| (k.time, k.position())).collect();
+        let rotations: Vec<(f64, Quat)> = self.keyframes.iter().map(|k| (k.time, k.rotation())).collect();
+        Some((catmull_rom_position(&positions, time)?, catmull_rom_rotation(&rotations, time)?))
+    }
+
+    /// Insert an authored keyframe at `index`, `spacing` seconds after the one before it
+    ///
+    /// The keyframes after it move back by `spacing` to make room. The first keyframe is
+    /// always at 0 s.
+    pub fn insert_keyframe(&mut self, index: usize, spacing: f64, position: DVec3, rotation: Quat) {
+        let index = index.min(self.keyframes.len());
+        let time = match index.checked_sub(1) {
+            Some(previous) => self.keyframes[previous].time + spacing,
+            None => 0.0,
+        };
+        for keyframe in &mut self.keyframes[index..] {
+            keyframe.time += spacing;
+        }
+        self.keyframes.insert(index, CameraKeyframe::new(time, position, rotation));
+    }
+
+    /// Remove the keyframe at `index`, keeping the first remaining one at 0 s
+    pub fn remove_keyframe(&mut self, index: usize) -> Option<CameraKeyframe> {
+        if index >= self.keyframes.len() {
+            return None;
+        }
+        let removed = self.keyframes.remove(index);
+        if let Some(start) = self.keyframes.first().map(|first| first.time) {
+            for keyframe in &mut self.keyframes {
+                keyframe.time -= start;
+            }
+        }
+        Some(removed)
+    }
+
+    /// Lengthen the time between keyframe `index` and the one before it by `delta` seconds
+    /// (shorten it when negative), moving the later keyframes along; returns the new gap
+    pub fn retime_keyframe(&mut self, index: usize, delta: f64) -> Option<f64> {
+        if index == 0 || index >= self.keyframes.len() {
+            return None;
+        }
+        let gap = self.keyframes[index].time - self.keyframes[index - 1].time;
+        let new_gap = (gap + delta).max(MIN_KEYFRAME_GAP);
+        for keyframe in &mut self.keyframes[index..] {
+            keyframe.time += new_gap - gap;
+        }
+        Some(new_gap)
+    }
+
+    /// Load the path from its file, keeping the file location
+    pub fn load(&mut self) -> Result<(), anyhow::Error> {
+        let loaded: CameraPath = serde_json::from_str(&fs::read_to_string(&self.path)?)?;
+        self.interpolation = loaded.interpolation;
+        self.keyframes = loaded.keyframes;
+        Ok(())
+    }
+
+    pub fn save(&self) -> Result<(), anyhow::Error> {
+        fs::write(&self.path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+/// What the camera path player is doing
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub enum CameraPathMode {
+    #[default]
+    Idle,
+    /// Adding a keyframe every frame; `elapsed` is the recording time so far
+    Recording { elapsed: f64 },
+    /// Driving the camera; `frame` counts rendered frames and `elapsed` real seconds
+    Playing { frame: usize, elapsed: f64 },
+}
+
+/// Recording and playback of the camera path
+#[derive(Resource, Debug)]
+pub struct CameraPathPlayer {
+    pub mode: CameraPathMode,
+    /// Authored keyframe being edited; new keyframes go after it
+    pub selected: Option<usize>,
+    /// Seconds between authored keyframes added one after the other
+    pub keyframe_spacing: f64,
+    /// Start playing as soon as the app runs
+    pub play_at_launch: bool,
+    /// Quit the app when playback ends, for scripted runs
+    pub exit_after_playback: bool,
+}
+
+impl Default for CameraPathPlayer {
+    fn default() -> Self {
+        Self {
+            mode: CameraPathMode::Idle,
+            selected: None,
+            keyframe_spacing: 3.0,
+            play_at_launch: false,
+            exit_after_playback: false,
+        }
+    }
+}
+
+impl CameraPathPlayer {
+    pub fn is_playing(&self) -> bool {
+        matches!(self.mode, CameraPathMode::Playing { .. })
+    }
+
+    pub fn is_recording(&self) -> bool {
+        matches!(self.mode, CameraPathMode::Recording { .. })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(times: &[f64]) -> CameraPath {
+        CameraPath {
+            interpolation: Interpolation::CatmullRom,
+            keyframes: times.iter().map(|&t| CameraKeyframe::new(t, DVec3::splat(t), Quat::IDENTITY)).collect(),
+            path: PathBuf::new(),
+        }
+    }
+
+    fn times(path: &CameraPath) -> Vec<f64> {
+        path.keyframes.iter().map(|k| k.time).collect()
+    }
+
+    #[test]
+    fn inserting_makes_room_after_the_keyframe_before() {
+        let mut camera_path = path(&[0.0, 2.0, 5.0]);
+        camera_path.insert_keyframe(1, 1.5, DVec3::ZERO, Quat::IDENTITY);
+        assert_eq!(times(&camera_path), vec![0.0, 1.5, 3.5, 6.5]);
+        camera_path.insert_keyframe(0, 1.0, DVec3::ZERO, Quat::IDENTITY);
+        assert_eq!(times(&camera_path), vec![0.0, 1.0, 2.5, 4.5, 7.5]);
+        camera_path.insert_keyframe(99, 1.0, DVec3::ZERO, Quat::IDENTITY);
+        assert_eq!(camera_path.duration(), 8.5);
+    }
+
+    #[test]
+    fn removing_the_first_keyframe_restarts_the_path_at_zero() {
+        let mut camera_path = path(&[0.0, 2.0, 5.0]);
+        assert_eq!(camera_path.remove_keyframe(0).map(|k| k.time), Some(0.0));
+        assert_eq!(times(&camera_path), vec![0.0, 3.0]);
+        assert!(camera_path.remove_keyframe(2).is_none());
+    }
+
+    #[test]
+    fn retiming_moves_the_later_keyframes_and_keeps_a_gap() {
+        let mut camera_path = path(&[0.0, 2.0, 5.0]);
+        assert_eq!(camera_path.retime_keyframe(1, 0.5), Some(2.5));
+        assert_eq!(times(&camera_path), vec![0.0, 2.5, 5.5]);
+        assert_eq!(camera_path.retime_keyframe(2, -10.0), Some(MIN_KEYFRAME_GAP));
+        assert_eq!(times(&camera_path), vec![0.0, 2.5, 2.5 + MIN_KEYFRAME_GAP]);
+        assert_eq!(camera_path.retime_keyframe(0, 1.0), None);
+    }
+}
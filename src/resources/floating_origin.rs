@@ -0,0 +1,38 @@
+use bevy::prelude::*;
+use bevy::math::DVec3;
+
+/// Render-space distance from the origin (in world units, i.e. Mercator metres) at which the origin is rebased
+pub const DEFAULT_REBASE_DISTANCE: f32 = 2000.0;
+
+/// Origin of render space, expressed in f64 world coordinates.
+///
+/// Everything that needs precise placement keeps its position in f64 world space
+/// (see `WorldPosition`) and is rendered relative to this origin, so f32 transforms
+/// stay small no matter how far from (0,0) the camera travels.
+/// The origin only moves horizontally so the ground stays at y = 0 in render space.
+#[derive(Resource)]
+pub struct FloatingOrigin {
+    pub origin: DVec3,
+    pub rebase_distance: f32,
+}
+
+impl Default for FloatingOrigin {
+    fn default() -> Self {
+        Self {
+            origin: DVec3::ZERO,
+            rebase_distance: DEFAULT_REBASE_DISTANCE,
+        }
+    }
+}
+
+impl FloatingOrigin {
+    /// Convert a world-space position to a render-space translation
+    pub fn world_to_render(&self, world: DVec3) -> Vec3 {
+        (world - self.origin).as_vec3()
+    }
+
+    /// Convert a render-space translation back to world space
+    pub fn render_to_world(&self, render: Vec3) -> DVec3 {
+        self.origin + render.as_dvec3()
+    }
+}
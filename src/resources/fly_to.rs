@@ -0,0 +1,36 @@
+use bevy::prelude::*;
+use bevy::math::DVec3;
+use crate::events::FlyToRequested;
+use crate::utils::flight_path::SmoothZoomPath;
+
+/// A camera flight in progress
+///
+/// The flight moves the ground point in the middle of the view along a smooth zoom and pan
+/// path, with the camera at a distance from it that follows the width of the view.
+pub struct Flight {
+    pub request: FlyToRequested,
+    pub path: SmoothZoomPath,
+    /// Ground points (world space) in the middle of the view at the start and the end
+    pub start_center: DVec3,
+    pub end_center: DVec3,
+    pub start_heading: f32,
+    pub end_heading: f32,
+    pub start_pitch: f32,
+    pub end_pitch: f32,
+    /// Ratio between the width of ground in view and the distance to it
+    pub width_per_distance: f64,
+    pub duration: f32,
+    pub elapsed: f32,
+}
+
+/// The current fly-to, if any; while it runs it owns the camera
+#[derive(Resource, Default)]
+pub struct FlyToState {
+    pub flight: Option<Flight>,
+}
+
+impl FlyToState {
+    pub fn is_flying(&self) -> bool {
+        self.flight.is_some()
+    }
+}
@@ -0,0 +1,59 @@
+use bevy::prelude::*;
+use bevy::math::DVec3;
+
+/// Settings for the globe rendering mode
+#[derive(Resource)]
+pub struct GlobeSettings {
+    pub enabled: bool,
+    /// Camera altitude in metres above which the map is shown fully as a globe
+    pub globe_altitude: f64,
+    /// Camera altitude in metres below which the map is shown fully as a flat Mercator plane
+    pub flat_altitude: f64,
+}
+
+impl Default for GlobeSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            globe_altitude: 3_000_000.0,
+            flat_altitude: 300_000.0,
+        }
+    }
+}
+
+/// Current blend between the flat Mercator plane (0.0) and the globe (1.0)
+///
+/// The globe is placed so that it touches the flat plane at `anchor` with a matching
+/// tangent plane and scale, which keeps the morph smooth around the point of interest.
+#[derive(Resource, Default)]
+pub struct GlobeMorph {
+    pub factor: f32,
+    /// World position on the ground where the globe touches the flat plane
+    pub anchor: DVec3,
+}
+
+/// State of the orbit camera used while the globe is visible
+#[derive(Resource)]
+pub struct OrbitCamera {
+    pub active: bool,
+    /// World position on the ground the camera orbits around
+    pub target: DVec3,
+    /// Distance from the target in world units
+    pub distance: f64,
+    /// Rotation around the vertical axis in radians, same convention as `MouseLookState::yaw`
+    pub heading: f32,
+    /// Angle away from looking straight down in radians
+    pub tilt: f32,
+}
+
+impl Default for OrbitCamera {
+    fn default() -> Self {
+        Self {
+            active: false,
+            target: DVec3::ZERO,
+            distance: 1.0,
+            heading: 0.0,
+            tilt: 0.0,
+        }
+    }
+}
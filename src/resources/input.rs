@@ -6,4 +6,34 @@ pub struct MouseLookState {
     pub mouse_motion: Vec2,
     pub pitch: f32,
     pub yaw: f32,
-} 
\ No newline at end of file
+}
+
+/// Which controller drives the camera on the flat map
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CameraMode {
+    /// First-person free flight with WASD and mouse look, cursor locked
+    #[default]
+    Fly,
+    /// Web map style: drag to pan, scroll to zoom, right-drag to rotate and tilt, cursor free
+    Map,
+    /// First-person on foot at eye height above the ground, with gravity and jumping
+    Walk,
+}
+
+impl CameraMode {
+    /// Whether the mode looks around with the locked mouse cursor
+    pub fn uses_mouse_look(&self) -> bool {
+        matches!(self, CameraMode::Fly | CameraMode::Walk)
+    }
+}
+
+/// Vertical motion of the walking camera
+#[derive(Resource, Default)]
+pub struct WalkState {
+    /// Upward speed in world units per second, negative while falling
+    pub vertical_speed: f32,
+    /// Whether the camera stands on the ground, and so can jump
+    pub grounded: bool,
+    /// Whether the camera has been put on the ground since walk mode was entered
+    pub landed: bool,
+}
@@ -0,0 +1,338 @@
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use serde::Deserialize;
+
+/// Something the user can do, independent of the key or button that does it
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum InputAction {
+    MoveForward,
+    MoveBackward,
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    Boost,
+    ToggleCursorGrab,
+    ToggleDebug,
+    ToggleCameraMode,
+    ToggleWalk,
+    ToggleGlobe,
+    FlyHome,
+    SaveBookmark,
+    NextBookmark,
+    PreviousBookmark,
+    DeleteBookmark,
+    ExportBookmarks,
+    ToggleRecording,
+    TogglePlayback,
+    AddKeyframe,
+    RemoveKeyframe,
+    ReplaceKeyframe,
+    PreviousKeyframe,
+    NextKeyframe,
+    ShortenKeyframeGap,
+    LengthenKeyframeGap,
+    ToggleTrackPlayback,
+    SlowerTrack,
+    FasterTrack,
+    ToggleTrackFollow,
+    ToggleLiveFollow,
+    CopyInspection,
+    ToggleLayer1,
+    ToggleLayer2,
+    ToggleLayer3,
+    ToggleLayer4,
+    ToggleLayer5,
+    ToggleLayer6,
+    ToggleLayer7,
+    ToggleLayer8,
+    ToggleLayer9,
+}
+
+impl InputAction {
+    /// Actions toggling the map layers, in the order of MapLayers::layers
+    pub const LAYER_TOGGLES: [InputAction; 9] = [
+        InputAction::ToggleLayer1,
+        InputAction::ToggleLayer2,
+        InputAction::ToggleLayer3,
+        InputAction::ToggleLayer4,
+        InputAction::ToggleLayer5,
+        InputAction::ToggleLayer6,
+        InputAction::ToggleLayer7,
+        InputAction::ToggleLayer8,
+        InputAction::ToggleLayer9,
+    ];
+
+    /// Whether the action moves or turns the camera, rather than triggering something once
+    pub fn is_movement(&self) -> bool {
+        matches!(self,
+            InputAction::MoveForward
+            | InputAction::MoveBackward
+            | InputAction::MoveLeft
+            | InputAction::MoveRight
+            | InputAction::MoveUp
+            | InputAction::MoveDown
+            | InputAction::Boost)
+    }
+}
+
+/// Keys and gamepad buttons bound to each action
+///
+/// Bindings come from the `[input.keys]` and `[input.gamepad]` tables of the config file,
+/// which replace the defaults of the actions they list.
+#[derive(Resource, Clone, Debug)]
+pub struct InputBindings {
+    pub keys: HashMap<InputAction, Vec<KeyCode>>,
+    pub gamepad_buttons: HashMap<InputAction, Vec<GamepadButton>>,
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        use InputAction::*;
+
+        let keys = [
+            (MoveForward, vec![KeyCode::KeyW]),
+            (MoveBackward, vec![KeyCode::KeyS]),
+            (MoveLeft, vec![KeyCode::KeyA]),
+            (MoveRight, vec![KeyCode::KeyD]),
+            (MoveUp, vec![KeyCode::Space]),
+            (MoveDown, vec![KeyCode::ControlLeft]),
+            (Boost, vec![KeyCode::ShiftLeft]),
+            (ToggleCursorGrab, vec![KeyCode::Escape]),
+            (ToggleDebug, vec![KeyCode::Digit1]),
+            (ToggleCameraMode, vec![KeyCode::KeyM]),
+            (ToggleWalk, vec![KeyCode::KeyV]),
+            (ToggleGlobe, vec![KeyCode::KeyG]),
+            (FlyHome, vec![KeyCode::KeyH]),
+            (SaveBookmark, vec![KeyCode::KeyB]),
+            (NextBookmark, vec![KeyCode::Period]),
+            (PreviousBookmark, vec![KeyCode::Comma]),
+            (DeleteBookmark, vec![KeyCode::Delete]),
+            (ExportBookmarks, vec![KeyCode::KeyE]),
+            (ToggleRecording, vec![KeyCode::KeyR]),
+            (TogglePlayback, vec![KeyCode::KeyP]),
+            (AddKeyframe, vec![KeyCode::KeyK]),
+            (RemoveKeyframe, vec![KeyCode::Backspace]),
+            (ReplaceKeyframe, vec![KeyCode::KeyU]),
+            (PreviousKeyframe, vec![KeyCode::PageUp]),
+            (NextKeyframe, vec![KeyCode::PageDown]),
+            (ShortenKeyframeGap, vec![KeyCode::Minus]),
+            (LengthenKeyframeGap, vec![KeyCode::Equal]),
+            (ToggleTrackPlayback, vec![KeyCode::KeyT]),
+            (SlowerTrack, vec![KeyCode::BracketLeft]),
+            (FasterTrack, vec![KeyCode::BracketRight]),
+            (ToggleTrackFollow, vec![KeyCode::KeyF]),
+            (ToggleLiveFollow, vec![KeyCode::KeyL]),
+            (CopyInspection, vec![KeyCode::KeyC]),
+            (ToggleLayer1, vec![KeyCode::F1]),
+            (ToggleLayer2, vec![KeyCode::F2]),
+            (ToggleLayer3, vec![KeyCode::F3]),
+            (ToggleLayer4, vec![KeyCode::F4]),
+            (ToggleLayer5, vec![KeyCode::F5]),
+            (ToggleLayer6, vec![KeyCode::F6]),
+            (ToggleLayer7, vec![KeyCode::F7]),
+            (ToggleLayer8, vec![KeyCode::F8]),
+            (ToggleLayer9, vec![KeyCode::F9]),
+        ];
+        // The sticks move and look; the triggers climb and sink with analog strength
+        let gamepad_buttons = [
+            (MoveUp, vec![GamepadButton::RightTrigger2]),
+            (MoveDown, vec![GamepadButton::LeftTrigger2]),
+            (Boost, vec![GamepadButton::LeftTrigger]),
+            (ToggleDebug, vec![GamepadButton::Select]),
+            (ToggleCameraMode, vec![GamepadButton::North]),
+            (ToggleWalk, vec![GamepadButton::East]),
+            (ToggleGlobe, vec![GamepadButton::West]),
+            (FlyHome, vec![GamepadButton::Start]),
+            (SaveBookmark, vec![GamepadButton::DPadUp]),
+            (NextBookmark, vec![GamepadButton::DPadRight]),
+            (PreviousBookmark, vec![GamepadButton::DPadLeft]),
+        ];
+
+        Self {
+            keys: keys.into_iter().collect(),
+            gamepad_buttons: gamepad_buttons.into_iter().collect(),
+        }
+    }
+}
+
+impl InputBindings {
+    /// Short name of the first key bound to an action, for showing in the UI ("C", "1", "PageUp")
+    pub fn key_label(&self, action: InputAction) -> Option<String> {
+        let name = format!("{:?}", self.keys.get(&action)?.first()?);
+        let short = name.strip_prefix("Key").or_else(|| name.strip_prefix("Digit")).unwrap_or(&name);
+        Some(short.to_string())
+    }
+}
+
+/// Actions resolved from the bindings for the current frame
+#[derive(Resource, Default, Debug)]
+pub struct ActionState {
+    // Strength of each active action, 0.0 - 1.0; keys count as 1.0, triggers as far as they are pulled
+    values: HashMap<InputAction, f32>,
+    just_pressed: HashSet<InputAction>,
+    /// Gamepad movement stick after the dead zone and response curve, x right and y forward
+    pub move_stick: Vec2,
+    /// Gamepad look stick after the dead zone and response curve, x right and y up
+    pub look_stick: Vec2,
+}
+
+impl ActionState {
+    pub fn value(&self, action: InputAction) -> f32 {
+        self.values.get(&action).copied().unwrap_or(0.0)
+    }
+
+    pub fn pressed(&self, action: InputAction) -> bool {
+        self.value(action) > 0.0
+    }
+
+    pub fn just_pressed(&self, action: InputAction) -> bool {
+        self.just_pressed.contains(&action)
+    }
+
+    /// Difference between two opposite actions, -1.0 - 1.0
+    pub fn axis(&self, negative: InputAction, positive: InputAction) -> f32 {
+        self.value(positive) - self.value(negative)
+    }
+
+    /// Whether any movement or look input is given
+    pub fn any_movement(&self) -> bool {
+        self.move_stick != Vec2::ZERO
+            || self.look_stick != Vec2::ZERO
+            || self.values.keys().any(InputAction::is_movement)
+    }
+
+    pub fn clear(&mut self) {
+        self.values.clear();
+        self.just_pressed.clear();
+        self.move_stick = Vec2::ZERO;
+        self.look_stick = Vec2::ZERO;
+    }
+
+    pub fn set(&mut self, action: InputAction, value: f32, just_pressed: bool) {
+        if value > 0.0 {
+            let current = self.values.entry(action).or_insert(0.0);
+            *current = current.max(value.min(1.0));
+        }
+        if just_pressed {
+            self.just_pressed.insert(action);
+        }
+    }
+}
+
+/// Parse a key name as used in the config file: the names of `KeyCode`, like "KeyW",
+/// "Digit1", "Space", "ShiftLeft", "ArrowUp" or "F5"
+pub fn parse_key_code(name: &str) -> Option<KeyCode> {
+    if let Some(letter) = name.strip_prefix("Key") {
+        const LETTERS: [KeyCode; 26] = [
+            KeyCode::KeyA, KeyCode::KeyB, KeyCode::KeyC, KeyCode::KeyD, KeyCode::KeyE, KeyCode::KeyF,
+            KeyCode::KeyG, KeyCode::KeyH, KeyCode::KeyI, KeyCode::KeyJ, KeyCode::KeyK, KeyCode::KeyL,
+            KeyCode::KeyM, KeyCode::KeyN, KeyCode::KeyO, KeyCode::KeyP, KeyCode::KeyQ, KeyCode::KeyR,
+            KeyCode::KeyS, KeyCode::KeyT, KeyCode::KeyU, KeyCode::KeyV, KeyCode::KeyW, KeyCode::KeyX,
+            KeyCode::KeyY, KeyCode::KeyZ,
+        ];
+        let &[byte] = letter.as_bytes() else {
+            return None;
+        };
+        return byte.checked_sub(b'A').and_then(|index| LETTERS.get(index as usize).copied());
+    }
+    if let Some(digit) = name.strip_prefix("Digit") {
+        const DIGITS: [KeyCode; 10] = [
+            KeyCode::Digit0, KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3, KeyCode::Digit4,
+            KeyCode::Digit5, KeyCode::Digit6, KeyCode::Digit7, KeyCode::Digit8, KeyCode::Digit9,
+        ];
+        return digit.parse::<usize>().ok().and_then(|index| DIGITS.get(index).copied());
+    }
+    if let Some(number) = name.strip_prefix('F') {
+        const FUNCTION_KEYS: [KeyCode; 12] = [
+            KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4, KeyCode::F5, KeyCode::F6,
+            KeyCode::F7, KeyCode::F8, KeyCode::F9, KeyCode::F10, KeyCode::F11, KeyCode::F12,
+        ];
+        return number.parse::<usize>().ok()
+            .and_then(|number| number.checked_sub(1))
+            .and_then(|index| FUNCTION_KEYS.get(index).copied());
+    }
+
+    let key = match name {
+        "Space" => KeyCode::Space,
+        "Escape" => KeyCode::Escape,
+        "Enter" => KeyCode::Enter,
+        "Tab" => KeyCode::Tab,
+        "Backspace" => KeyCode::Backspace,
+        "ShiftLeft" => KeyCode::ShiftLeft,
+        "ShiftRight" => KeyCode::ShiftRight,
+        "ControlLeft" => KeyCode::ControlLeft,
+        "ControlRight" => KeyCode::ControlRight,
+        "AltLeft" => KeyCode::AltLeft,
+        "AltRight" => KeyCode::AltRight,
+        "ArrowUp" => KeyCode::ArrowUp,
+        "ArrowDown" => KeyCode::ArrowDown,
+        "ArrowLeft" => KeyCode::ArrowLeft,
+        "ArrowRight" => KeyCode::ArrowRight,
+        "PageUp" => KeyCode::PageUp,
+        "PageDown" => KeyCode::PageDown,
+        "Home" => KeyCode::Home,
+        "End" => KeyCode::End,
+        "Insert" => KeyCode::Insert,
+        "Delete" => KeyCode::Delete,
+        "Minus" => KeyCode::Minus,
+        "Equal" => KeyCode::Equal,
+        "Comma" => KeyCode::Comma,
+        "Period" => KeyCode::Period,
+        "Slash" => KeyCode::Slash,
+        "Semicolon" => KeyCode::Semicolon,
+        "Quote" => KeyCode::Quote,
+        "BracketLeft" => KeyCode::BracketLeft,
+        "BracketRight" => KeyCode::BracketRight,
+        "Backslash" => KeyCode::Backslash,
+        "Backquote" => KeyCode::Backquote,
+        _ => return None,
+    };
+    Some(key)
+}
+
+/// Parse a gamepad button name as used in the config file: the names of `GamepadButton`,
+/// like "South", "LeftTrigger2" or "DPadUp"
+pub fn parse_gamepad_button(name: &str) -> Option<GamepadButton> {
+    let button = match name {
+        "South" => GamepadButton::South,
+        "East" => GamepadButton::East,
+        "North" => GamepadButton::North,
+        "West" => GamepadButton::West,
+        "C" => GamepadButton::C,
+        "Z" => GamepadButton::Z,
+        "LeftTrigger" => GamepadButton::LeftTrigger,
+        "LeftTrigger2" => GamepadButton::LeftTrigger2,
+        "RightTrigger" => GamepadButton::RightTrigger,
+        "RightTrigger2" => GamepadButton::RightTrigger2,
+        "Select" => GamepadButton::Select,
+        "Start" => GamepadButton::Start,
+        "Mode" => GamepadButton::Mode,
+        "LeftThumb" => GamepadButton::LeftThumb,
+        "RightThumb" => GamepadButton::RightThumb,
+        "DPadUp" => GamepadButton::DPadUp,
+        "DPadDown" => GamepadButton::DPadDown,
+        "DPadLeft" => GamepadButton::DPadLeft,
+        "DPadRight" => GamepadButton::DPadRight,
+        _ => return None,
+    };
+    Some(button)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_labels_are_short_names_of_the_first_key() {
+        let mut bindings = InputBindings::default();
+        assert_eq!(bindings.key_label(InputAction::CopyInspection).as_deref(), Some("C"));
+        bindings.keys.insert(InputAction::CopyInspection, vec![KeyCode::Digit7, KeyCode::KeyC]);
+        assert_eq!(bindings.key_label(InputAction::CopyInspection).as_deref(), Some("7"));
+        bindings.keys.insert(InputAction::CopyInspection, vec![KeyCode::PageUp]);
+        assert_eq!(bindings.key_label(InputAction::CopyInspection).as_deref(), Some("PageUp"));
+        bindings.keys.insert(InputAction::CopyInspection, Vec::new());
+        assert_eq!(bindings.key_label(InputAction::CopyInspection), None);
+    }
+}
@@ -0,0 +1,92 @@
+use bevy::prelude::*;
+use crate::utils::geodesy::{LatLon, GeoBounds};
+
+/// A layer of the tile under an inspected point, and where its image comes from
+#[derive(Clone, Debug)]
+pub struct InspectedLayer {
+    pub name: String,
+    /// URL the tile is fetched from
+    pub url: String,
+    pub attribution: String,
+}
+
+/// What is on the map at a clicked point
+#[derive(Clone, Debug)]
+pub struct Inspection {
+    pub position: LatLon,
+    /// Tile under the point at the zoom level shown there, with x wrapped into the world
+    pub zoom: u32,
+    pub x: u32,
+    pub y: u32,
+    /// Geographic extent of that tile
+    pub bounds: GeoBounds,
+    /// Layers composited into that tile, bottom first
+    pub layers: Vec<InspectedLayer>,
+    /// Where the map was clicked, in logical pixels from the top left of the window
+    pub screen_position: Vec2,
+}
+
+impl Inspection {
+    /// Tile address in the usual z/x/y form
+    pub fn tile_path(&self) -> String {
+        format!("{}/{}/{}", self.zoom, self.x, self.y)
+    }
+
+    /// Coordinates in degrees, minutes and seconds
+    pub fn dms(&self) -> String {
+        format!(
+            "{} {}",
+            degrees_minutes_seconds(self.position.lat, 'N', 'S'),
+            degrees_minutes_seconds(self.position.lon, 'E', 'W'),
+        )
+    }
+
+    /// Text put on the clipboard: the coordinates, then the tile
+    pub fn clipboard_text(&self) -> String {
+        format!("{:.6}, {:.6}\n{}", self.position.lat, self.position.lon, self.tile_path())
+    }
+}
+
+fn degrees_minutes_seconds(degrees: f64, positive: char, negative: char) -> String {
+    let hemisphere = if degrees < 0.0 { negative } else { positive };
+    // Round to tenths of a second first, so 59.95" doesn't show up as 60.0"
+    let tenths = (degrees.abs() * 36_000.0).round() as u64;
+    format!(
+        "{}°{:02}'{:04.1}\"{}",
+        tenths / 36_000,
+        tenths / 600 % 60,
+        (tenths % 600) as f64 / 10.0,
+        hemisphere,
+    )
+}
+
+/// The point of the map being inspected
+#[derive(Resource, Default, Debug)]
+pub struct MapInspection {
+    pub current: Option<Inspection>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn degrees_split_into_minutes_and_seconds() {
+        assert_eq!(degrees_minutes_seconds(48.858_222, 'N', 'S'), "48°51'29.6\"N");
+        assert_eq!(degrees_minutes_seconds(2.294_5, 'E', 'W'), "2°17'40.2\"E");
+        assert_eq!(degrees_minutes_seconds(0.0, 'N', 'S'), "0°00'00.0\"N");
+    }
+
+    #[test]
+    fn negative_degrees_are_south_and_west() {
+        assert_eq!(degrees_minutes_seconds(-33.856_8, 'N', 'S'), "33°51'24.5\"S");
+        assert_eq!(degrees_minutes_seconds(-151.215_3, 'E', 'W'), "151°12'55.1\"W");
+    }
+
+    #[test]
+    fn seconds_round_up_into_minutes_and_degrees() {
+        // 59.97" would show as 60.0" if the seconds were rounded on their own
+        assert_eq!(degrees_minutes_seconds(10.0 + 59.0 / 60.0 + 59.97 / 3600.0, 'N', 'S'), "11°00'00.0\"N");
+        assert_eq!(degrees_minutes_seconds(5.0 + 59.99 / 3600.0, 'E', 'W'), "5°01'00.0\"E");
+    }
+}
@@ -0,0 +1,132 @@
+use bevy::prelude::*;
+use crate::resources::constants::{MIN_ZOOM_LEVEL, MAX_ZOOM_LEVEL};
+
+/// A raster tile source that is stacked with the other layers on every tile
+#[derive(Clone, Debug, PartialEq)]
+pub struct MapLayer {
+    /// Display name, also the directory the layer's tiles are cached in
+    pub name: String,
+    /// Tile URL with `{z}`, `{x}` and `{y}` placeholders
+    pub url_template: String,
+    /// Layers with a higher z-order are drawn on top
+    pub z_order: i32,
+    /// Opacity of the layer over the ones below it (0.0 - 1.0)
+    pub opacity: f32,
+    /// Zoom range the source has tiles for
+    pub min_zoom: u32,
+    pub max_zoom: u32,
+    pub enabled: bool,
+    /// Credit the tile source asks for wherever its tiles are shown
+    pub attribution: String,
+}
+
+impl MapLayer {
+    pub fn new(name: &str, url_template: &str, z_order: i32) -> Self {
+        Self {
+            name: name.to_string(),
+            url_template: url_template.to_string(),
+            z_order,
+            opacity: 1.0,
+            min_zoom: MIN_ZOOM_LEVEL,
+            max_zoom: MAX_ZOOM_LEVEL,
+            enabled: true,
+            attribution: String::new(),
+        }
+    }
+
+    pub fn with_opacity(mut self, opacity: f32) -> Self {
+        self.opacity = opacity.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn with_zoom_range(mut self, min_zoom: u32, max_zoom: u32) -> Self {
+        self.min_zoom = min_zoom;
+        self.max_zoom = max_zoom;
+        self
+    }
+
+    pub fn with_enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    pub fn with_attribution(mut self, attribution: &str) -> Self {
+        self.attribution = attribution.to_string();
+        self
+    }
+
+    /// File extension of the layer's tiles, taken from the URL template ("png" when it has none)
+    pub fn file_extension(&self) -> &str {
+        let path = self.url_template.split(['?', '#']).next().unwrap_or_default();
+        let file = path.rsplit('/').next().unwrap_or_default();
+        match file.rsplit_once('.') {
+            Some((_, extension)) if !extension.is_empty() && extension.chars().all(|c| c.is_ascii_alphanumeric()) => {
+                extension
+            }
+            _ => "png",
+        }
+    }
+
+    /// Whether the layer contributes to tiles at a zoom level
+    pub fn is_active_at(&self, zoom: u32) -> bool {
+        self.enabled && self.opacity > 0.0 && (self.min_zoom..=self.max_zoom).contains(&zoom)
+    }
+}
+
+/// The raster layers composited into every map tile
+///
+/// Toggling or changing layers reloads the tiles on screen with the new stack.
+#[derive(Resource)]
+pub struct MapLayers {
+    pub layers: Vec<MapLayer>,
+    /// Bumped for every reload with a new stack; loads started for an older one are stale
+    pub generation: u64,
+}
+
+impl Default for MapLayers {
+    fn default() -> Self {
+        Self {
+            layers: vec![
+                MapLayer::new("osm", "https://a.tile.openstreetmap.org/{z}/{x}/{y}.png", 0)
+                    .with_attribution("© OpenStreetMap contributors"),
+                MapLayer::new(
+                    "hillshade",
+                    "https://server.arcgisonline.com/ArcGIS/rest/services/Elevation/World_Hillshade/MapServer/tile/{z}/{y}/{x}",
+                    10,
+                )
+                .with_opacity(0.4)
+                .with_zoom_range(MIN_ZOOM_LEVEL, 16)
+                .with_enabled(false)
+                .with_attribution("Esri, USGS, NGA, NASA, CGIAR and the GIS User Community"),
+                MapLayer::new("transit", "https://a.tiles.openrailwaymap.org/standard/{z}/{x}/{y}.png", 20)
+                    .with_zoom_range(2, MAX_ZOOM_LEVEL)
+                    .with_enabled(false)
+                    .with_attribution("© OpenRailwayMap contributors, © OpenStreetMap contributors"),
+            ],
+            generation: 0,
+        }
+    }
+}
+
+impl MapLayers {
+    /// The layers that contribute to tiles at a zoom level, bottom first
+    pub fn active_at(&self, zoom: u32) -> Vec<MapLayer> {
+        let mut layers: Vec<MapLayer> = self.layers.iter().filter(|layer| layer.is_active_at(zoom)).cloned().collect();
+        layers.sort_by_key(|layer| layer.z_order);
+        layers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_extension_follows_the_url_template() {
+        let extension = |url| MapLayer::new("test", url, 0).file_extension().to_string();
+        assert_eq!(extension("https://tiles.example.org/{z}/{x}/{y}.png"), "png");
+        assert_eq!(extension("https://tiles.example.org/{z}/{x}/{y}.ktx2?key=abc.def"), "ktx2");
+        assert_eq!(extension("https://tiles.example.org/{z}/{x}/{y}"), "png");
+        assert_eq!(extension("https://tiles.example.org/v1.2/{z}/{x}/{y}"), "png");
+    }
+}
@@ -3,9 +3,43 @@ pub mod runtime;
 pub mod settings;
 pub mod input;
 pub mod constants;
+pub mod floating_origin;
+pub mod globe;
+pub mod tile_registry;
+pub mod camera_motion;
+pub mod residency;
+pub mod zoom_policy;
+pub mod map_layers;
+pub mod fly_to;
+pub mod start_view;
+pub mod session;
+pub mod input_bindings;
+pub mod bookmarks;
+pub mod camera_path;
+pub mod track;
+pub mod position_feed;
+pub mod inspection;
+pub mod prefetch_queue;
 
 pub use osm_data::*;
 pub use runtime::*;
 pub use settings::*;
 pub use input::*;
+pub use floating_origin::*;
+pub use globe::*;
+pub use tile_registry::*;
+pub use camera_motion::*;
+pub use residency::*;
+pub use zoom_policy::*;
+pub use map_layers::*;
+pub use fly_to::*;
+pub use start_view::*;
+pub use session::*;
+pub use input_bindings::*;
+pub use bookmarks::*;
+pub use camera_path::*;
+pub use track::*;
+pub use position_feed::*;
+pub use inspection::*;
+pub use prefetch_queue::*;
 // Constants are used directly, so no need to re-export 
\ No newline at end of file
@@ -1,15 +1,32 @@
 use bevy::prelude::*;
+use bevy::utils::HashSet;
 use std::sync::Arc;
 use parking_lot::Mutex;
+use bevy::image::CompressedImageFormats;
+use crate::resources::TileKey;
 
 #[derive(Resource)]
 pub struct OSMData {
-    pub tiles: Vec<(u32, u32, u32, Entity)>, // (x, y, zoom, entity)
-    pub background_tiles: Vec<(u32, u32, u32, Entity)>, // (x, y, zoom, entity) for low-res background
-    pub loaded_tiles: Vec<(u32, u32, u32)>,  // (x, y, zoom)
-    pub loaded_background_tiles: Vec<(u32, u32, u32)>,  // (x, y, zoom) for background
-    pub pending_tiles: Arc<Mutex<Vec<(u32, u32, u32, Option<image::DynamicImage>, bool)>>>, // (x, y, zoom, image, is_background)
-    pub current_zoom: u32,
+    // Requested and spawned tiles are tracked in the TileRegistry resource
+    // Progress reported by the async loaders, applied to the tile entities each frame
+    // Each report carries the layer generation its load was started for
+    pub pending_tiles: Arc<Mutex<Vec<(u64, TileProgress)>>>,
     pub background_zoom: u32, // Zoom level for background tiles
-    pub total_time: f32, // Track total time for garbage collection
-} 
\ No newline at end of file
+    // Focus tiles the view selected this frame, which prefetching no longer owns
+    pub view_tiles: HashSet<TileKey>,
+    // Compressed texture formats the GPU supports, detected at startup
+    pub texture_formats: CompressedImageFormats,
+}
+
+/// Progress of an async tile load, reported back to the main thread
+pub enum TileProgress {
+    Downloading(Entity),
+    Decoding(Entity),
+    /// The tile is decoded into GPU-ready texture data
+    Loaded(Entity, Image),
+    /// The tile is decoded, but layers that failed were left out; carries the reason and
+    /// the number of earlier attempts, so it is retried like a failed tile
+    Incomplete(Entity, Image, String, u32),
+    /// The load failed; carries the reason and the number of earlier attempts
+    Failed(Entity, String, u32),
+}
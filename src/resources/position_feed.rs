@@ -0,0 +1,85 @@
+use bevy::prelude::*;
+use std::sync::Arc;
+use parking_lot::Mutex;
+use crate::utils::geodesy::LatLon;
+use crate::utils::position_source::{PositionFormat, PositionReport};
+
+/// What the connection task reports back to the main thread
+pub enum FeedMessage {
+    Connected,
+    Disconnected(String),
+    Report(PositionReport),
+}
+
+/// Connection to the live position source and how its positions are used
+#[derive(Resource)]
+pub struct PositionFeed {
+    /// Host and port to connect to; no feed without one
+    pub address: Option<String>,
+    pub format: PositionFormat,
+    /// Messages from the connection task, applied each frame
+    pub pending: Arc<Mutex<Vec<FeedMessage>>>,
+    /// Seconds the camera takes to catch up with a new position
+    pub smoothing: f64,
+    /// Seconds without a position after which the fix counts as lost
+    pub fix_timeout: f64,
+    /// Height of the camera above the map in metres
+    pub camera_height: f64,
+    /// Turn the camera in the direction the device moves in
+    pub follow_course: bool,
+}
+
+impl Default for PositionFeed {
+    fn default() -> Self {
+        Self {
+            address: None,
+            format: PositionFormat::Nmea,
+            pending: Arc::new(Mutex::new(Vec::new())),
+            smoothing: 1.0,
+            fix_timeout: 3.0,
+            camera_height: 1.7,
+            follow_course: true,
+        }
+    }
+}
+
+/// State of the live position
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub enum FixStatus {
+    /// Not connected to the position source
+    #[default]
+    Disconnected,
+    /// Connected, but no position yet
+    Waiting,
+    Fix,
+    /// No position since `since` (seconds of app time); the camera stays at the last one
+    Lost { since: f64 },
+}
+
+/// The live device position, smoothed for the camera
+#[derive(Resource, Debug, Default)]
+pub struct LivePosition {
+    pub status: FixStatus,
+    /// Whether the camera follows the live position
+    pub follow: bool,
+    /// Last reported position, and the app time it arrived at
+    pub fix: Option<(LatLon, f64)>,
+    pub altitude: Option<f64>,
+    /// Speed over ground in metres per second
+    pub speed: Option<f64>,
+    /// Course over ground in degrees clockwise from north
+    pub course: Option<f64>,
+    /// Estimated horizontal error in metres
+    pub accuracy: Option<f64>,
+    /// Position the camera is at, easing towards the reported one
+    pub smoothed: Option<LatLon>,
+    /// Smoothed compass heading of the camera in degrees
+    pub camera_heading: Option<f64>,
+}
+
+impl LivePosition {
+    /// Whether the live position has the camera
+    pub fn is_following(&self) -> bool {
+        self.follow && self.smoothed.is_some()
+    }
+}
@@ -0,0 +1,15 @@
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use tokio::task::AbortHandle;
+use crate::resources::TileKey;
+
+/// Tiles being loaded ahead of the camera, kept apart from the loads for the view so they
+/// can be limited and cancelled
+#[derive(Resource, Default)]
+pub struct PrefetchQueue {
+    /// Prefetched tiles still loading, with the handle that cancels their load
+    pub in_flight: HashMap<TileKey, (Entity, AbortHandle)>,
+    /// Tiles along the predicted path as of the last prefetch; loads for any other tile
+    /// are cancelled
+    pub wanted: HashSet<TileKey>,
+}
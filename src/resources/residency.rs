@@ -0,0 +1,22 @@
+use bevy::prelude::*;
+
+// Default budget: about a thousand 256x256 RGBA tiles with their meshes
+const DEFAULT_TILE_BUDGET_BYTES: usize = 256 * 1024 * 1024;
+
+/// Budget for the GPU memory used by tile textures and meshes, and the current usage
+#[derive(Resource)]
+pub struct TileResidency {
+    /// Bytes tiles may occupy before the least useful ones are evicted
+    pub budget_bytes: usize,
+    /// Bytes occupied by the resident tiles, updated every frame
+    pub used_bytes: usize,
+}
+
+impl Default for TileResidency {
+    fn default() -> Self {
+        Self {
+            budget_bytes: DEFAULT_TILE_BUDGET_BYTES,
+            used_bytes: 0,
+        }
+    }
+}
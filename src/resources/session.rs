@@ -0,0 +1,47 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use crate::resources::CameraPose;
+
+/// State kept between runs of the app
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct Session {
+    pub camera: CameraPose,
+    /// Names of the enabled map layers
+    pub layers: Vec<String>,
+    pub debug_mode: bool,
+}
+
+impl Session {
+    pub fn load(path: &PathBuf) -> Result<Self, anyhow::Error> {
+        let text = fs::read_to_string(path)?;
+        Ok(toml::from_str(&text)?)
+    }
+
+    pub fn save(&self, path: &PathBuf) -> Result<(), anyhow::Error> {
+        fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Where the session is kept and what was restored from it at launch
+#[derive(Resource, Debug)]
+pub struct SessionSettings {
+    pub path: PathBuf,
+    /// Whether the session is restored at launch and saved on exit
+    pub enabled: bool,
+    /// Session read at launch, if there was one
+    pub restored: Option<Session>,
+}
+
+impl Default for SessionSettings {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from("session.toml"),
+            enabled: true,
+            restored: None,
+        }
+    }
+}
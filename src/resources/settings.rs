@@ -1,4 +1,5 @@
 use bevy::prelude::*;
+use serde::Deserialize;
 
 // Settings for debug display
 #[derive(Resource)]
@@ -12,4 +13,163 @@ impl Default for DebugSettings {
             debug_mode: false,
         }
     }
-} 
\ No newline at end of file
+}
+
+// Settings for choosing which tiles to show
+#[derive(Resource)]
+pub struct TileSelectionSettings {
+    /// Largest size in screen pixels a tile texel may have before the tile is split
+    pub max_screen_space_error: f32,
+    /// Upper limit on the number of tiles selected for a view
+    pub max_tiles: usize,
+}
+
+impl Default for TileSelectionSettings {
+    fn default() -> Self {
+        Self {
+            max_screen_space_error: 1.5,
+            max_tiles: 96,
+        }
+    }
+}
+
+// Settings for applying loaded tiles on the main thread
+#[derive(Resource)]
+pub struct TileUploadSettings {
+    /// Most loaded tiles turned into meshes and textures per frame; at least one always is
+    pub max_uploads_per_frame: usize,
+    /// Time in milliseconds per frame after which further loaded tiles wait for the next frame
+    pub max_upload_millis: f32,
+}
+
+impl Default for TileUploadSettings {
+    fn default() -> Self {
+        Self {
+            max_uploads_per_frame: 8,
+            max_upload_millis: 4.0,
+        }
+    }
+}
+
+// Settings for loading tiles ahead of the moving camera
+#[derive(Resource)]
+pub struct PrefetchSettings {
+    /// How far ahead to predict the camera path, in seconds
+    pub horizon_secs: f32,
+    /// Number of predicted poses along the path
+    pub steps: u32,
+    /// Upper limit on the number of tiles selected for each predicted pose
+    pub max_tiles: usize,
+    /// Most prefetch loads in flight at once, on top of the loads for the current view
+    pub max_concurrent_loads: usize,
+}
+
+impl Default for PrefetchSettings {
+    fn default() -> Self {
+        Self {
+            horizon_secs: 3.0,
+            steps: 3,
+            max_tiles: 48,
+            max_concurrent_loads: 4,
+        }
+    }
+}
+
+// Settings for filling the view up to the horizon
+#[derive(Resource)]
+pub struct HorizonSettings {
+    /// Half width in tiles of each background ring, around the tile below the camera
+    pub ring_half_width: i32,
+    /// Zoom levels between the base zoom and the innermost background ring
+    pub ring_zoom_offset: u32,
+    /// Whether distance fog hides the far edge of the map
+    pub fog_enabled: bool,
+    /// Fraction of the horizon distance where the fog starts
+    pub fog_start_fraction: f32,
+}
+
+impl Default for HorizonSettings {
+    fn default() -> Self {
+        Self {
+            ring_half_width: 1,
+            ring_zoom_offset: 4,
+            fog_enabled: true,
+            fog_start_fraction: 0.4,
+        }
+    }
+}
+
+// Settings for how strongly the camera responds to input, from the `[controls]` table of the config file
+#[derive(Resource, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ControlSettings {
+    /// Radians of mouse look per pixel of mouse motion
+    pub look_sensitivity: f32,
+    /// Radians per second of look at full gamepad stick deflection
+    pub gamepad_look_speed: f32,
+    /// Fraction of stick travel that is ignored around the centre
+    pub stick_dead_zone: f32,
+    /// Response curve of the sticks beyond the dead zone: 1.0 is linear, higher gives finer control near the centre
+    pub stick_exponent: f32,
+    /// Invert the vertical look axis of mouse and gamepad
+    pub invert_look_y: bool,
+    /// Fraction of the current height flown per second
+    pub altitude_speed_factor: f32,
+    /// How fly speed grows with height: 1.0 is proportional, lower is gentler at high altitude
+    pub altitude_speed_exponent: f32,
+    /// Height (world units) below which the fly speed stops decreasing
+    pub min_speed_height: f32,
+    /// Speed multiplier while boosting
+    pub boost_multiplier: f32,
+    /// Height of the walking camera above the ground, in metres
+    pub eye_height: f32,
+    /// Walking speed in metres per second, before boosting
+    pub walk_speed: f32,
+    /// Downward acceleration while walking, in metres per second squared
+    pub gravity: f32,
+    /// Upward speed of a jump, in metres per second
+    pub jump_speed: f32,
+    /// Lowest height of the fly camera above the ground, in metres
+    pub min_fly_altitude: f32,
+}
+
+impl Default for ControlSettings {
+    fn default() -> Self {
+        Self {
+            look_sensitivity: 0.002,
+            gamepad_look_speed: 2.0,
+            stick_dead_zone: 0.15,
+            stick_exponent: 2.0,
+            invert_look_y: false,
+            altitude_speed_factor: 0.5,
+            altitude_speed_exponent: 1.0,
+            min_speed_height: 10.0,
+            boost_multiplier: 3.0,
+            eye_height: 1.7,
+            walk_speed: 1.5,
+            gravity: 9.81,
+            jump_speed: 4.0,
+            min_fly_altitude: 2.0,
+        }
+    }
+}
+
+impl ControlSettings {
+    /// Apply the dead zone and response curve to a stick position
+    pub fn stick_response(&self, stick: Vec2) -> Vec2 {
+        let length = stick.length();
+        if length <= self.stick_dead_zone {
+            return Vec2::ZERO;
+        }
+        let scaled = ((length - self.stick_dead_zone) / (1.0 - self.stick_dead_zone).max(f32::EPSILON)).min(1.0);
+        stick / length * scaled.powf(self.stick_exponent)
+    }
+
+    /// Fly speed in world units per second at a height, before boosting
+    pub fn fly_speed(&self, height: f32) -> f32 {
+        // Covering a fixed fraction of the height per second feels the same at street level and from orbit
+        // The exponent bends the curve around the minimum height, where the speed stays the same
+        let height = height.max(self.min_speed_height);
+        self.altitude_speed_factor * height.powf(self.altitude_speed_exponent) * self.min_speed_height.powf(1.0 - self.altitude_speed_exponent)
+    }
+}
@@ -0,0 +1,70 @@
+use bevy::prelude::*;
+use bevy::math::DVec3;
+use serde::{Deserialize, Serialize};
+use crate::events::FlyToRequested;
+use crate::resources::constants::{GRONINGEN_LAT, GRONINGEN_LON, START_ALTITUDE_METERS};
+use crate::utils::projection::{world_to_lat_lon, world_altitude};
+
+/// Camera location and view direction in map terms, as used in config and session files
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct CameraPose {
+    /// WGS84 degrees
+    pub lat: f64,
+    pub lon: f64,
+    /// Metres above ground
+    pub altitude: f64,
+    /// Compass heading in degrees, clockwise from north
+    pub heading: f32,
+    /// Degrees above the horizon, negative looks down
+    pub pitch: f32,
+}
+
+impl Default for CameraPose {
+    fn default() -> Self {
+        Self {
+            lat: GRONINGEN_LAT,
+            lon: GRONINGEN_LON,
+            altitude: START_ALTITUDE_METERS,
+            heading: 0.0,
+            // Nearly straight down, within the mouse look limits
+            pitch: -85.0,
+        }
+    }
+}
+
+impl CameraPose {
+    /// Pose of a camera at a world position with the fly camera's yaw and pitch (radians)
+    pub fn from_camera(world: DVec3, yaw: f32, pitch: f32) -> Self {
+        let (lat, lon) = world_to_lat_lon(world.x, world.z);
+        Self {
+            lat,
+            lon,
+            altitude: world_altitude(world),
+            heading: (-yaw.to_degrees()).rem_euclid(360.0),
+            pitch: pitch.to_degrees(),
+        }
+    }
+
+    /// Yaw in radians around the up axis, as used by the cameras; zero looks north
+    pub fn yaw(&self) -> f32 {
+        // Positive yaw turns counter-clockwise seen from above, the opposite of a compass
+        -self.heading.to_radians()
+    }
+
+    /// Pitch in radians, as used by the cameras
+    pub fn pitch_radians(&self) -> f32 {
+        self.pitch.to_radians()
+    }
+}
+
+/// Where the camera starts and where "home" is
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct StartView {
+    /// Configured start location from the command line or config file
+    pub home: CameraPose,
+    /// Pose at launch: the restored session unless a start location was given on the command line
+    pub initial: CameraPose,
+    /// Place to fly to from there right after launch (--fly-to)
+    pub fly_to: Option<FlyToRequested>,
+}
@@ -0,0 +1,139 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+/// The set of tiles a registry entry belongs to
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum TileSource {
+    /// OSM raster tiles selected for the current view
+    Focus,
+    /// Low zoom OSM raster tiles kept below the view for context
+    Background,
+}
+
+/// Registry key of a tile: its source and z/x/y, with x as an unwrapped column
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct TileKey {
+    pub source: TileSource,
+    pub x: i32,
+    pub y: u32,
+    pub zoom: u32,
+}
+
+impl TileKey {
+    pub fn new(source: TileSource, x: i32, y: u32, zoom: u32) -> Self {
+        Self { source, x, y, zoom }
+    }
+
+    /// The tile one zoom level up that contains this one
+    pub fn parent(&self) -> Option<TileKey> {
+        if self.zoom == 0 {
+            return None;
+        }
+        // div_euclid keeps unwrapped columns west of the antimeridian in the right parent
+        Some(TileKey::new(self.source, self.x.div_euclid(2), self.y / 2, self.zoom - 1))
+    }
+
+    /// The four tiles one zoom level down that make up this one
+    pub fn children(&self) -> [TileKey; 4] {
+        let (x, y, zoom) = (self.x * 2, self.y * 2, self.zoom + 1);
+        [
+            TileKey::new(self.source, x, y, zoom),
+            TileKey::new(self.source, x + 1, y, zoom),
+            TileKey::new(self.source, x, y + 1, zoom),
+            TileKey::new(self.source, x + 1, y + 1, zoom),
+        ]
+    }
+}
+
+/// Registry entry of a tile; the full lifecycle lives in the tile's `TileState` component
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TileEntry {
+    pub entity: Entity,
+    /// Whether the real image is on screen, so the tile covers its area
+    pub ready: bool,
+}
+
+/// Index of all requested and spawned tiles
+///
+/// Tiles are stored by source and z/x/y, and every quadtree node knows how many registered
+/// tiles lie below it, so coverage queries only descend where tiles actually exist.
+#[derive(Resource, Default)]
+pub struct TileRegistry {
+    tiles: HashMap<TileKey, TileEntry>,
+    entities: HashMap<Entity, TileKey>,
+    // Number of registered tiles strictly below each quadtree node
+    descendants: HashMap<TileKey, u32>,
+}
+
+impl TileRegistry {
+    pub fn contains(&self, key: &TileKey) -> bool {
+        self.tiles.contains_key(key)
+    }
+
+    /// Register the entity spawned for a newly requested tile
+    pub fn insert(&mut self, key: TileKey, entity: Entity) {
+        match self.tiles.insert(key, TileEntry { entity, ready: false }) {
+            Some(previous) => {
+                self.entities.remove(&previous.entity);
+            }
+            None => self.update_ancestors(&key, |count| count + 1),
+        }
+        self.entities.insert(entity, key);
+    }
+
+    /// Mark a tile as showing its real image
+    pub fn set_ready(&mut self, key: &TileKey) {
+        if let Some(entry) = self.tiles.get_mut(key) {
+            entry.ready = true;
+        }
+    }
+
+    /// Forget a tile; returns its entry
+    pub fn remove(&mut self, key: &TileKey) -> Option<TileEntry> {
+        let entry = self.tiles.remove(key)?;
+        self.entities.remove(&entry.entity);
+        self.update_ancestors(key, |count| count.saturating_sub(1));
+        Some(entry)
+    }
+
+    /// Forget the tile spawned as this entity; returns its key
+    pub fn remove_entity(&mut self, entity: Entity) -> Option<TileKey> {
+        let key = self.entities.get(&entity).copied()?;
+        self.remove(&key);
+        Some(key)
+    }
+
+    /// Key of the tile spawned as this entity
+    pub fn key_of(&self, entity: Entity) -> Option<TileKey> {
+        self.entities.get(&entity).copied()
+    }
+
+    /// Whether the area of a tile is fully covered by ready tiles of the same source
+    /// at the tile's zoom level or finer
+    pub fn is_covered(&self, key: &TileKey) -> bool {
+        if self.tiles.get(key).is_some_and(|entry| entry.ready) {
+            return true;
+        }
+        self.is_covered_by_children(key)
+    }
+
+    /// Whether the area of a tile is fully covered by ready tiles that are finer than it
+    pub fn is_covered_by_children(&self, key: &TileKey) -> bool {
+        self.descendants.get(key).is_some_and(|&count| count >= 4)
+            && key.children().iter().all(|child| self.is_covered(child))
+    }
+
+    // Apply a count change to every quadtree node above a tile
+    fn update_ancestors(&mut self, key: &TileKey, change: impl Fn(u32) -> u32) {
+        let mut node = key.parent();
+        while let Some(parent) = node {
+            let count = change(self.descendants.get(&parent).copied().unwrap_or(0));
+            if count == 0 {
+                self.descendants.remove(&parent);
+            } else {
+                self.descendants.insert(parent, count);
+            }
+            node = parent.parent();
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use bevy::prelude::*;
+use bevy::math::DVec3;
+use std::path::PathBuf;
+use crate::utils::geodesy::{
+    LatLon, GeoBounds, haversine_distance, vincenty_distance, initial_bearing, destination_point, geo_to_world,
+};
+use crate::utils::projection::altitude_to_world;
+
+// Track seconds before and after a moment that the direction of travel is taken over,
+// which keeps GPS jitter from swinging the follow camera around
+const BEARING_WINDOW: f64 = 4.0;
+
+/// A position of a GPS track
+#[derive(Clone, Copy, Debug)]
+pub struct TrackPoint {
+    pub position: LatLon,
+    /// Elevation above sea level in metres, 0.0 when the file has none
+    pub elevation: f64,
+    /// Seconds from the start of the track
+    pub time: f64,
+}
+
+/// Where the track is at a moment of playback
+#[derive(Clone, Copy, Debug)]
+pub struct TrackSample {
+    pub position: LatLon,
+    pub elevation: f64,
+    /// Direction of travel in degrees clockwise from north, `None` while standing still
+    pub bearing: Option<f64>,
+}
+
+/// The loaded GPS track, empty until one is given on the command line or dropped on the window
+#[derive(Resource, Debug, Default)]
+pub struct GpxTrack {
+    pub name: String,
+    pub points: Vec<TrackPoint>,
+    /// File to load at launch
+    pub path: Option<PathBuf>,
+    min_elevation: f64,
+}
+
+impl GpxTrack {
+    pub fn new(name: String, points: Vec<TrackPoint>) -> Self {
+        let min_elevation = points.iter().map(|point| point.elevation).reduce(f64::min).unwrap_or(0.0);
+        Self {
+            name,
+            points,
+            path: None,
+            min_elevation,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.points.last().map_or(0.0, |point| point.time)
+    }
+
+    /// Distance along the track in metres
+    pub fn length(&self) -> f64 {
+        self.points
+            .windows(2)
+            .map(|pair| {
+                let (from, to) = (pair[0].position, pair[1].position);
+                vincenty_distance(from, to).unwrap_or_else(|| haversine_distance(from, to))
+            })
+            .sum()
+    }
+
+    /// Smallest box around the whole track, `None` for an empty one
+    pub fn bounds(&self) -> Option<GeoBounds> {
+        GeoBounds::from_points(self.points.iter().map(|point| point.position))
+    }
+
+    /// Position along the track at `time` seconds, between the points around it
+    pub fn position_at(&self, time: f64) -> Option<(LatLon, f64)> {
+        let first = self.points.first()?;
+        let next = self.points.partition_point(|point| point.time <= time);
+        if next == 0 {
+            return Some((first.position, first.elevation));
+        }
+        let from = self.points[next - 1];
+        let Some(to) = self.points.get(next) else {
+            return Some((from.position, from.elevation));
+        };
+
+        let t = ((time - from.time) / (to.time - from.time)).clamp(0.0, 1.0);
+        let distance = haversine_distance(from.position, to.position);
+        let position = destination_point(from.position, initial_bearing(from.position, to.position), distance * t);
+        Some((position, from.elevation + (to.elevation - from.elevation) * t))
+    }
+
+    pub fn sample(&self, time: f64) -> Option<TrackSample> {
+        let (position, elevation) = self.position_at(time)?;
+        let (behind, _) = self.position_at((time - BEARING_WINDOW).max(0.0))?;
+        let (ahead, _) = self.position_at((time + BEARING_WINDOW).min(self.duration()))?;
+        // Less than a metre apart is standing still, and its bearing is noise
+        let bearing = (haversine_distance(behind, ahead) > 1.0).then(|| initial_bearing(behind, ahead));
+        Some(TrackSample { position, elevation, bearing })
+    }
+
+    /// World position of a track position, `height` metres above its lowest point
+    ///
+    /// The map is flat at sea level, so the track keeps its shape but is lowered onto it.
+    pub fn to_world(&self, position: LatLon, elevation: f64, height: f64) -> DVec3 {
+        let (x, z) = geo_to_world(position);
+        DVec3::new(x, altitude_to_world(elevation - self.min_elevation + height, position.lat), z)
+    }
+}
+
+/// Replay of the GPS track and the camera following it
+#[derive(Resource, Debug)]
+pub struct TrackPlayback {
+    pub playing: bool,
+    /// Seconds from the start of the track
+    pub time: f64,
+    /// Track seconds per real second
+    pub speed: f64,
+    /// Whether the camera follows the current position
+    pub follow: bool,
+    /// Distance of the follow camera behind the position in metres
+    pub follow_distance: f64,
+    /// Height of the follow camera above the position in metres
+    pub follow_height: f64,
+    /// Direction the follow camera looks in, in degrees clockwise from the direction of travel
+    pub heading_offset: f64,
+    /// Height of the drawn track above the map in metres
+    pub line_height: f64,
+    /// Smoothed compass heading of the follow camera in degrees
+    pub camera_heading: Option<f64>,
+}
+
+impl Default for TrackPlayback {
+    fn default() -> Self {
+        Self {
+            playing: false,
+            time: 0.0,
+            speed: 1.0,
+            follow: false,
+            follow_distance: 300.0,
+            follow_height: 150.0,
+            heading_offset: 0.0,
+            line_height: 5.0,
+            camera_heading: None,
+        }
+    }
+}
+
+impl TrackPlayback {
+    /// Whether the follow camera has the camera
+    pub fn is_following(&self) -> bool {
+        self.follow
+    }
+}
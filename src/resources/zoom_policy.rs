@@ -0,0 +1,63 @@
+use bevy::prelude::*;
+use crate::resources::constants::{DEFAULT_ZOOM_LEVEL, MIN_ZOOM_LEVEL, MAX_ZOOM_LEVEL};
+
+// How far (in zoom levels) past the halfway point the target must move before switching
+const DEFAULT_HYSTERESIS: f64 = 0.25;
+// Shortest time between two zoom level switches
+const DEFAULT_MIN_DWELL_SECS: f32 = 0.5;
+
+/// The single source of the current base zoom level
+///
+/// The camera gives a fractional target zoom; the level only changes once the target
+/// leaves the band around the current level and the current level has been held for a
+/// while, so hovering near a threshold does not flip between levels every frame.
+#[derive(Resource)]
+pub struct ZoomPolicy {
+    /// Zoom level currently used for the UI, the background layer and prefetching
+    pub current: u32,
+    /// Extra distance in zoom levels beyond the rounding point before the level switches
+    pub hysteresis: f64,
+    /// Minimum time in seconds a level is kept before switching again
+    pub min_dwell_secs: f32,
+    last_switch: Option<f32>,
+}
+
+impl Default for ZoomPolicy {
+    fn default() -> Self {
+        Self {
+            current: DEFAULT_ZOOM_LEVEL,
+            hysteresis: DEFAULT_HYSTERESIS,
+            min_dwell_secs: DEFAULT_MIN_DWELL_SECS,
+            last_switch: None,
+        }
+    }
+}
+
+impl ZoomPolicy {
+    /// Zoom level for a fractional target zoom, keeping the current level while the
+    /// target is inside its hysteresis band
+    pub fn level_for(&self, target: f64) -> u32 {
+        let target = target.clamp(MIN_ZOOM_LEVEL as f64, MAX_ZOOM_LEVEL as f64);
+        let band = 0.5 + self.hysteresis;
+        if (target - self.current as f64).abs() <= band {
+            self.current
+        } else {
+            target.round() as u32
+        }
+    }
+
+    /// Feed this frame's target zoom; returns whether the current level changed
+    pub fn update(&mut self, target: f64, now: f32) -> bool {
+        let level = self.level_for(target);
+        if level == self.current {
+            return false;
+        }
+        // The first switch is immediate, so the start position gets the right level
+        if self.last_switch.is_some_and(|last| now - last < self.min_dwell_secs) {
+            return false;
+        }
+        self.current = level;
+        self.last_switch = Some(now);
+        true
+    }
+}
@@ -0,0 +1,113 @@
+use bevy::prelude::*;
+use bevy::window::FileDragAndDrop;
+use std::fs;
+use std::path::Path;
+use crate::components::WorldPosition;
+use crate::events::FlyToRequested;
+use crate::resources::{ActionState, Bookmark, Bookmarks, CameraPose, InputAction, MouseLookState};
+use crate::utils::geojson::{bookmarks_from_geojson, bookmarks_to_geojson};
+
+/// Read the bookmarks saved in earlier runs
+pub fn load_bookmarks(mut bookmarks: ResMut<Bookmarks>) {
+    if !bookmarks.path.exists() {
+        return;
+    }
+    match read_bookmarks(&bookmarks.path) {
+        Ok(loaded) => {
+            info!("Loaded {} bookmarks from {}", loaded.len(), bookmarks.path.display());
+            bookmarks.bookmarks = loaded;
+        }
+        Err(e) => warn!("Failed to read bookmarks {}: {}", bookmarks.path.display(), e),
+    }
+}
+
+/// Save the camera pose as a bookmark (B), fly to the next or previous one (. and ,),
+/// delete the selected one (Delete) and export them all (E)
+pub fn bookmark_actions(
+    action_state: Res<ActionState>,
+    mouse_look_state: Res<MouseLookState>,
+    mut bookmarks: ResMut<Bookmarks>,
+    mut fly_to_events: EventWriter<FlyToRequested>,
+    camera_query: Query<&WorldPosition, With<Camera3d>>,
+) {
+    if action_state.just_pressed(InputAction::SaveBookmark) {
+        if let Ok(camera_world) = camera_query.get_single() {
+            let pose = CameraPose::from_camera(camera_world.0, mouse_look_state.yaw, mouse_look_state.pitch);
+            let bookmark = bookmarks.add(pose);
+            info!("Saved {} at {:.5}, {:.5}", bookmark.name, bookmark.pose.lat, bookmark.pose.lon);
+        }
+    }
+
+    let step = if action_state.just_pressed(InputAction::NextBookmark) {
+        1
+    } else if action_state.just_pressed(InputAction::PreviousBookmark) {
+        -1
+    } else {
+        0
+    };
+    if step != 0 {
+        if let Some(bookmark) = bookmarks.step(step) {
+            info!("Flying to {}", bookmark.name);
+            fly_to_events.send(FlyToRequested::to_pose(&bookmark.pose));
+        }
+    }
+
+    if action_state.just_pressed(InputAction::DeleteBookmark) {
+        if let Some(removed) = bookmarks.remove_selected() {
+            info!("Deleted {}", removed.name);
+        }
+    }
+
+    if action_state.just_pressed(InputAction::ExportBookmarks) {
+        match write_bookmarks(&bookmarks.export_path, &bookmarks.bookmarks) {
+            Ok(()) => info!("Exported {} bookmarks to {}", bookmarks.bookmarks.len(), bookmarks.export_path.display()),
+            Err(e) => warn!("Failed to export bookmarks to {}: {}", bookmarks.export_path.display(), e),
+        }
+    }
+}
+
+/// Import the bookmarks of a GeoJSON file dropped on the window
+pub fn import_dropped_bookmarks(
+    mut drop_events: EventReader<FileDragAndDrop>,
+    mut bookmarks: ResMut<Bookmarks>,
+) {
+    for event in drop_events.read() {
+        let FileDragAndDrop::DroppedFile { path_buf, .. } = event else {
+            continue;
+        };
+        let is_geojson = path_buf
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("geojson") || extension.eq_ignore_ascii_case("json"));
+        if !is_geojson {
+            continue;
+        }
+
+        match read_bookmarks(path_buf) {
+            Ok(imported) => {
+                let added = bookmarks.merge(imported);
+                info!("Imported {} bookmarks from {}", added, path_buf.display());
+            }
+            Err(e) => warn!("Failed to import bookmarks from {}: {}", path_buf.display(), e),
+        }
+    }
+}
+
+/// Write the bookmarks to their GeoJSON file whenever they change
+pub fn save_bookmarks(bookmarks: Res<Bookmarks>) {
+    // Loading them at startup is not a change worth writing
+    if !bookmarks.is_changed() || bookmarks.is_added() {
+        return;
+    }
+
+    if let Err(e) = write_bookmarks(&bookmarks.path, &bookmarks.bookmarks) {
+        warn!("Failed to save bookmarks to {}: {}", bookmarks.path.display(), e);
+    }
+}
+
+fn read_bookmarks(path: &Path) -> Result<Vec<Bookmark>, anyhow::Error> {
+    bookmarks_from_geojson(&fs::read_to_string(path)?)
+}
+
+fn write_bookmarks(path: &Path, bookmarks: &[Bookmark]) -> Result<(), anyhow::Error> {
+    Ok(fs::write(path, bookmarks_to_geojson(bookmarks)?)?)
+}
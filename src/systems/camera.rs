@@ -1,6 +1,15 @@
 use bevy::prelude::*;
 use bevy::input::mouse::MouseMotion;
-use crate::resources::MouseLookState;
+use crate::components::WorldPosition;
+use crate::resources::{MouseLookState, OrbitCamera, CameraMode, WalkState, FlyToState, ActionState, ControlSettings, InputAction, CameraPathPlayer, TrackPlayback, LivePosition};
+use crate::utils::picking::GroundSampler;
+use crate::utils::projection::{world_to_lat_lon, mercator_scale};
+
+// Fastest fall of the walking camera, in metres per second
+const TERMINAL_SPEED: f32 = 55.0;
+// Highest the ground rises above the flat map, in metres; the fly camera doesn't look for
+// the ground when further up than this, as that takes a ray cast against the tiles
+const MAX_GROUND_HEIGHT: f32 = 9000.0;
 
 /// System to capture mouse movement for camera look
 pub fn mouse_look_system(
@@ -12,33 +21,55 @@ pub fn mouse_look_system(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn camera_movement(
     time: Res<Time>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+    action_state: Res<ActionState>,
+    control_settings: Res<ControlSettings>,
     mut mouse_look_state: ResMut<MouseLookState>,
-    mut query: Query<&mut Transform, With<Camera3d>>,
+    orbit_camera: Res<OrbitCamera>,
+    camera_mode: Res<CameraMode>,
+    fly_to: Res<FlyToState>,
+    path_player: Res<CameraPathPlayer>,
+    track_playback: Res<TrackPlayback>,
+    live_position: Res<LivePosition>,
+    mut walk_state: ResMut<WalkState>,
+    mut ground: GroundSampler,
+    mut query: Query<(&mut Transform, &WorldPosition), With<Camera3d>>,
 ) {
-    // Movement settings
-    let base_movement_speed = 5.0;
-    let boost_multiplier = 3.0; // Speed multiplier when shift is pressed
-    let look_sensitivity = 0.002;
+    // The orbit camera takes over while the globe is visible, the map camera when selected,
+    // and a fly-to, camera path or follow camera while it runs
+    if orbit_camera.active
+        || !camera_mode.uses_mouse_look()
+        || fly_to.is_flying()
+        || path_player.is_playing()
+        || track_playback.is_following()
+        || live_position.is_following()
+    {
+        return;
+    }
+
     let delta = time.delta_secs();
 
-    // Apply mouse motion to update camera rotation (looking around)
-    if !mouse_look_state.mouse_motion.is_nan() && mouse_look_state.mouse_motion.length_squared() > 0.0 {
-        // Update pitch and yaw based on mouse motion
-        mouse_look_state.yaw -= mouse_look_state.mouse_motion.x * look_sensitivity;
-        mouse_look_state.pitch -= mouse_look_state.mouse_motion.y * look_sensitivity;
+    // Mouse motion plus the look stick, which turns at a rate rather than by a distance
+    let mut look = Vec2::ZERO;
+    if !mouse_look_state.mouse_motion.is_nan() {
+        look = -mouse_look_state.mouse_motion * control_settings.look_sensitivity;
+    }
+    look += action_state.look_stick * Vec2::new(-1.0, 1.0) * control_settings.gamepad_look_speed * delta;
+    if control_settings.invert_look_y {
+        look.y = -look.y;
+    }
+    mouse_look_state.mouse_motion = Vec2::ZERO;
 
+    if look != Vec2::ZERO {
+        mouse_look_state.yaw += look.x;
         // Clamp pitch to prevent the camera from flipping
-        mouse_look_state.pitch = mouse_look_state.pitch.clamp(-1.5, 1.5);
-
-        // Reset motion for next frame
-        mouse_look_state.mouse_motion = Vec2::ZERO;
+        mouse_look_state.pitch = (mouse_look_state.pitch + look.y).clamp(-1.5, 1.5);
     }
 
     // Apply rotation to camera transform
-    let mut transform = query.single_mut();
+    let (mut transform, camera_world) = query.single_mut();
 
     // Create rotation quaternion from pitch and yaw
     let yaw_rotation = Quat::from_rotation_y(mouse_look_state.yaw);
@@ -47,69 +78,149 @@ pub fn camera_movement(
     // Combine rotations and set the camera's rotation
     transform.rotation = yaw_rotation * pitch_rotation;
 
-    // Calculate movement direction based on camera orientation
+    // Movement relative to the camera direction, from the bound keys and the move stick
     let forward = *transform.forward();
     let right = *transform.right();
-    let mut movement = Vec3::ZERO;
+    let forward_input = action_state.axis(InputAction::MoveBackward, InputAction::MoveForward) + action_state.move_stick.y;
+    let right_input = action_state.axis(InputAction::MoveLeft, InputAction::MoveRight) + action_state.move_stick.x;
+    let up_input = action_state.axis(InputAction::MoveDown, InputAction::MoveUp);
+    let mut movement = forward * forward_input + right * right_input;
+    movement.y += up_input;
 
-    // Apply movement based on key input (relative to camera direction)
-    if keyboard_input.pressed(KeyCode::KeyW) {
-        movement += forward;
-    }
-    if keyboard_input.pressed(KeyCode::KeyS) {
-        movement -= forward;
-    }
-    if keyboard_input.pressed(KeyCode::KeyA) {
-        movement -= right;
-    }
-    if keyboard_input.pressed(KeyCode::KeyD) {
-        movement += right;
+    // Full input in any direction moves at full speed, partial stick or trigger input slower
+    let movement = movement.clamp_length_max(1.0);
+
+    let boost = if action_state.pressed(InputAction::Boost) {
+        control_settings.boost_multiplier
+    } else {
+        1.0
+    };
+    // Real metres are stretched by Mercator, so heights and speeds given in metres are scaled
+    let (lat, _) = world_to_lat_lon(camera_world.0.x, camera_world.0.z);
+    let metre = mercator_scale(lat) as f32;
+
+    if *camera_mode == CameraMode::Walk {
+        // Walk along the ground in the direction the camera faces, whatever the pitch
+        let heading = Quat::from_rotation_y(mouse_look_state.yaw);
+        let walk = (heading * Vec3::NEG_Z * forward_input + heading * Vec3::X * right_input).clamp_length_max(1.0);
+        transform.translation += walk * control_settings.walk_speed * metre * boost * delta;
+
+        if walk_state.grounded && action_state.just_pressed(InputAction::MoveUp) {
+            walk_state.vertical_speed = control_settings.jump_speed * metre;
+            walk_state.grounded = false;
+        }
+        walk_state.vertical_speed = (walk_state.vertical_speed - control_settings.gravity * metre * delta)
+            .max(-TERMINAL_SPEED * metre);
+        transform.translation.y += walk_state.vertical_speed * delta;
+
+        // Stand on the ground at eye height; the first frame puts the camera there from any height
+        let eye = ground.height_under(transform.translation) + control_settings.eye_height * metre;
+        if transform.translation.y <= eye || !walk_state.landed {
+            transform.translation.y = eye;
+            walk_state.vertical_speed = 0.0;
+            walk_state.grounded = true;
+            walk_state.landed = true;
+        } else {
+            walk_state.grounded = false;
+        }
+        return;
     }
 
-    // Apply up/down movement
-    if keyboard_input.pressed(KeyCode::Space) {
-        movement.y += 1.0;
+    // Faster when higher up, so the speed feels the same at street level and from orbit
+    let speed = control_settings.fly_speed(transform.translation.y);
+
+    // Apply movement to position
+    transform.translation += movement * speed * boost * delta;
+
+    // Keep clear of the ground, which may rise above the flat map where there is terrain
+    let clearance = control_settings.min_fly_altitude * metre;
+    if transform.translation.y < (MAX_GROUND_HEIGHT * metre) + clearance {
+        let lowest = ground.height_under(transform.translation) + clearance;
+        transform.translation.y = transform.translation.y.max(lowest);
     }
-    if keyboard_input.pressed(KeyCode::ControlLeft) { // Changed from ShiftLeft to ControlLeft for down movement
-        movement.y -= 1.0;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::render::mesh::MeshAabb;
+    use crate::components::TileCoords;
+
+    const GROUND: f32 = 50.0;
+
+    // The camera movement system over a tile mesh raised GROUND units above the map
+    fn app(camera_mode: CameraMode, camera_y: f32) -> App {
+        let mut app = App::new();
+        app.insert_resource(Time::<()>::default())
+            .init_resource::<Assets<Mesh>>()
+            .init_resource::<ActionState>()
+            .init_resource::<MouseLookState>()
+            .init_resource::<OrbitCamera>()
+            .init_resource::<FlyToState>()
+            .init_resource::<CameraPathPlayer>()
+            .init_resource::<TrackPlayback>()
+            .init_resource::<LivePosition>()
+            .init_resource::<WalkState>()
+            .insert_resource(ControlSettings::default())
+            .insert_resource(camera_mode)
+            .add_systems(Update, camera_movement);
+
+        let mesh = Mesh::from(Plane3d::default().mesh().size(1000.0, 1000.0));
+        let aabb = mesh.compute_aabb().unwrap();
+        let mesh = app.world_mut().resource_mut::<Assets<Mesh>>().add(mesh);
+        let transform = Transform::from_xyz(0.0, GROUND, 0.0);
+        app.world_mut().spawn((
+            Mesh3d(mesh),
+            aabb,
+            transform,
+            GlobalTransform::from(transform),
+            InheritedVisibility::VISIBLE,
+            ViewVisibility::default(),
+            TileCoords { x: 0, y: 0, zoom: 0, last_used: 0.0 },
+        ));
+        app.world_mut().spawn((
+            Camera3d::default(),
+            Transform::from_xyz(10.0, camera_y, -20.0),
+            WorldPosition::default(),
+        ));
+        app
     }
 
-    // Normalize movement vector if it's not zero
-    if movement != Vec3::ZERO {
-        movement = movement.normalize();
+    fn camera_y(app: &mut App) -> f32 {
+        app.world_mut().query_filtered::<&Transform, With<Camera3d>>().single(app.world()).translation.y
     }
 
-    // Calculate altitude-based speed multiplier
-    // As camera height increases, speed increases proportionally
-    let height = transform.translation.y.max(1.0); // Ensure minimum height of 1.0
-    let altitude_factor = {
-        if height <= 5.0 {
-            1.0 // Base speed at low heights
-        } else if height <= 20.0 {
-            // Linear scaling for medium heights: 1.0 - 4.0x
-            1.0 + (height - 5.0) / 5.0
-        } else if height <= 50.0 {
-            // Medium-high altitudes: 4.0 - 8.0x
-            4.0 + (height - 20.0) / 10.0
-        } else if height <= 100.0 {
-            // High altitudes: 8.0 - 15.0x
-            8.0 + (height - 50.0) / 10.0
-        } else {
-            // Very high altitudes: 15.0x and above
-            15.0 + (height - 100.0) / 20.0
-        }
-    };
+    // World units per metre where the test camera is
+    fn metre() -> f32 {
+        let (lat, _) = world_to_lat_lon(0.0, 0.0);
+        mercator_scale(lat) as f32
+    }
 
-    // Check if boost mode (Shift) is active
-    let boost = if keyboard_input.pressed(KeyCode::ShiftLeft) {
-        boost_multiplier
-    } else {
-        1.0
-    };
+    #[test]
+    fn walking_stands_at_eye_height_on_a_raised_tile() {
+        let mut app = app(CameraMode::Walk, 400.0);
+        app.update();
+        let eye = GROUND + ControlSettings::default().eye_height * metre();
+        assert!((camera_y(&mut app) - eye).abs() < 1e-3, "{} != {eye}", camera_y(&mut app));
+        assert!(app.world().resource::<WalkState>().grounded);
+
+        // Stays there rather than sinking into the tile
+        app.update();
+        assert!((camera_y(&mut app) - eye).abs() < 1e-3);
+    }
 
-    // Calculate final movement speed using both altitude and boost factors
-    let movement_speed = base_movement_speed * altitude_factor * boost;
+    #[test]
+    fn flying_is_kept_clear_of_a_raised_tile() {
+        let mut app = app(CameraMode::Fly, GROUND + 0.1);
+        app.update();
+        let lowest = GROUND + ControlSettings::default().min_fly_altitude * metre();
+        assert!((camera_y(&mut app) - lowest).abs() < 1e-3, "{} != {lowest}", camera_y(&mut app));
+    }
 
-    // Apply movement to position
-    transform.translation += movement * movement_speed * delta;
-} 
\ No newline at end of file
+    #[test]
+    fn flying_above_the_ground_is_left_alone() {
+        let mut app = app(CameraMode::Fly, 300.0);
+        app.update();
+        assert_eq!(camera_y(&mut app), 300.0);
+    }
+}
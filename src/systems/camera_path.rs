@@ -0,0 +1,261 @@
+use bevy::prelude::*;
+use crate::components::WorldPosition;
+use crate::resources::{
+    ActionState, CameraKeyframe, CameraPath, CameraPathMode, CameraPathPlayer, FloatingOrigin, FlyToState,
+    InputAction, Interpolation, MouseLookState, OrbitCamera,
+};
+
+/// Read the camera path saved in an earlier run, and start playing it if asked to
+pub fn load_camera_path(mut camera_path: ResMut<CameraPath>, mut player: ResMut<CameraPathPlayer>) {
+    if camera_path.path.exists() {
+        match camera_path.load() {
+            Ok(()) => info!("Loaded camera path with {} keyframes from {}", camera_path.keyframes.len(), camera_path.path.display()),
+            Err(e) => warn!("Failed to read camera path {}: {}", camera_path.path.display(), e),
+        }
+    }
+
+    if player.play_at_launch {
+        if camera_path.keyframes.is_empty() {
+            warn!("No camera path to play in {}", camera_path.path.display());
+        } else {
+            player.mode = CameraPathMode::Playing { frame: 0, elapsed: 0.0 };
+        }
+    }
+}
+
+// Seconds within which R has to be pressed again to record over an authored path
+const CONFIRM_RECORDING_SECONDS: f64 = 3.0;
+// Seconds a keyframe gap changes by per key press
+const RETIME_STEP: f64 = 0.5;
+
+/// Start and stop recording (R) and playback (P)
+///
+/// Recording over an authored path needs R pressed twice, as it throws the keyframes away.
+pub fn camera_path_actions(
+    time: Res<Time>,
+    action_state: Res<ActionState>,
+    mut camera_path: ResMut<CameraPath>,
+    mut player: ResMut<CameraPathPlayer>,
+    mut fly_to: ResMut<FlyToState>,
+    mut orbit_camera: ResMut<OrbitCamera>,
+    // Until when (elapsed seconds) another R records over the authored path
+    mut confirm_until: Local<Option<f64>>,
+) {
+    if action_state.just_pressed(InputAction::ToggleRecording) {
+        let authored = camera_path.interpolation == Interpolation::CatmullRom && !camera_path.keyframes.is_empty();
+        let now = time.elapsed_secs_f64();
+        if player.is_recording() {
+            player.mode = CameraPathMode::Idle;
+            save(&camera_path);
+            info!("Recorded {} frames ({:.1} s)", camera_path.keyframes.len(), camera_path.duration());
+        } else if authored && !confirm_until.is_some_and(|until| now <= until) {
+            *confirm_until = Some(now + CONFIRM_RECORDING_SECONDS);
+            warn!("Recording replaces the authored path of {} keyframes, press R again to go ahead",
+                  camera_path.keyframes.len());
+        } else {
+            *confirm_until = None;
+            camera_path.interpolation = Interpolation::Exact;
+            camera_path.keyframes.clear();
+            player.selected = None;
+            player.mode = CameraPathMode::Recording { elapsed: 0.0 };
+            info!("Recording camera path");
+        }
+    }
+
+    if action_state.just_pressed(InputAction::TogglePlayback) {
+        if player.is_playing() {
+            stop_playback(&mut player, &mut orbit_camera);
+            info!("Camera path playback stopped");
+        } else if camera_path.keyframes.is_empty() {
+            info!("No camera path to play");
+        } else {
+            if player.is_recording() {
+                save(&camera_path);
+            }
+            // The path owns the camera until it ends
+            fly_to.flight = None;
+            player.mode = CameraPathMode::Playing { frame: 0, elapsed: 0.0 };
+            info!("Playing camera path ({:.1} s)", camera_path.duration());
+        }
+    }
+}
+
+/// Author a path from keyframes: add one after the selected keyframe (K), remove (Backspace)
+/// or replace (U) the selected one, select the previous or next one (Page Up and Page Down),
+/// which puts the camera there, and shorten or lengthen the time before it (- and =)
+#[allow(clippy::too_many_arguments)]
+pub fn edit_camera_path(
+    action_state: Res<ActionState>,
+    floating_origin: Res<FloatingOrigin>,
+    mut camera_path: ResMut<CameraPath>,
+    mut player: ResMut<CameraPathPlayer>,
+    mut fly_to: ResMut<FlyToState>,
+    mut mouse_look_state: ResMut<MouseLookState>,
+    mut camera_query: Query<(&mut Transform, &WorldPosition), With<Camera3d>>,
+) {
+    if player.mode != CameraPathMode::Idle {
+        return;
+    }
+    let Ok((mut transform, camera_world)) = camera_query.get_single_mut() else {
+        return;
+    };
+    let count = camera_path.keyframes.len();
+    // Editing continues from the selected keyframe, or else the last one
+    let selected = player.selected.filter(|&index| index < count).or(count.checked_sub(1));
+
+    if action_state.just_pressed(InputAction::AddKeyframe) {
+        // Keyframes start a new authored path rather than extending a recording
+        if camera_path.interpolation == Interpolation::Exact {
+            camera_path.interpolation = Interpolation::CatmullRom;
+            camera_path.keyframes.clear();
+        }
+        let index = selected.map_or(0, |selected| selected + 1).min(camera_path.keyframes.len());
+        camera_path.insert_keyframe(index, player.keyframe_spacing, camera_world.0, transform.rotation);
+        player.selected = Some(index);
+        save(&camera_path);
+        info!("Added keyframe {} of {} at {:.1} s", index + 1, camera_path.keyframes.len(), camera_path.keyframes[index].time);
+        return;
+    }
+
+    // The rest only edits authored paths
+    if camera_path.interpolation != Interpolation::CatmullRom {
+        return;
+    }
+    let Some(selected) = selected else {
+        return;
+    };
+
+    if action_state.just_pressed(InputAction::RemoveKeyframe) {
+        camera_path.remove_keyframe(selected);
+        player.selected = selected.checked_sub(1).or((!camera_path.keyframes.is_empty()).then_some(0));
+        save(&camera_path);
+        info!("Removed keyframe {}, {} left", selected + 1, camera_path.keyframes.len());
+        // The other edits would go by the keyframes from before the removal
+        return;
+    }
+
+    if action_state.just_pressed(InputAction::ReplaceKeyframe) {
+        let time = camera_path.keyframes[selected].time;
+        camera_path.keyframes[selected] = CameraKeyframe::new(time, camera_world.0, transform.rotation);
+        save(&camera_path);
+        info!("Replaced keyframe {} at {:.1} s", selected + 1, time);
+    }
+
+    let step = if action_state.just_pressed(InputAction::NextKeyframe) {
+        Some((selected + 1).min(count - 1))
+    } else if action_state.just_pressed(InputAction::PreviousKeyframe) {
+        Some(selected.saturating_sub(1))
+    } else {
+        None
+    };
+    if let Some(index) = step {
+        player.selected = Some(index);
+        let keyframe = camera_path.keyframes[index];
+        // Put the camera on the keyframe, so it can be adjusted and replaced from there
+        fly_to.flight = None;
+        transform.translation = floating_origin.world_to_render(keyframe.position());
+        transform.rotation = keyframe.rotation();
+        let (yaw, pitch, _) = keyframe.rotation().to_euler(EulerRot::YXZ);
+        mouse_look_state.yaw = yaw;
+        mouse_look_state.pitch = pitch;
+        info!("Keyframe {} of {} at {:.1} s", index + 1, count, keyframe.time);
+    }
+
+    let retime = if action_state.just_pressed(InputAction::LengthenKeyframeGap) {
+        RETIME_STEP
+    } else if action_state.just_pressed(InputAction::ShortenKeyframeGap) {
+        -RETIME_STEP
+    } else {
+        0.0
+    };
+    if retime != 0.0 {
+        let index = player.selected.unwrap_or(selected);
+        match camera_path.retime_keyframe(index, retime) {
+            Some(gap) => {
+                save(&camera_path);
+                info!("Keyframe {} now {:.1} s after the one before, path {:.1} s", index + 1, gap, camera_path.duration());
+            }
+            None => info!("The first keyframe always starts the path"),
+        }
+    }
+}
+
+/// Add the camera pose of this frame to the recording
+pub fn record_camera_path(
+    time: Res<Time>,
+    mut camera_path: ResMut<CameraPath>,
+    mut player: ResMut<CameraPathPlayer>,
+    camera_query: Query<(&Transform, &WorldPosition), With<Camera3d>>,
+) {
+    let CameraPathMode::Recording { elapsed } = &mut player.mode else {
+        return;
+    };
+    let Ok((transform, camera_world)) = camera_query.get_single() else {
+        return;
+    };
+
+    camera_path.keyframes.push(CameraKeyframe::new(*elapsed, camera_world.0, transform.rotation));
+    *elapsed += time.delta_secs_f64();
+}
+
+/// Drive the camera along the camera path
+///
+/// This sets the same transform the other camera controllers do, so the tile systems see
+/// exactly what they saw while recording.
+#[allow(clippy::too_many_arguments)]
+pub fn play_camera_path(
+    time: Res<Time>,
+    camera_path: Res<CameraPath>,
+    floating_origin: Res<FloatingOrigin>,
+    mut player: ResMut<CameraPathPlayer>,
+    mut orbit_camera: ResMut<OrbitCamera>,
+    mut mouse_look_state: ResMut<MouseLookState>,
+    mut app_exit: EventWriter<AppExit>,
+    mut camera_query: Query<&mut Transform, With<Camera3d>>,
+) {
+    let CameraPathMode::Playing { frame, elapsed } = player.mode else {
+        return;
+    };
+
+    let pose = match camera_path.interpolation {
+        Interpolation::Exact => camera_path.keyframes.get(frame).map(|k| (k.position(), k.rotation())),
+        Interpolation::CatmullRom if elapsed <= camera_path.duration() => camera_path.sample(elapsed),
+        Interpolation::CatmullRom => None,
+    };
+    let Some((position, rotation)) = pose else {
+        stop_playback(&mut player, &mut orbit_camera);
+        info!("Camera path playback finished");
+        if player.exit_after_playback {
+            app_exit.send(AppExit::Success);
+        }
+        return;
+    };
+
+    if let Ok(mut transform) = camera_query.get_single_mut() {
+        transform.translation = floating_origin.world_to_render(position);
+        transform.rotation = rotation;
+    }
+    // The fly and map cameras continue from these angles afterwards
+    let (yaw, pitch, _) = rotation.to_euler(EulerRot::YXZ);
+    mouse_look_state.yaw = yaw;
+    mouse_look_state.pitch = pitch;
+    // Motion gathered during playback must not turn the camera afterwards
+    mouse_look_state.mouse_motion = Vec2::ZERO;
+
+    player.mode = CameraPathMode::Playing {
+        frame: frame + 1,
+        elapsed: elapsed + time.delta_secs_f64(),
+    };
+}
+
+fn stop_playback(player: &mut CameraPathPlayer, orbit_camera: &mut OrbitCamera) {
+    player.mode = CameraPathMode::Idle;
+    // Let the orbit camera pick up from the current pose if the globe is in view
+    orbit_camera.active = false;
+}
+
+fn save(camera_path: &CameraPath) {
+    if let Err(e) = camera_path.save() {
+        warn!("Failed to save camera path to {}: {}", camera_path.path.display(), e);
+    }
+}
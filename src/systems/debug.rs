@@ -1,14 +1,18 @@
 use bevy::prelude::*;
-use crate::resources::{OSMData, DebugSettings};
-use crate::components::{TileCoords};
+use bevy::math::DVec3;
+use crate::resources::{DebugSettings, ZoomPolicy, ActionState, InputAction};
+use crate::components::{TileCoords, WorldPosition};
+use crate::events::{TileRequested, TileLoaded, TileFailed, TileEvicted};
+use crate::debug_log;
 use crate::utils::coordinate_conversion::world_to_tile_coords;
+use crate::utils::projection::{world_altitude, world_to_lat_lon, ground_resolution};
 
 /// System to toggle debug mode with the 1 key
 pub fn toggle_debug_mode(
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+    action_state: Res<ActionState>,
     mut debug_settings: ResMut<DebugSettings>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::Digit1) {
+    if action_state.just_pressed(InputAction::ToggleDebug) {
         debug_settings.debug_mode = !debug_settings.debug_mode;
         info!("Debug mode: {}", if debug_settings.debug_mode { "ON" } else { "OFF" });
     }
@@ -16,10 +20,10 @@ pub fn toggle_debug_mode(
 
 /// Debug system to print information about loaded tiles
 pub fn debug_info(
-    osm_data: Res<OSMData>,
+    zoom_policy: Res<ZoomPolicy>,
     debug_settings: Res<DebugSettings>,
     time: Res<Time>,
-    camera_query: Query<&Transform, With<Camera3d>>,
+    camera_query: Query<&WorldPosition, With<Camera3d>>,
     tile_query: Query<&TileCoords>,
 ) {
     // Skip if debug mode is disabled
@@ -32,24 +36,49 @@ pub fn debug_info(
         return;
     }
 
-    if let Ok(camera_transform) = camera_query.get_single() {
-        let x = camera_transform.translation.x;
-        let y = camera_transform.translation.y;
-        let z = camera_transform.translation.z;
+    if let Ok(camera_world) = camera_query.get_single() {
+        // Report the precise world position rather than the render-space translation
+        let DVec3 { x, y, z } = camera_world.0;
         
         // Current tile at current zoom level
-        let (tile_x, tile_y) = world_to_tile_coords(x, z, osm_data.current_zoom);
+        let (tile_x, tile_y) = world_to_tile_coords(x, z, zoom_policy.current);
+        let (lat, _) = world_to_lat_lon(x, z);
         
         // Count active tiles
         let active_tiles = tile_query.iter().count();
         
         // Debug info
         info!(
-            "Pos: ({:.1}, {:.1}, {:.1}) | Zoom: {} | Tile: {},{} | Active tiles: {}",
+            "Pos: ({:.1}, {:.1}, {:.1}) | Alt: {:.1} m | Zoom: {} ({:.2} m/px) | Tile: {},{} | Active tiles: {}",
             x, y, z,
-            osm_data.current_zoom,
+            world_altitude(camera_world.0),
+            zoom_policy.current,
+            ground_resolution(lat, zoom_policy.current),
             tile_x, tile_y,
             active_tiles
         );
     }
-} 
\ No newline at end of file
+}
+
+/// Debug system to log tile lifecycle events
+pub fn log_tile_events(
+    debug_settings: Res<DebugSettings>,
+    mut requested_events: EventReader<TileRequested>,
+    mut loaded_events: EventReader<TileLoaded>,
+    mut failed_events: EventReader<TileFailed>,
+    mut evicted_events: EventReader<TileEvicted>,
+) {
+    for event in requested_events.read() {
+        debug_log!(debug_settings, "Tile requested: {:?} ({:?})", event.key, event.entity);
+    }
+    for event in loaded_events.read() {
+        debug_log!(debug_settings, "Tile loaded: {:?} ({:?})", event.key, event.entity);
+    }
+    for event in failed_events.read() {
+        debug_log!(debug_settings, "Tile failed: {:?} ({:?}) after {} retries: {}",
+                  event.key, event.entity, event.retries, event.reason);
+    }
+    for event in evicted_events.read() {
+        debug_log!(debug_settings, "Tile evicted: {:?} ({:?})", event.key, event.entity);
+    }
+}
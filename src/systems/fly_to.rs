@@ -0,0 +1,233 @@
+use bevy::prelude::*;
+use bevy::input::mouse::MouseWheel;
+use bevy::math::DVec3;
+use std::f32::consts::{PI, TAU};
+use crate::components::WorldPosition;
+use crate::events::{FlyToRequested, FlyToArrived};
+use crate::resources::{FlyToState, Flight, FloatingOrigin, MouseLookState, OrbitCamera, StartView, ActionState, InputAction, CameraPathPlayer, TrackPlayback, LivePosition};
+use crate::utils::flight_path::SmoothZoomPath;
+use crate::utils::picking::ray_ground_intersection;
+use crate::utils::projection::{lat_lon_to_world, altitude_to_world, WORLD_SIZE};
+
+// Flight time per unit of path length, and the limits on the total
+const SECONDS_PER_PATH_UNIT: f32 = 0.8;
+const MIN_FLIGHT_SECONDS: f32 = 1.0;
+const MAX_FLIGHT_SECONDS: f32 = 10.0;
+// Pitch range at the destination, so there is always ground in the middle of the view
+const MIN_FLIGHT_PITCH: f32 = -1.55;
+const MAX_FLIGHT_PITCH: f32 = -0.2;
+// The ground in view only counts as the start of the flight when it is this close, in camera heights
+const MAX_START_CENTER_DISTANCE: f32 = 10.0;
+// Mouse motion in pixels per frame that cancels a flight
+const CANCEL_MOTION: f32 = 4.0;
+
+/// Fly back to the configured start location with the H key
+pub fn fly_home(
+    action_state: Res<ActionState>,
+    start_view: Res<StartView>,
+    mut fly_to_events: EventWriter<FlyToRequested>,
+) {
+    if action_state.just_pressed(InputAction::FlyHome) {
+        fly_to_events.send(FlyToRequested::to_pose(&start_view.home));
+    }
+}
+
+/// Fly to the place given with --fly-to once the camera is in place
+pub fn fly_to_at_launch(
+    start_view: Res<StartView>,
+    mut fly_to_events: EventWriter<FlyToRequested>,
+) {
+    if let Some(request) = start_view.fly_to {
+        fly_to_events.send(request);
+    }
+}
+
+/// Start a flight for the latest fly-to request, replacing any flight in progress
+#[allow(clippy::too_many_arguments)]
+pub fn start_fly_to(
+    mut requests: EventReader<FlyToRequested>,
+    mut fly_to: ResMut<FlyToState>,
+    floating_origin: Res<FloatingOrigin>,
+    mouse_look_state: Res<MouseLookState>,
+    path_player: Res<CameraPathPlayer>,
+    track_playback: Res<TrackPlayback>,
+    live_position: Res<LivePosition>,
+    camera_query: Query<(&Transform, &Projection, &WorldPosition), With<Camera3d>>,
+) {
+    let Some(request) = requests.read().last().cloned() else {
+        return;
+    };
+    // A playing camera path or a follow camera keeps the camera to itself
+    if path_player.is_playing() || track_playback.is_following() || live_position.is_following() {
+        return;
+    }
+    let Ok((transform, projection, camera_world)) = camera_query.get_single() else {
+        return;
+    };
+
+    let fov = match projection {
+        Projection::Perspective(perspective) => perspective.fov,
+        Projection::Orthographic(_) => std::f32::consts::FRAC_PI_4,
+    };
+    let width_per_distance = 2.0 * (fov as f64 * 0.5).tan();
+
+    // Start from the ground in the middle of the view, or below the camera when looking up
+    let height = transform.translation.y.max(1.0);
+    let below = Vec3::new(transform.translation.x, 0.0, transform.translation.z);
+    let start_center_render = ray_ground_intersection(Ray3d::new(transform.translation, transform.forward()))
+        .filter(|center| center.distance(transform.translation) < height * MAX_START_CENTER_DISTANCE)
+        .unwrap_or(below);
+    let start_center = floating_origin.render_to_world(start_center_render);
+    let start_distance = camera_world.0.distance(start_center);
+
+    // The destination in the world copy closest to the camera, so flights never go the long way round
+    let (end_x, end_z) = lat_lon_to_world(request.lat, request.lon);
+    let end_x = end_x - WORLD_SIZE * ((end_x - start_center.x) / WORLD_SIZE).round();
+    let end_center = DVec3::new(end_x, 0.0, end_z);
+
+    let start_pitch = mouse_look_state.pitch;
+    let end_pitch = request.pitch.unwrap_or(start_pitch).clamp(MIN_FLIGHT_PITCH, MAX_FLIGHT_PITCH);
+    let start_heading = mouse_look_state.yaw;
+    // Turn the short way round
+    let heading_change = (request.heading.unwrap_or(start_heading) - start_heading + PI).rem_euclid(TAU) - PI;
+    let end_distance = altitude_to_world(request.altitude.max(1.0), request.lat) / (-end_pitch as f64).sin();
+
+    let path = SmoothZoomPath::new(
+        start_distance * width_per_distance,
+        end_distance * width_per_distance,
+        start_center.distance(end_center),
+    );
+    let duration = flight_duration(&path);
+    info!("Flying to {:.5}, {:.5} at {:.0} m in {:.1} s", request.lat, request.lon, request.altitude, duration);
+
+    fly_to.flight = Some(Flight {
+        request,
+        path,
+        start_center,
+        end_center,
+        start_heading,
+        end_heading: start_heading + heading_change,
+        start_pitch,
+        end_pitch,
+        width_per_distance,
+        duration,
+        elapsed: 0.0,
+    });
+}
+
+/// Move the camera along the current flight, cancel it on user input and report the arrival
+#[allow(clippy::too_many_arguments)]
+pub fn update_fly_to(
+    time: Res<Time>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    action_state: Res<ActionState>,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    mut wheel_events: EventReader<MouseWheel>,
+    mut arrived_events: EventWriter<FlyToArrived>,
+    mut fly_to: ResMut<FlyToState>,
+    mut mouse_look_state: ResMut<MouseLookState>,
+    mut orbit_camera: ResMut<OrbitCamera>,
+    floating_origin: Res<FloatingOrigin>,
+    mut camera_query: Query<&mut Transform, With<Camera3d>>,
+) {
+    // Read the wheel every frame, so old scrolling can't cancel the next flight
+    let scrolled = wheel_events.read().count() > 0;
+    let Some(flight) = fly_to.flight.as_mut() else {
+        return;
+    };
+
+    // Any input after the flight started hands the camera back to the user where it is
+    let user_input = scrolled
+        || keyboard_input.get_just_pressed().next().is_some()
+        || action_state.any_movement()
+        || mouse_buttons.get_just_pressed().next().is_some()
+        || mouse_look_state.mouse_motion.length() > CANCEL_MOTION;
+    if flight.elapsed > 0.0 && user_input {
+        info!("Fly-to cancelled");
+        fly_to.flight = None;
+        return;
+    }
+    mouse_look_state.mouse_motion = Vec2::ZERO;
+
+    let Ok(mut transform) = camera_query.get_single_mut() else {
+        return;
+    };
+
+    flight.elapsed += time.delta_secs();
+    let t = (flight.elapsed / flight.duration).min(1.0);
+    let eased = ease_in_out(t);
+
+    // The path gives how far the middle of the view has travelled and how much ground is in view
+    let (travelled, width) = flight.path.at(eased as f64);
+    let direction = (flight.end_center - flight.start_center).normalize_or_zero();
+    let center = flight.start_center + direction * travelled;
+    let distance = width / flight.width_per_distance;
+
+    let heading = flight.start_heading + (flight.end_heading - flight.start_heading) * eased;
+    let pitch = flight.start_pitch + (flight.end_pitch - flight.start_pitch) * eased;
+    let rotation = Quat::from_rotation_y(heading) * Quat::from_rotation_x(pitch);
+    let eye = center - (rotation * Vec3::NEG_Z).as_dvec3() * distance;
+
+    transform.translation = floating_origin.world_to_render(eye);
+    transform.rotation = rotation;
+    // The fly and map cameras continue from these angles afterwards
+    mouse_look_state.yaw = heading;
+    mouse_look_state.pitch = pitch;
+
+    if t >= 1.0 {
+        let request = &flight.request;
+        arrived_events.send(FlyToArrived {
+            lat: request.lat,
+            lon: request.lon,
+            altitude: request.altitude,
+        });
+        // Let the orbit camera pick up from the new pose if the globe is in view
+        orbit_camera.active = false;
+        fly_to.flight = None;
+    }
+}
+
+/// Log where fly-to flights end up
+pub fn log_fly_to_arrivals(mut arrived_events: EventReader<FlyToArrived>) {
+    for event in arrived_events.read() {
+        info!("Arrived at {:.5}, {:.5} at {:.0} m", event.lat, event.lon, event.altitude);
+    }
+}
+
+// Seconds a flight along a path takes, longer for longer paths within fixed limits
+fn flight_duration(path: &SmoothZoomPath) -> f32 {
+    (path.length() as f32 * SECONDS_PER_PATH_UNIT).clamp(MIN_FLIGHT_SECONDS, MAX_FLIGHT_SECONDS)
+}
+
+// Cubic ease-in-out, so flights start and stop gently
+fn ease_in_out(t: f32) -> f32 {
+    if t < 0.5 {
+        4.0 * t * t * t
+    } else {
+        1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flights_take_longer_over_longer_paths_within_limits() {
+        let hop = flight_duration(&SmoothZoomPath::new(1000.0, 1000.0, 2000.0));
+        let jump = flight_duration(&SmoothZoomPath::new(1000.0, 1000.0, 1e5));
+        assert!(hop < jump);
+
+        assert_eq!(flight_duration(&SmoothZoomPath::new(1000.0, 1000.0, 0.0)), MIN_FLIGHT_SECONDS);
+        assert_eq!(flight_duration(&SmoothZoomPath::new(1.0, 1.0, 1e12)), MAX_FLIGHT_SECONDS);
+    }
+
+    #[test]
+    fn easing_starts_and_stops_gently() {
+        assert_eq!(ease_in_out(0.0), 0.0);
+        assert_eq!(ease_in_out(0.5), 0.5);
+        assert_eq!(ease_in_out(1.0), 1.0);
+        assert!(ease_in_out(0.1) < 0.1);
+        assert!(ease_in_out(0.9) > 0.9);
+    }
+}
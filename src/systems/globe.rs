@@ -0,0 +1,211 @@
+use bevy::prelude::*;
+use bevy::math::DVec3;
+use bevy::render::mesh::VertexAttributeValues;
+use bevy::render::primitives::Aabb;
+use std::f32::consts::FRAC_PI_2;
+use crate::components::{TileCoords, WorldPosition, MorphedTile};
+use crate::resources::{GlobeSettings, GlobeMorph, OrbitCamera, MouseLookState, FloatingOrigin, FlyToState, ActionState, ControlSettings, InputAction, CameraPathPlayer, TrackPlayback, LivePosition};
+use crate::utils::projection::{GlobeFrame, HALF_WORLD_SIZE, tile_size_world, world_altitude};
+
+// Orbit camera settings; look sensitivity and boost come from ControlSettings
+const ORBIT_MAX_TILT: f32 = 1.3; // Radians away from looking straight down
+const ORBIT_PAN_SPEED: f64 = 0.5; // Fraction of the orbit distance panned per second
+const ORBIT_ZOOM_SPEED: f64 = 1.0; // Exponential zoom rate per second
+const ORBIT_MAX_DISTANCE_RADII: f64 = 4.0; // Furthest orbit, in globe radii
+
+/// Toggle the globe mode with the G key
+pub fn toggle_globe_mode(
+    action_state: Res<ActionState>,
+    mut globe_settings: ResMut<GlobeSettings>,
+) {
+    if action_state.just_pressed(InputAction::ToggleGlobe) {
+        globe_settings.enabled = !globe_settings.enabled;
+        info!("Globe mode: {}", if globe_settings.enabled { "ON" } else { "OFF" });
+    }
+}
+
+/// Blend factor between flat (0.0) and globe (1.0) for a camera altitude in metres
+pub fn morph_factor(altitude: f64, globe_settings: &GlobeSettings) -> f32 {
+    if !globe_settings.enabled {
+        return 0.0;
+    }
+    let range = (globe_settings.globe_altitude - globe_settings.flat_altitude).max(1.0);
+    let t = ((altitude - globe_settings.flat_altitude) / range).clamp(0.0, 1.0);
+    (t * t * (3.0 - 2.0 * t)) as f32 // Smoothstep for an eased transition
+}
+
+/// Update the morph factor from the camera altitude and hand the camera over between
+/// the fly camera (flat map) and the orbit camera (globe)
+pub fn update_globe_morph(
+    globe_settings: Res<GlobeSettings>,
+    floating_origin: Res<FloatingOrigin>,
+    mut globe_morph: ResMut<GlobeMorph>,
+    mut orbit_camera: ResMut<OrbitCamera>,
+    mut mouse_look_state: ResMut<MouseLookState>,
+    camera_query: Query<(&Transform, &WorldPosition), With<Camera3d>>,
+) {
+    let Ok((camera_transform, camera_world)) = camera_query.get_single() else {
+        return;
+    };
+
+    let factor = morph_factor(world_altitude(camera_world.0), &globe_settings);
+
+    if factor > 0.0 && !orbit_camera.active {
+        // Orbit around the ground point in view, or the point straight below the camera
+        let ray_origin = camera_transform.translation;
+        let ray_direction = camera_transform.forward();
+        let t = -ray_origin.y / ray_direction.y;
+        let target_render = if ray_direction.y < -0.05 && t < ray_origin.y * 10.0 {
+            ray_origin + ray_direction * t
+        } else {
+            ray_origin
+        };
+        let mut target = floating_origin.render_to_world(target_render);
+        target.y = 0.0;
+
+        orbit_camera.target = target;
+        orbit_camera.distance = camera_world.0.distance(target);
+        orbit_camera.heading = mouse_look_state.yaw;
+        orbit_camera.tilt = (FRAC_PI_2 + mouse_look_state.pitch).clamp(0.0, ORBIT_MAX_TILT);
+        orbit_camera.active = true;
+    } else if factor == 0.0 && orbit_camera.active {
+        // Back on the flat map - let the fly camera continue from the current pose
+        mouse_look_state.yaw = orbit_camera.heading;
+        mouse_look_state.pitch = (orbit_camera.tilt - FRAC_PI_2).clamp(-1.5, 1.5);
+        orbit_camera.active = false;
+    }
+
+    globe_morph.factor = factor;
+    if orbit_camera.active {
+        globe_morph.anchor = orbit_camera.target;
+    }
+}
+
+/// Orbit camera controls while the globe is visible:
+/// mouse turns and tilts, WASD pans the target, Space/Ctrl zoom out and in
+#[allow(clippy::too_many_arguments)]
+pub fn orbit_camera_movement(
+    time: Res<Time>,
+    action_state: Res<ActionState>,
+    control_settings: Res<ControlSettings>,
+    floating_origin: Res<FloatingOrigin>,
+    mut mouse_look_state: ResMut<MouseLookState>,
+    mut orbit_camera: ResMut<OrbitCamera>,
+    fly_to: Res<FlyToState>,
+    path_player: Res<CameraPathPlayer>,
+    track_playback: Res<TrackPlayback>,
+    live_position: Res<LivePosition>,
+    mut query: Query<&mut Transform, With<Camera3d>>,
+) {
+    // A fly-to, camera path or follow camera moves the camera itself
+    if !orbit_camera.active
+        || fly_to.is_flying()
+        || path_player.is_playing()
+        || track_playback.is_following()
+        || live_position.is_following()
+    {
+        return;
+    }
+    let delta = time.delta_secs() as f64;
+
+    // Mouse motion and the look stick turn the camera around the target and tilt it towards the horizon
+    let mut look = Vec2::ZERO;
+    if !mouse_look_state.mouse_motion.is_nan() {
+        look = mouse_look_state.mouse_motion * control_settings.look_sensitivity;
+    }
+    look += action_state.look_stick * Vec2::new(1.0, -1.0) * control_settings.gamepad_look_speed * delta as f32;
+    if control_settings.invert_look_y {
+        look.y = -look.y;
+    }
+    orbit_camera.heading -= look.x;
+    orbit_camera.tilt = (orbit_camera.tilt - look.y).clamp(0.0, ORBIT_MAX_TILT);
+    mouse_look_state.mouse_motion = Vec2::ZERO;
+
+    // Pan the target along the ground relative to the heading
+    let heading_rotation = Quat::from_rotation_y(orbit_camera.heading);
+    let forward = heading_rotation * Vec3::NEG_Z;
+    let right = heading_rotation * Vec3::X;
+    let pan = forward * (action_state.axis(InputAction::MoveBackward, InputAction::MoveForward) + action_state.move_stick.y)
+        + right * (action_state.axis(InputAction::MoveLeft, InputAction::MoveRight) + action_state.move_stick.x);
+    let boost = if action_state.pressed(InputAction::Boost) {
+        control_settings.boost_multiplier as f64
+    } else {
+        1.0
+    };
+    let pan_speed = orbit_camera.distance * ORBIT_PAN_SPEED * boost;
+    orbit_camera.target += pan.clamp_length_max(1.0).as_dvec3() * pan_speed * delta;
+    orbit_camera.target.z = orbit_camera.target.z.clamp(-HALF_WORLD_SIZE, HALF_WORLD_SIZE);
+
+    // Zoom exponentially so it feels the same at every distance
+    let zoom = action_state.axis(InputAction::MoveDown, InputAction::MoveUp) as f64;
+    let max_distance = GlobeFrame::new(orbit_camera.target).radius() * ORBIT_MAX_DISTANCE_RADII;
+    orbit_camera.distance = (orbit_camera.distance * (zoom * ORBIT_ZOOM_SPEED * boost * delta).exp())
+        .clamp(1.0, max_distance);
+
+    // Place the camera above the target, pulled back along the heading by the tilt
+    let Ok(mut transform) = query.get_single_mut() else {
+        return;
+    };
+    let offset = (Vec3::Y * orbit_camera.tilt.cos() - forward * orbit_camera.tilt.sin()).as_dvec3();
+    let eye = orbit_camera.target + offset * orbit_camera.distance;
+    let target_render = floating_origin.world_to_render(orbit_camera.target);
+    transform.translation = floating_origin.world_to_render(eye);
+    // Use the heading as the up hint so looking straight down stays well defined
+    transform.look_at(target_render, forward);
+}
+
+/// Bend tile meshes between the flat plane and the globe according to the morph factor
+#[allow(clippy::type_complexity)]
+pub fn morph_tile_meshes(
+    mut commands: Commands,
+    globe_morph: Res<GlobeMorph>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut tile_query: Query<(Entity, &TileCoords, &WorldPosition, &Mesh3d, &mut Transform, Option<&MorphedTile>)>,
+) {
+    let factor = globe_morph.factor;
+    let frame = GlobeFrame::new(globe_morph.anchor);
+
+    for (entity, tile_coords, world_position, mesh_handle, mut transform, morphed) in tile_query.iter_mut() {
+        // Skip tiles that are already in the right shape
+        match morphed {
+            None if factor == 0.0 => continue,
+            Some(state) if state.factor == factor && state.anchor == globe_morph.anchor => continue,
+            _ => {}
+        }
+
+        let Some(mesh) = meshes.get_mut(&mesh_handle.0) else {
+            continue;
+        };
+        let Some(VertexAttributeValues::Float32x2(uvs)) = mesh.attribute(Mesh::ATTRIBUTE_UV_0) else {
+            continue;
+        };
+
+        let tile_size = tile_size_world(tile_coords.zoom);
+        let positions: Vec<[f32; 3]> = if factor == 0.0 {
+            // Back to the flat unit grid, scaled by the transform
+            uvs.iter().map(|&[u, v]| [u, 0.0, v]).collect()
+        } else {
+            // Absolute offsets from the tile corner, so the transform is not scaled
+            uvs.iter()
+                .map(|&[u, v]| {
+                    let flat = world_position.0 + DVec3::new(u as f64 * tile_size, 0.0, v as f64 * tile_size);
+                    (frame.morph(flat, factor as f64) - world_position.0).as_vec3().to_array()
+                })
+                .collect()
+        };
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        // Bevy only computes bounds once, so drop them to get them recalculated for the new shape
+        commands.entity(entity).remove::<Aabb>();
+
+        if factor == 0.0 {
+            transform.scale = Vec3::new(tile_size as f32, 1.0, tile_size as f32);
+            commands.entity(entity).remove::<MorphedTile>();
+        } else {
+            transform.scale = Vec3::ONE;
+            commands.entity(entity).insert(MorphedTile {
+                factor,
+                anchor: globe_morph.anchor,
+            });
+        }
+    }
+}
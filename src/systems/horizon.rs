@@ -0,0 +1,44 @@
+use bevy::prelude::*;
+use bevy::math::DVec3;
+use bevy::pbr::{DistanceFog, FogFalloff};
+use crate::components::WorldPosition;
+use crate::resources::{HorizonSettings, GlobeMorph};
+use crate::utils::projection::{horizon_distance, mercator_scale, world_altitude, world_to_lat_lon};
+
+/// Distance in world units to the furthest ground a camera at a world position can see:
+/// the horizon, or the camera far plane when that is closer
+pub fn visible_ground_distance(camera_world: DVec3, projection: &Projection) -> f64 {
+    let far = match projection {
+        Projection::Perspective(perspective) => perspective.far,
+        Projection::Orthographic(orthographic) => orthographic.far,
+    };
+    let (lat, _) = world_to_lat_lon(camera_world.x, camera_world.z);
+    let horizon = horizon_distance(world_altitude(camera_world)) * mercator_scale(lat);
+    horizon.min(far as f64)
+}
+
+/// Fade the far edge of the map into the clear color with distance fog that follows the horizon
+///
+/// The fog fades out as the map bends onto the globe, where the globe's own edge is the horizon.
+pub fn update_horizon_fog(
+    horizon_settings: Res<HorizonSettings>,
+    globe_morph: Res<GlobeMorph>,
+    clear_color: Res<ClearColor>,
+    mut camera_query: Query<(&WorldPosition, &Projection, &mut DistanceFog), With<Camera3d>>,
+) {
+    let Ok((camera_world, projection, mut fog)) = camera_query.get_single_mut() else {
+        return;
+    };
+
+    let end = visible_ground_distance(camera_world.0, projection) as f32;
+    let start = end * horizon_settings.fog_start_fraction.clamp(0.0, 1.0);
+
+    // The alpha of the fog color sets how strong the fog is
+    let strength = if horizon_settings.fog_enabled {
+        1.0 - globe_morph.factor
+    } else {
+        0.0
+    };
+    fog.color = clear_color.0.with_alpha(strength);
+    fog.falloff = FogFalloff::Linear { start, end };
+}
@@ -0,0 +1,50 @@
+use bevy::prelude::*;
+use crate::resources::{ActionState, ControlSettings, InputBindings};
+
+// Analog button values up to this are resting triggers rather than a pull
+const TRIGGER_DEAD_ZONE: f32 = 0.05;
+
+/// Resolve the keyboard and gamepad state into actions for this frame
+pub fn update_action_state(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    bindings: Res<InputBindings>,
+    control_settings: Res<ControlSettings>,
+    gamepads: Query<&Gamepad>,
+    mut action_state: ResMut<ActionState>,
+) {
+    action_state.clear();
+
+    for (action, keys) in bindings.keys.iter() {
+        for key in keys {
+            if keyboard_input.pressed(*key) {
+                action_state.set(*action, 1.0, keyboard_input.just_pressed(*key));
+            }
+        }
+    }
+
+    for gamepad in gamepads.iter() {
+        for (action, buttons) in bindings.gamepad_buttons.iter() {
+            for button in buttons {
+                // Analog buttons like the triggers give how far they are pulled, from the
+                // lightest touch; the others are on or off
+                let value = match gamepad.get(*button) {
+                    Some(value) => Some(value).filter(|value| *value > TRIGGER_DEAD_ZONE),
+                    None => gamepad.pressed(*button).then_some(1.0),
+                };
+                if let Some(value) = value {
+                    action_state.set(*action, value, gamepad.just_pressed(*button));
+                }
+            }
+        }
+
+        let move_stick = control_settings.stick_response(gamepad.left_stick());
+        let look_stick = control_settings.stick_response(gamepad.right_stick());
+        // With several gamepads connected the strongest input wins
+        if move_stick.length() > action_state.move_stick.length() {
+            action_state.move_stick = move_stick;
+        }
+        if look_stick.length() > action_state.look_stick.length() {
+            action_state.look_stick = look_stick;
+        }
+    }
+}
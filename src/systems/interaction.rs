@@ -1,35 +1,169 @@
 use bevy::prelude::*;
-use crate::resources::DebugSettings;
+use bevy::picking::mesh_picking::ray_cast::{MeshRayCast, RayCastSettings};
+use bevy::window::{CursorGrabMode, PrimaryWindow};
+use crate::components::TileCoords;
+use crate::events::CopyInspectionRequested;
+use crate::osm::OSMTile;
+use crate::resources::{
+    ActionState, DebugSettings, FloatingOrigin, GlobeMorph, InputAction, Inspection, MapInspection, MapLayers,
+    InspectedLayer, ZoomPolicy,
+};
+use crate::utils::geodesy::{world_to_geo, lat_lon_to_tile, tile_bounds};
+use crate::utils::picking::ray_ground_intersection;
+use crate::utils::projection::{GlobeFrame, HALF_WORLD_SIZE};
 use crate::debug_log;
 
-/// System to handle user interaction with the map
+// Furthest the cursor may move between press and release (logical pixels) for a click;
+// anything more is a drag that pans the map
+const CLICK_TOLERANCE: f32 = 4.0;
+
+/// Inspect the point of the map under the cursor when it is clicked
+///
+/// The cursor ray is cast against the tile meshes, so the point is found on the globe
+/// as well as on the flat map. With the cursor locked for mouse look, the middle of the
+/// screen is inspected.
+#[allow(clippy::too_many_arguments)]
 pub fn interact_with_map(
-    _keyboard_input: Res<ButtonInput<KeyCode>>,
     mouse_input: Res<ButtonInput<MouseButton>>,
     debug_settings: Res<DebugSettings>,
-    camera_query: Query<(&Transform, &Camera), With<Camera3d>>,
+    floating_origin: Res<FloatingOrigin>,
+    globe_morph: Res<GlobeMorph>,
+    map_layers: Res<MapLayers>,
+    zoom_policy: Res<ZoomPolicy>,
+    mut inspection: ResMut<MapInspection>,
+    mut ray_cast: MeshRayCast,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    ui_query: Query<&Interaction>,
+    camera_query: Query<(&GlobalTransform, &Camera), With<Camera3d>>,
+    tile_query: Query<&TileCoords>,
+    // Where the left button went down on the map, to tell a click from a drag
+    mut press: Local<Option<Vec2>>,
 ) {
-    // Only perform actions on mouse click
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let cursor = match window.cursor_options.grab_mode {
+        CursorGrabMode::None => window.cursor_position(),
+        _ => Some(window.size() / 2.0),
+    };
+
     if mouse_input.just_pressed(MouseButton::Left) {
-        if let Ok((camera_transform, _camera)) = camera_query.get_single() {
-            // Get camera position and direction
-            let ray_origin = camera_transform.translation;
-            let ray_direction = camera_transform.forward();
-            
-            // Simple ray-plane intersection to find which tile we're looking at
-            // Assume tiles are on the y=0 plane
-            let t = -ray_origin.y / ray_direction.y;
-            if t > 0.0 {
-                let hit_point = ray_origin + ray_direction * t;
-                debug_log!(debug_settings, "Ray hit ground at position: {:?}", hit_point);
-                
-                // Convert hit point to tile coordinates at the current zoom level
-                // (We can add this functionality later if needed)
-            } else {
-                debug_log!(debug_settings, "Ray didn't hit ground plane - make sure you're looking at the ground");
+        // Presses on the UI are not meant for the map
+        let over_ui = ui_query.iter().any(|interaction| *interaction != Interaction::None);
+        *press = cursor.filter(|_| !over_ui);
+        return;
+    }
+    if !mouse_input.just_released(MouseButton::Left) {
+        return;
+    }
+    let (Some(press), Some(cursor)) = (press.take(), cursor) else {
+        return;
+    };
+    if press.distance(cursor) > CLICK_TOLERANCE {
+        return;
+    }
+
+    // Pick against what was drawn last frame: the camera and tile GlobalTransforms and the
+    // floating origin before this frame's shift all still belong to it
+    let Ok((camera_transform, camera)) = camera_query.get_single() else {
+        debug_log!(debug_settings, "Camera not found!");
+        return;
+    };
+    let Ok(ray) = camera.viewport_to_world(camera_transform, cursor) else {
+        return;
+    };
+
+    let is_tile = |entity| tile_query.contains(entity);
+    let settings = RayCastSettings::default().with_filter(&is_tile);
+    let (hit_point, zoom) = match ray_cast.cast_ray(ray, &settings).first() {
+        Some((entity, hit)) => (hit.point, tile_query.get(*entity).ok().map(|tile| tile.zoom)),
+        // Where no tile is loaded yet the flat map is still there to hit, the globe is not
+        None => match ray_ground_intersection(ray).filter(|_| globe_morph.factor <= 0.0) {
+            Some(point) => (point, None),
+            None => {
+                debug_log!(debug_settings, "Click didn't hit the map");
+                return;
             }
-        } else {
-            debug_log!(debug_settings, "Camera not found!");
+        },
+    };
+
+    // Undo the globe bend to find the point on the flat Mercator map
+    let hit_world = floating_origin.render_to_world(hit_point);
+    let Some(world) = GlobeFrame::new(globe_morph.anchor).unmorph_ground(hit_world, globe_morph.factor as f64) else {
+        debug_log!(debug_settings, "Click hit the globe where it can't be unbent");
+        return;
+    };
+    debug_log!(debug_settings, "Click hit the map at world position: {:?}", world);
+    if world.z.abs() > HALF_WORLD_SIZE {
+        debug_log!(debug_settings, "Click is beyond the edge of the map");
+        return;
+    }
+
+    let zoom = zoom.unwrap_or(zoom_policy.current);
+    let position = world_to_geo(world.x, world.z);
+    let (x, y) = lat_lon_to_tile(position, zoom);
+    let tile = OSMTile::new(x as i32, y, zoom);
+    let layers = map_layers
+        .active_at(zoom)
+        .iter()
+        .map(|layer| InspectedLayer {
+            name: layer.name.clone(),
+            url: tile.get_url(layer),
+            attribution: layer.attribution.clone(),
+        })
+        .collect();
+
+    let result = Inspection {
+        position,
+        zoom,
+        x,
+        y,
+        bounds: tile_bounds(x, y, zoom),
+        layers,
+        screen_position: cursor,
+    };
+    info!("Inspected {:.6}, {:.6} in tile {}", result.position.lat, result.position.lon, result.tile_path());
+    inspection.current = Some(result);
+}
+
+/// Ask for the inspected point to be copied (C)
+pub fn inspection_actions(
+    action_state: Res<ActionState>,
+    inspection: Res<MapInspection>,
+    mut copy_events: EventWriter<CopyInspectionRequested>,
+) {
+    if inspection.current.is_some() && action_state.just_pressed(InputAction::CopyInspection) {
+        copy_events.send(CopyInspectionRequested);
+    }
+}
+
+/// Copy the inspected point to the system clipboard
+pub fn copy_inspection(
+    mut copy_events: EventReader<CopyInspectionRequested>,
+    inspection: Res<MapInspection>,
+    // Kept open, as on some platforms the copied text goes away with the clipboard handle
+    mut clipboard: Local<Option<arboard::Clipboard>>,
+) {
+    if copy_events.read().count() == 0 {
+        return;
+    }
+    let Some(current) = &inspection.current else {
+        return;
+    };
+
+    if clipboard.is_none() {
+        match arboard::Clipboard::new() {
+            Ok(opened) => *clipboard = Some(opened),
+            Err(e) => {
+                warn!("Clipboard unavailable: {}", e);
+                return;
+            }
+        }
+    }
+    if let Some(clipboard) = clipboard.as_mut() {
+        match clipboard.set_text(current.clipboard_text()) {
+            Ok(()) => info!("Copied the inspected point to the clipboard"),
+            Err(e) => warn!("Failed to copy to the clipboard: {}", e),
         }
     }
-} 
\ No newline at end of file
+}
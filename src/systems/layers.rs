@@ -0,0 +1,62 @@
+use bevy::prelude::*;
+use crate::resources::{OSMData, TokioRuntime, DebugSettings, MapLayers, ActionState, InputAction};
+use crate::components::{TileCoords, TileState};
+use crate::osm::OSMTile;
+use crate::systems::tiles::spawn_tile_load;
+use crate::debug_log;
+
+/// Toggle map layers with the F1 - F9 keys, or whatever the layer toggles are bound to
+pub fn toggle_map_layers(
+    action_state: Res<ActionState>,
+    mut map_layers: ResMut<MapLayers>,
+) {
+    for (index, action) in InputAction::LAYER_TOGGLES.iter().enumerate() {
+        if !action_state.just_pressed(*action) {
+            continue;
+        }
+        if let Some(layer) = map_layers.layers.get_mut(index) {
+            layer.enabled = !layer.enabled;
+            info!("Layer {}: {}", layer.name, if layer.enabled { "ON" } else { "OFF" });
+        }
+    }
+}
+
+/// Load the tiles again with the new layer stack whenever the layers change
+///
+/// Tiles keep showing their current image until the new one has loaded. The reload starts
+/// a new layer generation, so loads still running for the old stack are ignored.
+pub fn reload_tiles_on_layer_change(
+    mut map_layers: ResMut<MapLayers>,
+    osm_data: Res<OSMData>,
+    tokio_runtime: Res<TokioRuntime>,
+    debug_settings: Res<DebugSettings>,
+    mut tile_query: Query<(Entity, &TileCoords, &mut TileState)>,
+) {
+    if !map_layers.is_changed() || map_layers.is_added() {
+        return;
+    }
+    // Not a change of the stack itself, so it must not trigger another reload
+    map_layers.bypass_change_detection().generation += 1;
+
+    let mut reloaded = 0;
+    for (entity, tile_coords, mut state) in tile_query.iter_mut() {
+        // Tiles still loading pick up the old stack, so reload those as well
+        if *state == TileState::Evicting {
+            continue;
+        }
+        *state = TileState::Requested;
+        spawn_tile_load(
+            &tokio_runtime,
+            osm_data.pending_tiles.clone(),
+            entity,
+            OSMTile::new(tile_coords.x, tile_coords.y, tile_coords.zoom),
+            &map_layers,
+            0,
+            osm_data.texture_formats,
+            debug_settings.debug_mode,
+        );
+        reloaded += 1;
+    }
+
+    debug_log!(debug_settings, "Map layers changed, reloading {} tiles", reloaded);
+}
@@ -0,0 +1,182 @@
+use bevy::prelude::*;
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use bevy::math::DVec3;
+use bevy::window::{CursorGrabMode, PrimaryWindow};
+use crate::resources::{CameraMode, WalkState, MouseLookState, OrbitCamera, FloatingOrigin, FlyToState, ActionState, InputAction, CameraPathPlayer, TrackPlayback, LivePosition};
+use crate::utils::picking::{viewport_ray, ray_ground_intersection};
+
+// Zoom per scroll wheel line, as an exponent of the distance to the point under the cursor
+const MAP_ZOOM_SPEED: f32 = 0.2;
+// Pixels of a smooth-scrolling touchpad that count as one wheel line
+const PIXELS_PER_SCROLL_LINE: f32 = 100.0;
+// Radians of rotation and tilt per pixel of right-drag
+const MAP_ROTATE_SENSITIVITY: f32 = 0.005;
+// Pitch limits while tilting: nearly straight down to a little above the horizon
+const MAP_MIN_PITCH: f32 = -1.55;
+const MAP_MAX_PITCH: f32 = -0.15;
+// Lowest height (world units) the map camera can zoom down to
+const MAP_MIN_HEIGHT: f32 = 2.0;
+
+/// Ongoing mouse drags of the map camera
+#[derive(Default)]
+pub struct MapDrag {
+    // Ground point (world space) that stays under the cursor while panning
+    pan_anchor: Option<DVec3>,
+    // Ground point (world space) the camera rotates around while right-dragging
+    pivot: Option<DVec3>,
+    last_cursor: Option<Vec2>,
+}
+
+/// Switch between the fly camera and the map camera with the M key, and between flying
+/// and walking with the V key
+///
+/// The fly and walk cameras want the cursor locked for mouse look, the map camera needs it free.
+pub fn toggle_camera_mode(
+    action_state: Res<ActionState>,
+    mut camera_mode: ResMut<CameraMode>,
+    mut mouse_look_state: ResMut<MouseLookState>,
+    mut walk_state: ResMut<WalkState>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    let next_mode = if action_state.just_pressed(InputAction::ToggleCameraMode) {
+        match *camera_mode {
+            CameraMode::Map => CameraMode::Fly,
+            CameraMode::Fly | CameraMode::Walk => CameraMode::Map,
+        }
+    } else if action_state.just_pressed(InputAction::ToggleWalk) {
+        match *camera_mode {
+            CameraMode::Walk => CameraMode::Fly,
+            CameraMode::Fly | CameraMode::Map => CameraMode::Walk,
+        }
+    } else {
+        return;
+    };
+
+    *camera_mode = next_mode;
+    // Motion gathered before the switch must not turn the camera afterwards
+    mouse_look_state.mouse_motion = Vec2::ZERO;
+    // Walking starts on the ground below, rather than with a long fall
+    *walk_state = WalkState::default();
+
+    if let Ok(mut window) = windows.get_single_mut() {
+        let free_cursor = !camera_mode.uses_mouse_look();
+        window.cursor_options.visible = free_cursor;
+        window.cursor_options.grab_mode = if free_cursor { CursorGrabMode::None } else { CursorGrabMode::Locked };
+    }
+    info!("Camera mode: {:?}", *camera_mode);
+}
+
+/// Web map style camera controls on the flat map:
+/// left-drag pans the ground under the cursor, the wheel zooms towards the cursor and
+/// right-drag rotates and tilts around the ground point in the middle of the view
+#[allow(clippy::too_many_arguments)]
+pub fn map_camera_movement(
+    camera_mode: Res<CameraMode>,
+    orbit_camera: Res<OrbitCamera>,
+    fly_to: Res<FlyToState>,
+    path_player: Res<CameraPathPlayer>,
+    track_playback: Res<TrackPlayback>,
+    live_position: Res<LivePosition>,
+    floating_origin: Res<FloatingOrigin>,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    mut wheel_events: EventReader<MouseWheel>,
+    mut mouse_look_state: ResMut<MouseLookState>,
+    mut drag: Local<MapDrag>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut camera_query: Query<(&mut Transform, &Camera), With<Camera3d>>,
+) {
+    // The orbit camera takes over while the globe is visible, and a fly-to, camera path or
+    // follow camera while it runs
+    if *camera_mode != CameraMode::Map
+        || orbit_camera.active
+        || fly_to.is_flying()
+        || path_player.is_playing()
+        || track_playback.is_following()
+        || live_position.is_following()
+    {
+        *drag = MapDrag::default();
+        wheel_events.clear();
+        return;
+    }
+    // Raw mouse motion is for the fly camera only
+    mouse_look_state.mouse_motion = Vec2::ZERO;
+
+    let Ok((mut transform, camera)) = camera_query.get_single_mut() else {
+        return;
+    };
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let cursor = window.cursor_position();
+    let ground_under = |transform: &Transform, position: Vec2| {
+        viewport_ray(camera, transform, position).and_then(ray_ground_intersection)
+    };
+
+    // Pan: keep the ground point grabbed on press under the cursor
+    if mouse_buttons.pressed(MouseButton::Left) {
+        let hit = cursor.and_then(|cursor| ground_under(&transform, cursor));
+        if mouse_buttons.just_pressed(MouseButton::Left) {
+            drag.pan_anchor = hit.map(|hit| floating_origin.render_to_world(hit));
+        } else if let (Some(anchor), Some(hit)) = (drag.pan_anchor, hit) {
+            // Moving the camera sideways moves the point under the cursor by the same amount
+            let offset = floating_origin.world_to_render(anchor) - hit;
+            transform.translation.x += offset.x;
+            transform.translation.z += offset.z;
+        }
+    } else {
+        drag.pan_anchor = None;
+    }
+
+    // Zoom: move along the line from the camera to the point under the cursor
+    let scroll: f32 = wheel_events
+        .read()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / PIXELS_PER_SCROLL_LINE,
+        })
+        .sum();
+    if scroll != 0.0 {
+        let below = Vec3::new(transform.translation.x, 0.0, transform.translation.z);
+        let target = cursor.and_then(|cursor| ground_under(&transform, cursor)).unwrap_or(below);
+        let offset = transform.translation - target;
+        let mut factor = (-scroll * MAP_ZOOM_SPEED).exp();
+        // Stop at the minimum height rather than diving into the ground
+        if offset.y > 0.0 {
+            factor = factor.max(MAP_MIN_HEIGHT / offset.y);
+        }
+        transform.translation = target + offset * factor;
+    }
+
+    // Rotate and tilt: turn the camera rigidly around the ground point in the middle of the view
+    if mouse_buttons.pressed(MouseButton::Right) {
+        if mouse_buttons.just_pressed(MouseButton::Right) {
+            let center = window.size() * 0.5;
+            let below = Vec3::new(transform.translation.x, 0.0, transform.translation.z);
+            let pivot = ground_under(&transform, center).unwrap_or(below);
+            drag.pivot = Some(floating_origin.render_to_world(pivot));
+        } else if let (Some(pivot), Some(cursor), Some(last_cursor)) = (drag.pivot, cursor, drag.last_cursor) {
+            let delta = cursor - last_cursor;
+            let yaw_change = -delta.x * MAP_ROTATE_SENSITIVITY;
+            let pitch = (mouse_look_state.pitch - delta.y * MAP_ROTATE_SENSITIVITY).clamp(MAP_MIN_PITCH, MAP_MAX_PITCH);
+            let pitch_change = pitch - mouse_look_state.pitch;
+
+            // Same yaw-then-pitch rotation as the fly camera, applied to the offset from the pivot
+            let pivot_render = floating_origin.world_to_render(pivot);
+            let tilt = Quat::from_axis_angle(Quat::from_rotation_y(mouse_look_state.yaw) * Vec3::X, pitch_change);
+            let rotation = Quat::from_rotation_y(yaw_change) * tilt;
+            let position = pivot_render + rotation * (transform.translation - pivot_render);
+
+            if position.y >= MAP_MIN_HEIGHT {
+                transform.translation = position;
+                mouse_look_state.yaw += yaw_change;
+                mouse_look_state.pitch = pitch;
+            }
+        }
+    } else {
+        drag.pivot = None;
+    }
+    drag.last_cursor = cursor;
+
+    // Keep the fly camera's angles as the source of the orientation, so switching back is seamless
+    transform.rotation = Quat::from_rotation_y(mouse_look_state.yaw) * Quat::from_rotation_x(mouse_look_state.pitch);
+}
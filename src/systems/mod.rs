@@ -5,5 +5,18 @@ pub mod interaction;
 pub mod debug;
 pub mod window;
 pub mod ui;
+pub mod origin;
+pub mod globe;
+pub mod prefetch;
+pub mod horizon;
+pub mod layers;
+pub mod map_camera;
+pub mod fly_to;
+pub mod session;
+pub mod input;
+pub mod bookmarks;
+pub mod camera_path;
+pub mod track;
+pub mod position_feed;
 
 // Systems are imported directly where needed 
\ No newline at end of file
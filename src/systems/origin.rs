@@ -0,0 +1,37 @@
+use bevy::prelude::*;
+use crate::components::WorldPosition;
+use crate::resources::{FloatingOrigin, DebugSettings};
+use crate::debug_log;
+
+/// Keeps the camera's world position in sync and rebases the render-space origin
+/// around the camera once it drifts too far from it
+pub fn update_floating_origin(
+    mut floating_origin: ResMut<FloatingOrigin>,
+    debug_settings: Res<DebugSettings>,
+    mut camera_query: Query<(&mut Transform, &mut WorldPosition), With<Camera3d>>,
+    mut world_query: Query<(&WorldPosition, &mut Transform), Without<Camera3d>>,
+) {
+    let Ok((mut camera_transform, mut camera_world)) = camera_query.get_single_mut() else {
+        return;
+    };
+
+    // The camera moves in render space, so derive its precise world position from there
+    camera_world.0 = floating_origin.render_to_world(camera_transform.translation);
+
+    // Only the horizontal offset matters - the origin never leaves the ground plane
+    let offset = camera_transform.translation.xz();
+    if offset.length() < floating_origin.rebase_distance {
+        return;
+    }
+
+    floating_origin.origin.x = camera_world.0.x;
+    floating_origin.origin.z = camera_world.0.z;
+    debug_log!(debug_settings, "Rebased floating origin to ({:.3}, {:.3})",
+              floating_origin.origin.x, floating_origin.origin.z);
+
+    // Re-derive every render-space translation from the precise world positions
+    camera_transform.translation = floating_origin.world_to_render(camera_world.0);
+    for (world_position, mut transform) in world_query.iter_mut() {
+        transform.translation = floating_origin.world_to_render(world_position.0);
+    }
+}
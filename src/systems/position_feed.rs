@@ -0,0 +1,382 @@
+use bevy::prelude::*;
+use bevy::math::DVec3;
+use std::f32::consts::FRAC_PI_2;
+use std::time::Duration;
+use parking_lot::Mutex;
+use anyhow::anyhow;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use crate::components::WorldPosition;
+use crate::resources::{
+    ActionState, CameraPathPlayer, ControlSettings, FeedMessage, FixStatus, FloatingOrigin, FlyToState, GlobeMorph,
+    InputAction, LivePosition, MouseLookState, OrbitCamera, PositionFeed, TokioRuntime, TrackPlayback,
+};
+use crate::utils::geodesy::{destination_point, haversine_distance, initial_bearing};
+use crate::utils::position_source::{PositionProtocol, PositionReport};
+use crate::utils::projection::{GlobeFrame, lat_lon_to_world, altitude_to_world, mercator_scale, WORLD_SIZE};
+
+// Wait between attempts to reach the position source
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+// A source that sends nothing for this long is taken as gone and connected to again
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+// Longest line kept; sentences and reports are far shorter, so longer lines are dropped
+const MAX_LINE_LENGTH: usize = 4096;
+// Longest time a position is carried on along its course while waiting for the next one
+const MAX_EXTRAPOLATION: f64 = 2.0;
+// Jumps further than this (metres) are taken at once instead of smoothed
+const MAX_SMOOTHED_DISTANCE: f64 = 1000.0;
+// Slowest speed (m/s) at which the course is meaningful enough to turn the camera
+const MIN_COURSE_SPEED: f64 = 0.5;
+// Rate at which the camera turns towards the course, per second
+const HEADING_RATE: f64 = 2.0;
+// Height of the accuracy circle above the map in metres
+const ACCURACY_CIRCLE_HEIGHT: f64 = 0.5;
+
+/// Connect to the configured position source in the background, reconnecting whenever
+/// the connection drops, and follow it from the first fix
+pub fn start_position_feed(
+    feed: Res<PositionFeed>,
+    tokio_runtime: Res<TokioRuntime>,
+    mut live: ResMut<LivePosition>,
+) {
+    let Some(address) = feed.address.clone() else {
+        return;
+    };
+    info!("Reading {:?} positions from {}", feed.format, address);
+    live.follow = true;
+
+    let protocol = feed.format.protocol();
+    let pending = feed.pending.clone();
+    tokio_runtime.0.spawn(async move {
+        loop {
+            let reason = match read_positions(&address, protocol.as_ref(), &pending).await {
+                Ok(()) => "connection closed".to_string(),
+                Err(e) => e.to_string(),
+            };
+            pending.lock().push(FeedMessage::Disconnected(reason));
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    });
+}
+
+async fn read_positions(
+    address: &str,
+    protocol: &dyn PositionProtocol,
+    pending: &Mutex<Vec<FeedMessage>>,
+) -> Result<(), anyhow::Error> {
+    let mut stream = TcpStream::connect(address).await?;
+    pending.lock().push(FeedMessage::Connected);
+    if let Some(handshake) = protocol.handshake() {
+        stream.write_all(handshake.as_bytes()).await?;
+    }
+
+    // Read bytes rather than text, as receivers can mix binary messages into the stream
+    let mut reader = BufReader::new(stream);
+    let mut line = Vec::new();
+    loop {
+        let read = tokio::time::timeout(IDLE_TIMEOUT, read_line(&mut reader, &mut line))
+            .await
+            .map_err(|_| anyhow!("nothing received for {} s", IDLE_TIMEOUT.as_secs()))??;
+        if !read {
+            return Ok(());
+        }
+        if line.len() >= MAX_LINE_LENGTH {
+            continue;
+        }
+        if let Some(report) = protocol.parse_line(&String::from_utf8_lossy(&line)) {
+            pending.lock().push(FeedMessage::Report(report));
+        }
+    }
+}
+
+// Read up to and including the next newline into `line`, keeping at most MAX_LINE_LENGTH
+// bytes of it; false at the end of the stream
+async fn read_line<R: AsyncBufRead + Unpin>(reader: &mut R, line: &mut Vec<u8>) -> std::io::Result<bool> {
+    line.clear();
+    loop {
+        let available = reader.fill_buf().await?;
+        if available.is_empty() {
+            return Ok(!line.is_empty());
+        }
+        let (chunk, complete) = match available.iter().position(|&byte| byte == b'\n') {
+            Some(end) => (&available[..=end], true),
+            None => (available, false),
+        };
+        let room = MAX_LINE_LENGTH.saturating_sub(line.len());
+        line.extend_from_slice(&chunk[..chunk.len().min(room)]);
+        let used = chunk.len();
+        reader.consume(used);
+        if complete {
+            return Ok(true);
+        }
+    }
+}
+
+/// Apply what the position source reported since the last frame, and notice when the
+/// fix is lost
+pub fn apply_position_reports(
+    time: Res<Time>,
+    feed: Res<PositionFeed>,
+    mut live: ResMut<LivePosition>,
+    // Last connection error, so a source that is down is not reported every retry
+    mut last_error: Local<String>,
+) {
+    let now = time.elapsed_secs_f64();
+    let messages = std::mem::take(&mut *feed.pending.lock());
+    for message in messages {
+        match message {
+            FeedMessage::Connected => {
+                info!("Connected to position source");
+                last_error.clear();
+                live.status = FixStatus::Waiting;
+            }
+            FeedMessage::Disconnected(reason) => {
+                if *last_error != reason {
+                    warn!("Position source unavailable: {}", reason);
+                    *last_error = reason;
+                }
+                live.status = FixStatus::Disconnected;
+            }
+            FeedMessage::Report(PositionReport::NoFix) => {
+                if live.status == FixStatus::Fix {
+                    lose_fix(&mut live, now);
+                }
+            }
+            FeedMessage::Report(PositionReport::Fix(fix)) => {
+                if live.status != FixStatus::Fix {
+                    info!("Position fix at {:.5}, {:.5}", fix.position.lat, fix.position.lon);
+                }
+                live.status = FixStatus::Fix;
+                live.fix = Some((fix.position, now));
+                // NMEA spreads a fix over several sentences, so keep what this one leaves out
+                live.altitude = fix.altitude.or(live.altitude);
+                live.speed = fix.speed.or(live.speed);
+                live.course = fix.course.or(live.course);
+                live.accuracy = fix.accuracy.or(live.accuracy);
+            }
+        }
+    }
+
+    if live.status == FixStatus::Fix && live.fix.is_some_and(|(_, received)| now - received > feed.fix_timeout) {
+        lose_fix(&mut live, now);
+    }
+}
+
+fn lose_fix(live: &mut LivePosition, now: f64) {
+    let since = live.fix.map_or(now, |(_, received)| received);
+    warn!("Position fix lost");
+    live.status = FixStatus::Lost { since };
+    // Movement from before the loss would carry the next position off along an old course
+    live.speed = None;
+    live.course = None;
+}
+
+/// Ease the camera position towards the reported one, carrying on along the course
+/// between reports
+pub fn smooth_live_position(time: Res<Time>, feed: Res<PositionFeed>, mut live: ResMut<LivePosition>) {
+    let Some((position, received)) = live.fix else {
+        return;
+    };
+
+    let mut target = position;
+    if let (FixStatus::Fix, Some(speed), Some(course)) = (live.status, live.speed, live.course) {
+        let elapsed = (time.elapsed_secs_f64() - received).min(MAX_EXTRAPOLATION);
+        target = destination_point(position, course, speed * elapsed);
+    }
+
+    let current = live.smoothed.unwrap_or(target);
+    let distance = haversine_distance(current, target);
+    live.smoothed = Some(if distance > MAX_SMOOTHED_DISTANCE || feed.smoothing <= 0.0 {
+        target
+    } else {
+        let t = 1.0 - (-time.delta_secs_f64() / feed.smoothing).exp();
+        destination_point(current, initial_bearing(current, target), distance * t)
+    });
+}
+
+/// Let the camera follow the live position (L)
+pub fn position_feed_actions(
+    action_state: Res<ActionState>,
+    feed: Res<PositionFeed>,
+    mut live: ResMut<LivePosition>,
+    mut fly_to: ResMut<FlyToState>,
+    mut orbit_camera: ResMut<OrbitCamera>,
+    mut track_playback: ResMut<TrackPlayback>,
+) {
+    if feed.address.is_none() || !action_state.just_pressed(InputAction::ToggleLiveFollow) {
+        return;
+    }
+
+    live.follow = !live.follow;
+    if live.follow {
+        // The live position owns the camera until it is switched off
+        fly_to.flight = None;
+        track_playback.follow = false;
+        live.camera_heading = None;
+    } else {
+        // Let the orbit camera pick up from the current pose if the globe is in view
+        orbit_camera.active = false;
+    }
+    info!("Follow live position: {}", if live.follow { "ON" } else { "OFF" });
+}
+
+/// Put the camera at the live position, looking around with the mouse or along the course
+#[allow(clippy::too_many_arguments)]
+pub fn follow_live_position(
+    time: Res<Time>,
+    feed: Res<PositionFeed>,
+    action_state: Res<ActionState>,
+    control_settings: Res<ControlSettings>,
+    path_player: Res<CameraPathPlayer>,
+    track_playback: Res<TrackPlayback>,
+    floating_origin: Res<FloatingOrigin>,
+    globe_morph: Res<GlobeMorph>,
+    mut live: ResMut<LivePosition>,
+    mut mouse_look_state: ResMut<MouseLookState>,
+    mut camera_query: Query<(&mut Transform, &WorldPosition), With<Camera3d>>,
+) {
+    // A playing camera path wins, as does the track follow camera once it is switched on
+    if !live.is_following() || path_player.is_playing() || track_playback.is_following() {
+        return;
+    }
+    let Some(position) = live.smoothed else {
+        return;
+    };
+    let Ok((mut transform, camera_world)) = camera_query.get_single_mut() else {
+        return;
+    };
+    let delta = time.delta_secs();
+
+    let mut look = Vec2::ZERO;
+    if !mouse_look_state.mouse_motion.is_nan() {
+        look = -mouse_look_state.mouse_motion * control_settings.look_sensitivity;
+    }
+    look += action_state.look_stick * Vec2::new(-1.0, 1.0) * control_settings.gamepad_look_speed * delta;
+    if control_settings.invert_look_y {
+        look.y = -look.y;
+    }
+    mouse_look_state.mouse_motion = Vec2::ZERO;
+    mouse_look_state.pitch = (mouse_look_state.pitch + look.y).clamp(-1.5, 1.5);
+
+    // While moving the course sets the heading; standing still, or without a course, the
+    // mouse does
+    let moving = live.status == FixStatus::Fix && live.speed.is_some_and(|speed| speed > MIN_COURSE_SPEED);
+    let heading = match (feed.follow_course && moving, live.course, live.camera_heading) {
+        (true, Some(course), Some(current)) => {
+            let change = (course - current + 180.0).rem_euclid(360.0) - 180.0;
+            current + change * (1.0 - (-HEADING_RATE * delta as f64).exp())
+        }
+        (true, Some(course), None) => course,
+        _ => -((mouse_look_state.yaw + look.x) as f64).to_degrees(),
+    }
+    .rem_euclid(360.0);
+    live.camera_heading = Some(heading);
+    mouse_look_state.yaw = -(heading.to_radians() as f32);
+
+    let (x, z) = lat_lon_to_world(position.lat, position.lon);
+    let mut camera = DVec3::new(x, altitude_to_world(feed.camera_height, position.lat), z);
+    // Stay in the world copy the camera is in
+    camera.x -= WORLD_SIZE * ((camera.x - camera_world.0.x) / WORLD_SIZE).round();
+    // Over the bent map, like the accuracy circle
+    let camera = GlobeFrame::new(globe_morph.anchor).morph(camera, globe_morph.factor as f64);
+
+    transform.translation = floating_origin.world_to_render(camera);
+    transform.rotation = Quat::from_rotation_y(mouse_look_state.yaw) * Quat::from_rotation_x(mouse_look_state.pitch);
+}
+
+/// Draw a circle the size of the accuracy around the reported position, green with a fix
+/// and red once it is lost
+pub fn draw_live_position(
+    mut gizmos: Gizmos,
+    live: Res<LivePosition>,
+    floating_origin: Res<FloatingOrigin>,
+    globe_morph: Res<GlobeMorph>,
+    camera_query: Query<&WorldPosition, With<Camera3d>>,
+) {
+    let (Some((position, _)), Some(accuracy)) = (live.fix, live.accuracy) else {
+        return;
+    };
+    let Ok(camera_world) = camera_query.get_single() else {
+        return;
+    };
+
+    let (x, z) = lat_lon_to_world(position.lat, position.lon);
+    let mut center = DVec3::new(x, altitude_to_world(ACCURACY_CIRCLE_HEIGHT, position.lat), z);
+    center.x -= WORLD_SIZE * ((center.x - camera_world.0.x) / WORLD_SIZE).round();
+    let center = GlobeFrame::new(globe_morph.anchor).morph(center, globe_morph.factor as f64);
+
+    let color = if live.status == FixStatus::Fix {
+        Color::srgb(0.2, 0.9, 0.3)
+    } else {
+        Color::srgb(0.9, 0.2, 0.2)
+    };
+    let radius = (accuracy * mercator_scale(position.lat)) as f32;
+    let isometry = Isometry3d::new(floating_origin.world_to_render(center), Quat::from_rotation_x(FRAC_PI_2));
+    gizmos.circle(isometry, radius, color).resolution(64);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::geodesy::LatLon;
+    use crate::utils::position_source::PositionFix;
+
+    fn lines(mut input: &[u8]) -> Vec<Vec<u8>> {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let mut lines = Vec::new();
+        let mut line = Vec::new();
+        while runtime.block_on(read_line(&mut input, &mut line)).unwrap() {
+            lines.push(line.clone());
+        }
+        lines
+    }
+
+    fn report(speed: Option<f64>, course: Option<f64>) -> FeedMessage {
+        FeedMessage::Report(PositionReport::Fix(PositionFix {
+            position: LatLon::new(48.0, 11.0),
+            altitude: None,
+            speed,
+            course,
+            accuracy: None,
+        }))
+    }
+
+    #[test]
+    fn motion_from_before_a_lost_fix_is_forgotten() {
+        let mut app = App::new();
+        app.insert_resource(Time::<()>::default())
+            .insert_resource(PositionFeed::default())
+            .insert_resource(LivePosition::default())
+            .add_systems(Update, apply_position_reports);
+        let pending = app.world().resource::<PositionFeed>().pending.clone();
+
+        // RMC with motion, the fix lost, then a GGA-like fix without any
+        pending.lock().push(report(Some(12.0), Some(90.0)));
+        app.update();
+        assert_eq!(app.world().resource::<LivePosition>().speed, Some(12.0));
+        pending.lock().push(FeedMessage::Report(PositionReport::NoFix));
+        pending.lock().push(report(None, None));
+        app.update();
+
+        let live = app.world().resource::<LivePosition>();
+        assert_eq!(live.status, FixStatus::Fix);
+        assert_eq!((live.speed, live.course), (None, None));
+    }
+
+    #[test]
+    fn lines_keep_invalid_utf8() {
+        let read = lines(b"\xb5\x62\x01\xff$GPGGA,1\r\n$GPRMC,2");
+        assert_eq!(read, vec![b"\xb5\x62\x01\xff$GPGGA,1\r\n".to_vec(), b"$GPRMC,2".to_vec()]);
+        assert!(String::from_utf8_lossy(&read[0]).contains("$GPGGA,1"));
+    }
+
+    #[test]
+    fn long_lines_are_cut_and_the_next_line_still_read() {
+        let mut input = vec![b'x'; MAX_LINE_LENGTH * 3];
+        input.extend_from_slice(b"\n$GPGGA\n");
+        let read = lines(&input);
+        assert_eq!(read.len(), 2);
+        assert_eq!(read[0].len(), MAX_LINE_LENGTH);
+        assert_eq!(read[1], b"$GPGGA\n");
+    }
+}
@@ -0,0 +1,191 @@
+use bevy::prelude::*;
+use std::cmp::Ordering;
+use crate::resources::{OSMData, TokioRuntime, DebugSettings, FloatingOrigin, GlobeMorph, TileSelectionSettings, PrefetchSettings, PrefetchQueue, TileRegistry, TileKey, TileSource, CameraMotion, ZoomPolicy, MapLayers};
+use crate::components::{WorldPosition, TileState};
+use crate::events::{TileRequested, TileEvicted};
+use crate::resources::constants::{MIN_ZOOM_LEVEL, MAX_ZOOM_LEVEL};
+use crate::systems::tiles::{target_zoom_level, camera_frustum, view_parameters, request_tile, evict_tile};
+use crate::utils::projection::GlobeFrame;
+use crate::utils::tile_selection::{SelectionView, select_tiles};
+use crate::debug_log;
+
+// Fraction of the camera height that must be covered within the horizon before prefetching
+const MIN_TRAVEL_FRACTION: f32 = 0.1;
+// Rotation (radians) within the horizon before turning counts as moving
+const MIN_TURN_ANGLE: f32 = 0.2;
+// Lowest height (world units) a predicted camera position is clamped to
+const MIN_PREDICTED_HEIGHT: f32 = 1.0;
+
+/// Track the smoothed camera velocity from its precise world position
+pub fn track_camera_motion(
+    time: Res<Time>,
+    mut camera_motion: ResMut<CameraMotion>,
+    camera_query: Query<(&Transform, &WorldPosition), With<Camera3d>>,
+) {
+    if let Ok((transform, world_position)) = camera_query.get_single() {
+        camera_motion.update(world_position.0, transform.rotation, time.delta_secs());
+    }
+}
+
+/// Settle the prefetch loads still in flight before new ones are requested
+///
+/// Loads that finished, failed or were taken over by the view free their slot. Loads for
+/// tiles that left the predicted path are cancelled and their tiles evicted.
+pub fn maintain_prefetch_queue(
+    osm_data: Res<OSMData>,
+    debug_settings: Res<DebugSettings>,
+    mut queue: ResMut<PrefetchQueue>,
+    mut tile_registry: ResMut<TileRegistry>,
+    mut evicted_events: EventWriter<TileEvicted>,
+    mut tile_query: Query<&mut TileState>,
+) {
+    let PrefetchQueue { in_flight, wanted } = &mut *queue;
+    let mut cancelled = 0;
+
+    in_flight.retain(|key, (entity, load)| {
+        let Ok(mut state) = tile_query.get_mut(*entity) else {
+            return false;
+        };
+        if !matches!(*state, TileState::Requested | TileState::Downloading | TileState::Decoding) {
+            return false;
+        }
+        if osm_data.view_tiles.contains(key) {
+            // The view needs the tile now, so it loads on regardless
+            return false;
+        }
+        if wanted.contains(key) {
+            return true;
+        }
+        load.abort();
+        evict_tile(*entity, &mut state, &mut tile_registry, &mut evicted_events);
+        cancelled += 1;
+        false
+    });
+
+    if cancelled > 0 {
+        debug_log!(debug_settings, "Cancelled {} prefetch loads off the predicted path", cancelled);
+    }
+}
+
+/// Request tiles along the predicted camera path before the camera gets there
+///
+/// Prefetched tiles count as used until the camera is expected to reach them. They are
+/// requested after the tiles of the current view, and at most
+/// `PrefetchSettings::max_concurrent_loads` of them load at once.
+#[allow(clippy::too_many_arguments)]
+pub fn prefetch_tiles(
+    mut commands: Commands,
+    osm_data: Res<OSMData>,
+    mut queue: ResMut<PrefetchQueue>,
+    mut tile_registry: ResMut<TileRegistry>,
+    map_layers: Res<MapLayers>,
+    tokio_runtime: Res<TokioRuntime>,
+    debug_settings: Res<DebugSettings>,
+    selection_settings: Res<TileSelectionSettings>,
+    prefetch_settings: Res<PrefetchSettings>,
+    zoom_policy: Res<ZoomPolicy>,
+    floating_origin: Res<FloatingOrigin>,
+    globe_morph: Res<GlobeMorph>,
+    camera_motion: Res<CameraMotion>,
+    time: Res<Time>,
+    mut requested_events: EventWriter<TileRequested>,
+    camera_query: Query<(&Transform, &WorldPosition, &Camera, &Projection), With<Camera3d>>,
+) {
+    let Ok((transform, world_position, camera, projection)) = camera_query.get_single() else {
+        return;
+    };
+    let horizon = prefetch_settings.horizon_secs;
+    let height = transform.translation.y.max(MIN_PREDICTED_HEIGHT);
+    let (fov, viewport_height) = view_parameters(camera, projection);
+
+    // Zoom level the altitude is trending towards by the end of the horizon
+    let final_height = (height + camera_motion.velocity.y as f32 * horizon).max(MIN_PREDICTED_HEIGHT);
+    // Judged with the same hysteresis as the base zoom, so hovering near a threshold prefetches nothing
+    let base_zoom = zoom_policy.current;
+    let predicted_zoom = zoom_policy.level_for(target_zoom_level(final_height, fov, viewport_height));
+
+    // Nothing to predict for a camera that is (nearly) standing still
+    let travel = camera_motion.velocity.length() as f32 * horizon;
+    let turn = camera_motion.angular_velocity.length() * horizon;
+    if travel < height * MIN_TRAVEL_FRACTION && turn < MIN_TURN_ANGLE && predicted_zoom == base_zoom {
+        queue.wanted.clear();
+        return;
+    }
+
+    let globe = GlobeFrame::new(globe_morph.anchor);
+    let current_time = time.elapsed_secs();
+    let steps = prefetch_settings.steps.max(1);
+
+    // The current view at the next zoom level first, when the altitude is about to cross a threshold
+    // Halving the allowed error selects one level finer, doubling it one level coarser
+    let zoom_error_scale = match predicted_zoom.cmp(&base_zoom) {
+        Ordering::Greater => Some(0.5),
+        Ordering::Less => Some(2.0),
+        Ordering::Equal => None,
+    };
+    let zoom_pass = zoom_error_scale.map(|scale| (0.0, *transform, scale));
+
+    // Then the predicted poses along the path, nearest first
+    let path_passes = (1..=steps).map(|step| {
+        let seconds = horizon * step as f32 / steps as f32;
+        let mut predicted = *transform;
+        let world = camera_motion.predict_position(world_position.0, seconds);
+        predicted.translation = floating_origin.world_to_render(world);
+        predicted.translation.y = predicted.translation.y.max(MIN_PREDICTED_HEIGHT);
+        predicted.rotation = camera_motion.predict_rotation(transform.rotation, seconds);
+        (seconds, predicted, 1.0)
+    });
+
+    // Everything on the path, in the order it is needed, with the time it is needed by
+    let mut wanted = Vec::new();
+    queue.wanted.clear();
+    for (seconds, predicted, error_scale) in zoom_pass.into_iter().chain(path_passes) {
+        let frustum = camera_frustum(&predicted, projection);
+        let view = SelectionView {
+            frustum: &frustum,
+            floating_origin: &floating_origin,
+            camera_pos: predicted.translation,
+            fov,
+            viewport_height,
+            globe: &globe,
+            morph_factor: globe_morph.factor as f64,
+        };
+        let tiles = select_tiles(
+            &view,
+            MIN_ZOOM_LEVEL,
+            MAX_ZOOM_LEVEL,
+            selection_settings.max_screen_space_error * error_scale,
+            prefetch_settings.max_tiles,
+        );
+
+        debug_log!(debug_settings, "Prefetching {} tiles for {:.1} s ahead", tiles.len(), seconds);
+
+        for (x, y, zoom, _) in tiles {
+            let key = TileKey::new(TileSource::Focus, x, y, zoom);
+            if queue.wanted.insert(key) {
+                wanted.push((key, seconds));
+            }
+        }
+    }
+
+    for (key, seconds) in wanted {
+        if queue.in_flight.len() >= prefetch_settings.max_concurrent_loads {
+            break;
+        }
+        if tile_registry.contains(&key) {
+            continue;
+        }
+        let load = request_tile(
+            &mut commands,
+            &osm_data,
+            &mut tile_registry,
+            &map_layers,
+            &mut requested_events,
+            current_time + seconds, // Keep the tile until the camera should have arrived
+            &tokio_runtime,
+            &debug_settings,
+            key,
+        );
+        queue.in_flight.insert(key, load);
+    }
+}
@@ -0,0 +1,53 @@
+use bevy::prelude::*;
+use crate::components::WorldPosition;
+use crate::resources::{CameraPose, DebugSettings, MapLayers, Session, SessionSettings};
+
+/// Bring back the layers and debug state of the last session
+///
+/// The camera pose is restored by `setup`, through the start view.
+pub fn restore_session(
+    session_settings: Res<SessionSettings>,
+    mut map_layers: ResMut<MapLayers>,
+    mut debug_settings: ResMut<DebugSettings>,
+) {
+    let Some(session) = &session_settings.restored else {
+        return;
+    };
+
+    for layer in map_layers.layers.iter_mut() {
+        layer.enabled = session.layers.contains(&layer.name);
+    }
+    debug_settings.debug_mode = session.debug_mode;
+    info!("Restored session from {}", session_settings.path.display());
+}
+
+/// Write the camera pose, enabled layers and debug state when the app exits
+pub fn save_session_on_exit(
+    mut exit_events: EventReader<AppExit>,
+    session_settings: Res<SessionSettings>,
+    map_layers: Res<MapLayers>,
+    debug_settings: Res<DebugSettings>,
+    camera_query: Query<(&Transform, &WorldPosition), With<Camera3d>>,
+) {
+    if exit_events.read().count() == 0 || !session_settings.enabled {
+        return;
+    }
+    let Ok((transform, camera_world)) = camera_query.get_single() else {
+        return;
+    };
+    // Read from the camera itself, as the orbit and follow cameras leave mouse look behind
+    let (yaw, pitch, _) = transform.rotation.to_euler(EulerRot::YXZ);
+
+    let session = Session {
+        camera: CameraPose::from_camera(camera_world.0, yaw, pitch),
+        layers: map_layers.layers.iter()
+            .filter(|layer| layer.enabled)
+            .map(|layer| layer.name.clone())
+            .collect(),
+        debug_mode: debug_settings.debug_mode,
+    };
+    match session.save(&session_settings.path) {
+        Ok(()) => info!("Saved session to {}", session_settings.path.display()),
+        Err(e) => warn!("Failed to save session to {}: {}", session_settings.path.display(), e),
+    }
+}
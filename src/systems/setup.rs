@@ -1,7 +1,14 @@
 use bevy::prelude::*;
-use crate::resources::constants::{DEFAULT_ZOOM_LEVEL, MIN_ZOOM_LEVEL, MAX_ZOOM_LEVEL, BACKGROUND_ZOOM_LEVEL, GRONINGEN_X, GRONINGEN_Y, MAX_TILE_INDEX, zoom_level_from_camera_height};
+use bevy::pbr::DistanceFog;
+use bevy::image::CompressedImageFormats;
+use bevy::render::renderer::RenderDevice;
+use crate::resources::constants::{DEFAULT_ZOOM_LEVEL, BACKGROUND_ZOOM_LEVEL, MAX_TILE_INDEX};
+use crate::utils::projection::{lat_lon_to_world, altitude_to_world};
+use crate::utils::coordinate_conversion::world_to_tile_coords;
 use crate::osm::init_tile_cache;
-use crate::resources::{OSMData, TokioRuntime, DebugSettings};
+use crate::resources::{OSMData, TokioRuntime, DebugSettings, FloatingOrigin, StartView, MouseLookState};
+use crate::components::WorldPosition;
+use bevy::math::DVec3;
 use std::sync::Arc;
 use parking_lot::Mutex;
 use tokio::runtime::Runtime;
@@ -17,72 +24,70 @@ pub fn init_resources() -> (OSMData, TokioRuntime) {
         eprintln!("Warning: Failed to initialize tile cache: {}", e);
     }
 
-    // Calculate zoom level height thresholds using the standardized function
-    let mut height_thresholds = Vec::new();
-    
-    // Start from a low height (near the ground) and go up
-    // This provides a more balanced distribution following OpenStreetMap conventions
-    let heights = [
-        1.5, 3.0, 5.0, 8.0, 12.0, 20.0, 35.0, 60.0, 
-        100.0, 150.0, 200.0, 250.0, 300.0, 400.0, 500.0, 
-        700.0, 1000.0, 1500.0, 2000.0
-    ];
-    
-    for height in heights.iter() {
-        let zoom = zoom_level_from_camera_height(*height);
-        if zoom >= MIN_ZOOM_LEVEL && zoom <= MAX_ZOOM_LEVEL {
-            height_thresholds.push((*height, zoom));
-        }
-    }
-    
-    // Sort by height ascending (lower height = higher zoom)
-    height_thresholds.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
-
-    // Log the height thresholds for debugging
-    for (height, zoom) in &height_thresholds {
-        println!("Height {:.1}: zoom level {}", height, zoom);
-    }
-
+    // The base zoom level follows the camera through the ZoomPolicy resource
     let osm_data = OSMData {
-        tiles: Vec::new(),
-        background_tiles: Vec::new(),
-        loaded_tiles: Vec::new(),
-        loaded_background_tiles: Vec::new(),
         pending_tiles: Arc::new(Mutex::new(Vec::new())),
-        current_zoom: DEFAULT_ZOOM_LEVEL,
         background_zoom: BACKGROUND_ZOOM_LEVEL,
-        total_time: 0.0,
+        view_tiles: Default::default(),
+        // Filled in by detect_texture_formats once the GPU is known
+        texture_formats: CompressedImageFormats::NONE,
     };
 
     (osm_data, TokioRuntime(runtime))
 }
 
+/// Detect which compressed texture formats the GPU can sample, for the tile loaders
+pub fn detect_texture_formats(
+    render_device: Option<Res<RenderDevice>>,
+    mut osm_data: ResMut<OSMData>,
+) {
+    if let Some(render_device) = render_device {
+        osm_data.texture_formats = CompressedImageFormats::from_features(render_device.features());
+    }
+    info!("Compressed tile texture formats: {:?}", osm_data.texture_formats);
+}
+
 /// Setup the scene with initial camera, lighting, and ground plane
 pub fn setup(
     mut commands: Commands,
     _meshes: ResMut<Assets<Mesh>>,
     _materials: ResMut<Assets<StandardMaterial>>,
     debug_settings: Res<DebugSettings>,
+    start_view: Res<StartView>,
+    mut floating_origin: ResMut<FloatingOrigin>,
+    mut mouse_look_state: ResMut<MouseLookState>,
 ) {
-    // Calculate world coordinates for Groningen location
-    // With our new coordinate system:
-    // - X = OSM tile X (increasing eastward)
-    // - Z = OSM tile Y (increasing southward)
-    let world_x = GRONINGEN_X as f32;
-    let world_z = GRONINGEN_Y as f32;  // Direct mapping now, no need to invert
+    // Calculate world coordinates for the start location
+    // World space is Web Mercator in metres:
+    // - X = easting (increasing eastward, like OSM tile X)
+    // - Z = negated northing (increasing southward, like OSM tile Y)
+    let start = start_view.initial;
+    let (world_x, world_z) = lat_lon_to_world(start.lat, start.lon);
+    let camera_height = altitude_to_world(start.altitude, start.lat);
+    let camera_world = DVec3::new(world_x, camera_height, world_z);
+
+    // Start with the render-space origin right below the camera
+    floating_origin.origin = DVec3::new(world_x, 0.0, world_z);
+    let camera_render = floating_origin.world_to_render(camera_world);
 
-    // Camera - positioned slightly elevated with a first-person view
-    // Position at Groningen coordinates
+    // The cameras steer by these angles, so they have to match the initial view
+    mouse_look_state.yaw = start.yaw();
+    mouse_look_state.pitch = start.pitch_radians();
+
+    // Camera - first-person view at the start location
     commands.spawn((
         Camera3d::default(),
-        PerspectiveProjection {
+        Projection::Perspective(PerspectiveProjection {
             fov: std::f32::consts::PI / 2.0, // 90 degrees FOV
             aspect_ratio: 1.0, // Will be updated by Bevy
-            near: 0.1,
-            far: 10000.0,
-        },
-        Transform::from_xyz(world_x, 200.0, world_z) // Higher camera for better overview
-            .looking_at(Vec3::new(world_x, 0.0, world_z), Vec3::Y),
+            near: 0.5, // Metres - reverse-Z keeps depth precise far beyond this
+            far: 5.0e7, // Far enough to see the whole Mercator world from orbit
+        }),
+        Transform::from_translation(camera_render)
+            .with_rotation(Quat::from_rotation_y(mouse_look_state.yaw) * Quat::from_rotation_x(mouse_look_state.pitch)),
+        WorldPosition(camera_world),
+        // Hides the far edge of the map, the distances follow the horizon every frame
+        DistanceFog::default(),
     ));
 
     // Main light - directional to simulate sunlight
@@ -104,7 +109,8 @@ pub fn setup(
     // Ground plane removed - not needed with tile-based map
 
     // Log current position for debugging (console only)
-    debug_log!(debug_settings, "Starting at world position: ({}, {})", world_x, world_z);
-    debug_log!(debug_settings, "Corresponding to OSM tile: ({}, {})", GRONINGEN_X, GRONINGEN_Y);
+    let (tile_x, tile_y) = world_to_tile_coords(world_x, world_z, DEFAULT_ZOOM_LEVEL);
+    debug_log!(debug_settings, "Starting at world position: ({:.1}, {:.1}), altitude {} m", world_x, world_z, start.altitude);
+    debug_log!(debug_settings, "Corresponding to OSM tile: ({}, {})", tile_x, tile_y);
     debug_log!(debug_settings, "Zoom level: {}, MAX_TILE_INDEX: {}", DEFAULT_ZOOM_LEVEL, MAX_TILE_INDEX);
 } 
\ No newline at end of file
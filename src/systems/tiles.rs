@@ -1,301 +1,195 @@
 use bevy::prelude::*;
-use crate::resources::{OSMData, TokioRuntime, DebugSettings};
-use crate::components::{TileCoords};
-use crate::osm::{OSMTile, load_tile_image, create_tile_mesh, create_fallback_tile_mesh};
-use crate::utils::coordinate_conversion::world_to_tile_coords;
-use crate::resources::constants::{max_tile_index, MIN_ZOOM_LEVEL, MAX_ZOOM_LEVEL, BACKGROUND_ZOOM_LEVEL};
+use bevy::render::camera::CameraProjection;
+use bevy::image::CompressedImageFormats;
+use bevy::render::primitives::Frustum;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use parking_lot::Mutex;
+use tokio::task::AbortHandle;
+use crate::resources::{OSMData, TokioRuntime, DebugSettings, FloatingOrigin, GlobeMorph, TileSelectionSettings, TileRegistry, TileKey, TileSource, TileProgress, TileResidency, ZoomPolicy, HorizonSettings, TileUploadSettings, MapLayers};
+use crate::components::{TileCoords, TileState, TileMemory, BackgroundTile, WorldPosition};
+use bevy::math::DVec3;
+use crate::events::{TileRequested, TileLoaded, TileFailed, TileEvicted};
+use crate::osm::{OSMTile, fetch_tile_bytes, decode_tile_texture, create_tile_mesh, create_fallback_tile_mesh};
+use crate::utils::coordinate_conversion::tile_to_world;
+use crate::resources::constants::{MIN_ZOOM_LEVEL, MAX_ZOOM_LEVEL};
+use crate::utils::projection::{GlobeFrame, tile_size_world, world_units_per_pixel, zoom_for_resolution};
+use crate::utils::tile_selection::{SelectionView, select_tiles, horizon_rings};
+use crate::systems::horizon::visible_ground_distance;
 use crate::debug_log;
 
-// Process tiles based on camera position and view direction
+// Process tiles based on the camera frustum and the screen-space error of each tile
+#[allow(clippy::too_many_arguments)]
 pub fn process_tiles(
+    mut commands: Commands,
     mut osm_data: ResMut<OSMData>,
+    mut tile_registry: ResMut<TileRegistry>,
+    map_layers: Res<MapLayers>,
     tokio_runtime: Res<TokioRuntime>,
     debug_settings: Res<DebugSettings>,
-    camera_query: Query<(&Transform, &Camera), With<Camera3d>>,
+    selection_settings: Res<TileSelectionSettings>,
+    horizon_settings: Res<HorizonSettings>,
+    zoom_policy: Res<ZoomPolicy>,
+    floating_origin: Res<FloatingOrigin>,
+    globe_morph: Res<GlobeMorph>,
+    time: Res<Time>,
+    mut requested_events: EventWriter<TileRequested>,
+    camera_query: Query<(&Transform, &Camera, &Projection), With<Camera3d>>,
 ) {
     // Skip if we have no camera yet
-    if let Ok((camera_transform, _camera)) = camera_query.get_single() {
+    if let Ok((camera_transform, camera, projection)) = camera_query.get_single() {
         let camera_pos = camera_transform.translation;
-        let camera_forward = camera_transform.forward();
         
-        // Calculate base zoom level from camera height - this determines the detail level
-        let base_zoom = calculate_base_zoom_level(camera_pos.y);
-        
-        // Update global zoom level for UI and other systems
-        osm_data.current_zoom = base_zoom;
+        // Base zoom level from the zoom policy - used for the background layer
+        let (fov, viewport_height) = view_parameters(camera, projection);
+        let base_zoom = zoom_policy.current;
         
         // Set a fixed lower zoom level for background (global context)
         let background_zoom = (base_zoom.saturating_sub(4)).max(MIN_ZOOM_LEVEL).min(6);
         osm_data.background_zoom = background_zoom;
         
+        let frustum = camera_frustum(camera_transform, projection);
+        let globe = GlobeFrame::new(globe_morph.anchor);
+        let view = SelectionView {
+            frustum: &frustum,
+            floating_origin: &floating_origin,
+            camera_pos,
+            fov,
+            viewport_height,
+            globe: &globe,
+            morph_factor: globe_morph.factor as f64,
+        };
+        
         // Generate adaptive tiles with varying zoom levels
-        // This system uses larger tiles (lower zoom) for areas further from view center
+        // The quadtree selection uses larger tiles (lower zoom) where they look just as sharp
         generate_adaptive_tiles(
+            &mut commands,
             &mut osm_data,
+            &mut tile_registry,
+            &map_layers,
+            &mut requested_events,
+            time.elapsed_secs(),
             &tokio_runtime,
             &debug_settings,
-            camera_pos,
-            camera_forward.into(),
+            &selection_settings,
+            &horizon_settings,
+            &view,
             base_zoom,
+            visible_ground_distance(floating_origin.render_to_world(camera_pos), projection),
         );
     }
 }
 
-// Calculate appropriate base zoom level from camera height
-pub fn calculate_base_zoom_level(height: f32) -> u32 {
-    // Reduce height increments to make zoom level changes more responsive
-    match height {
-        h if h <= 1.0 => 19,   // Level 19: Local highways, crossings (1:1000 scale)
-        h if h <= 2.0 => 18,   // Level 18: Buildings, trees (1:2000 scale)
-        h if h <= 4.0 => 17,   // Level 17: Building blocks, parks, addresses
-        h if h <= 8.0 => 16,   // Level 16: Streets
-        h if h <= 15.0 => 15,  // Level 15: Small roads
-        h if h <= 30.0 => 14,  // Level 14: Detailed roads
-        h if h <= 60.0 => 13,  // Level 13: Villages, suburbs
-        h if h <= 120.0 => 12, // Level 12: Towns, city districts
-        h if h <= 250.0 => 11, // Level 11: Cities
-        h if h <= 500.0 => 10, // Level 10: Metropolitan areas
-        h if h <= 1000.0 => 9, // Level 9: Large metro areas
-        h if h <= 2000.0 => 8, // Level 8
-        h if h <= 4000.0 => 7, // Level 7: Small countries, US states
-        h if h <= 8000.0 => 6, // Level 6: Large European countries
-        h if h <= 16000.0 => 5, // Level 5: Large African countries
-        h if h <= 32000.0 => 4, // Level 4
-        h if h <= 64000.0 => 3, // Level 3: Largest countries
-        h if h <= 128000.0 => 2, // Level 2: Subcontinental areas
-        _ => 1,                  // Level 1: Whole world
+// Fractional zoom level for a camera height
+// The zoom level whose texels match the size of a screen pixel on the ground straight below
+// the camera, so detail follows the real metres-per-pixel on screen
+pub fn target_zoom_level(height: f32, fov: f32, viewport_height: f32) -> f64 {
+    let resolution = world_units_per_pixel(height as f64, fov as f64, viewport_height as f64);
+    zoom_for_resolution(resolution).clamp(MIN_ZOOM_LEVEL as f64, MAX_ZOOM_LEVEL as f64)
+}
+
+/// Update the base zoom level from the camera height, with hysteresis
+pub fn update_zoom_level(
+    mut zoom_policy: ResMut<ZoomPolicy>,
+    debug_settings: Res<DebugSettings>,
+    time: Res<Time>,
+    camera_query: Query<(&Transform, &Camera, &Projection), With<Camera3d>>,
+) {
+    let Ok((transform, camera, projection)) = camera_query.get_single() else {
+        return;
+    };
+    let (fov, viewport_height) = view_parameters(camera, projection);
+    let target = target_zoom_level(transform.translation.y, fov, viewport_height);
+    if zoom_policy.update(target, time.elapsed_secs()) {
+        debug_log!(debug_settings, "Zoom level {} (target {:.2})", zoom_policy.current, target);
     }
 }
 
-// Generate an adaptive grid of tiles with varying zoom levels
+// Vertical field of view and viewport height (in logical pixels) used for zoom selection
+pub fn view_parameters(camera: &Camera, projection: &Projection) -> (f32, f32) {
+    let fov = match projection {
+        Projection::Perspective(perspective) => perspective.fov,
+        Projection::Orthographic(_) => std::f32::consts::FRAC_PI_4,
+    };
+    let viewport_height = camera
+        .logical_viewport_size()
+        .map(|size| size.y)
+        .unwrap_or(720.0);
+    (fov, viewport_height)
+}
+
+// Camera frustum in render space, built from the current transform so it is not a frame behind
+// (Bevy only updates the Frustum component after this frame's movement and rebasing)
+pub fn camera_frustum(transform: &Transform, projection: &Projection) -> Frustum {
+    projection.compute_frustum(&GlobalTransform::from(*transform))
+}
+
+// Generate the tiles for the current view: low zoom background rings out to the horizon
+// for context, and the frustum and screen-space error based selection on top of it
+#[allow(clippy::too_many_arguments)]
 fn generate_adaptive_tiles(
+    commands: &mut Commands,
     osm_data: &mut OSMData,
+    tile_registry: &mut TileRegistry,
+    map_layers: &MapLayers,
+    requested_events: &mut EventWriter<TileRequested>,
+    current_time: f32,
     tokio_runtime: &TokioRuntime,
     debug_settings: &DebugSettings,
-    camera_pos: Vec3,
-    camera_forward: Vec3,
+    selection_settings: &TileSelectionSettings,
+    horizon_settings: &HorizonSettings,
+    view: &SelectionView,
     base_zoom: u32,
+    max_ground_distance: f64,
 ) {
-    // Project camera forward onto XZ plane
-    let view_dir_xz = Vec3::new(camera_forward.x, 0.0, camera_forward.z).normalize();
-    
-    // Calculate viewing distance based on camera height and viewing angle
-    let cam_height = camera_pos.y;
-    let _angle_factor = 1.0 + (1.0 - camera_forward.y.abs()) * 2.0;
-    
-    // For more horizontal views, look farther ahead
-    // For more vertical views, look closer to camera position
-    let horizontal_factor = (1.0 - camera_forward.y.abs()).powf(0.5); // Square root for smoother transition
-    let view_distance = cam_height * 1.5 * (1.0 + horizontal_factor * 3.0);
-    
-    // Calculate the exact point on the ground where the camera ray intersects
-    // This is where we want to center our high-detail tiles
-    let ground_y = 0.0; // Ground level
-    let t = if camera_forward.y != 0.0 {
-        // Parameter for ray-plane intersection: camera_pos + t * camera_forward = point on ground
-        (ground_y - camera_pos.y) / camera_forward.y
-    } else {
-        // If camera is perfectly horizontal, use a default distance
-        view_distance
-    };
-    
-    // Only use the intersection point if it's in front of the camera (t > 0)
-    // and within a reasonable distance
-    let view_target = if t > 0.0 && t < view_distance * 2.0 {
-        camera_pos + camera_forward * t
-    } else {
-        // Fallback: look ahead based on camera height
-        camera_pos + view_dir_xz * view_distance
-    };
-    
-    debug_log!(debug_settings, "View target: ({:.1}, {:.1}, {:.1}), height: {:.1}", 
-              view_target.x, view_target.y, view_target.z, cam_height);
-    
-    // All tiles to load with their coordinates and priority
-    let mut tiles_to_load = Vec::new();
-    
-    // Handle background (global context) tiles - use even lower zoom level
-    // and much fewer tiles to reduce the total load
-    let bg_zoom = (base_zoom.saturating_sub(5)).max(MIN_ZOOM_LEVEL).min(4);
+    // Background tiles in rings that get coarser with distance, out to the horizon,
+    // so the ground fills the view even where the selection gives up at shallow pitch
+    let bg_zoom = base_zoom.saturating_sub(horizon_settings.ring_zoom_offset).max(MIN_ZOOM_LEVEL);
     osm_data.background_zoom = bg_zoom;
     
-    // Get tile at camera position for background layer
-    let (bg_center_x, bg_center_y) = world_to_tile_coords(camera_pos.x, camera_pos.z, bg_zoom);
-    
-    // Add minimal set of background tiles (just enough for context)
-    let bg_range = 1; // Minimal background
-    for x_offset in -bg_range..=bg_range {
-        for y_offset in -bg_range..=bg_range {
-            let tile_x = (bg_center_x as i32 + x_offset).max(0) as u32;
-            let tile_y = (bg_center_y as i32 + y_offset).max(0) as u32;
-            
-            let priority = 1000 + x_offset.abs() + y_offset.abs(); // Lowest priority
-            tiles_to_load.push((tile_x, tile_y, bg_zoom, priority, true)); // true = background
-        }
-    }
-    
-    // Create a multi-level adaptive grid around the view target
-    // The key is to use larger tiles (lower zoom) for areas further from the view center
-    
-    // Determine the highest zoom level we'll use (based on camera height)
-    let highest_zoom = base_zoom.min(MAX_ZOOM_LEVEL);
-    
-    // OPTIMIZATION: Create much more aggressive zoom level reduction
-    // Based on camera height, dynamically calculate how many zoom levels to use
-    // and drastically reduce the number of tiles loaded
-    
-    // Dynamic zoom reduction based on camera height
-    let max_zoom_levels = if cam_height > 500.0 {
-        1 // At very high heights, just use one zoom level
-    } else if cam_height > 200.0 {
-        2 // At high heights, use two zoom levels
+    // Close to the background zoom the selection already covers everything at that detail
+    let background_tiles = if base_zoom > bg_zoom + 1 {
+        horizon_rings(
+            view,
+            bg_zoom,
+            MIN_ZOOM_LEVEL,
+            horizon_settings.ring_half_width,
+            max_ground_distance,
+        )
     } else {
-        3 // At lower heights, use three zoom levels for more detail variation
+        Vec::new()
     };
     
-    // Create zoom level array
-    let mut zoom_levels = Vec::with_capacity(max_zoom_levels);
-    
-    // Add highest zoom first - this is the center of view
-    zoom_levels.push(highest_zoom);
-    
-    // Add lower zoom levels as needed
-    if max_zoom_levels > 1 {
-        zoom_levels.push((highest_zoom.saturating_sub(2)).max(MIN_ZOOM_LEVEL));
-    }
-    
-    if max_zoom_levels > 2 {
-        zoom_levels.push((highest_zoom.saturating_sub(4)).max(MIN_ZOOM_LEVEL));
-    }
-    
-    // OPTIMIZATION: Keep track of covered areas to avoid loading redundant tiles
-    let mut covered_areas: Vec<(u32, u32, u32)> = Vec::new(); // (tile_x, tile_y, zoom)
-    
-    // 2. Generate tiles for each zoom level ring
-    for (ring_idx, &zoom) in zoom_levels.iter().enumerate() {
-        // Skip this ring if it's too similar to background
-        if zoom <= bg_zoom + 1 {
-            continue;
-        }
-        
-        // OPTIMIZATION: Use smaller radius for each ring
-        // Higher zoom levels (more detailed) should cover smaller areas
-        let radius = match ring_idx {
-            0 => 3, // Increased radius for highest detail ring
-            1 => 2, // Increased radius for middle ring 
-            _ => 2, // Increased radius for outer ring
-        };
-        
-        // Calculate target center - inner rings are centered precisely at view_target
-        // Outer rings can be slightly biased towards the camera position
-        let ring_center = if ring_idx == 0 {
-            view_target // Center ring is at exact view target
-        } else {
-            // Blend between view_target and camera_pos for outer rings
-            // This creates a better distribution for angled views
-            let blend_factor = ring_idx as f32 * 0.25; // 0.25 for ring 1, 0.5 for ring 2...
-            Vec3::lerp(
-                view_target,
-                Vec3::new(camera_pos.x, 0.0, camera_pos.z), // Project camera to ground
-                blend_factor
-            )
-        };
-        
-        // Get tile coordinates for center of this ring
-        let (center_x, center_y) = world_to_tile_coords(ring_center.x, ring_center.z, zoom);
-        
-        // Max tile index for this zoom level
-        let max_index = max_tile_index(zoom);
-        
-        // Priority base for this ring - inner rings have higher priority
-        let priority_base = ring_idx as i32 * 100;
-        
-        // Add tiles in a square pattern to cover the area
-        for x_offset in -radius as i32..=radius as i32 {
-            for y_offset in -radius as i32..=radius as i32 {
-                // For outer rings, focus on the edges and corners
-                let manhattan_dist = x_offset.abs() + y_offset.abs();
-                
-                // Skip inner tiles in outer rings to avoid redundancy
-                if ring_idx > 0 && manhattan_dist < ring_idx as i32 {
-                    continue;
-                }
-                
-                // Add extra coverage for diagonal directions
-                // This helps fill in gaps in the corners of the view
-                let is_diagonal = x_offset.abs() == y_offset.abs() && x_offset != 0;
-                
-                // Calculate tile coordinates with bounds checking
-                let tile_x = (center_x as i32 + x_offset).clamp(0, max_index as i32) as u32;
-                let tile_y = (center_y as i32 + y_offset).clamp(0, max_index as i32) as u32;
-                
-                // OPTIMIZATION: Check if this area is already covered by a higher zoom level
-                // Skip this tile if it would be redundant
-                let is_covered = covered_areas.iter().any(|&(x, y, z)| 
-                    is_same_area(tile_x, tile_y, zoom, x, y, z));
-                
-                if is_covered {
-                    continue;
-                }
-                
-                // Add this tile to covered areas
-                covered_areas.push((tile_x, tile_y, zoom));
-                
-                // Calculate priority - closer to center = higher priority
-                // Give diagonals slightly better priority to improve corner coverage
-                let priority_adjustment = if is_diagonal { -1 } else { 0 };
-                let priority = priority_base + manhattan_dist + priority_adjustment;
-                
-                // Add to tiles to load (false = not background)
-                tiles_to_load.push((tile_x, tile_y, zoom, priority, false));
-            }
-        }
-    }
-    
-    // No need to sort by priority - deduplication step will handle proper ordering
-    
-    // Further reduce total number of tiles
-    let max_total_tiles = 60; // Increased from 40 to allow better coverage
-    if tiles_to_load.len() > max_total_tiles {
-        // Keep all background tiles
-        let (background_tiles, mut foreground_tiles): (Vec<_>, Vec<_>) = 
-            tiles_to_load.into_iter().partition(|&(_, _, _, _, is_bg)| is_bg);
-        
-        // Sort foreground tiles by priority
-        foreground_tiles.sort_by_key(|&(_, _, _, priority, _)| priority);
-        
-        // Keep only the highest priority foreground tiles
-        foreground_tiles.truncate(max_total_tiles - background_tiles.len());
-        
-        // Recombine
-        tiles_to_load = background_tiles;
-        tiles_to_load.extend(foreground_tiles);
-    }
-    
-    // Remove duplicate tiles (keeping highest priority/zoom version)
-    // This ensures we don't load both a large tile and its higher detail equivalents
-    dedup_tiles(&mut tiles_to_load);
-    
-    // Process foreground and background tiles separately
-    let (foreground_tiles, background_tiles): (Vec<_>, Vec<_>) = 
-        tiles_to_load.into_iter()
-                    .partition(|&(_, _, _, _, is_bg)| !is_bg);
-    
+    // Walk the tile quadtree, refining visible tiles until their texels match the screen
+    // The selected tiles never overlap, so there is nothing to deduplicate
+    let foreground_tiles = select_tiles(
+        view,
+        MIN_ZOOM_LEVEL,
+        MAX_ZOOM_LEVEL,
+        selection_settings.max_screen_space_error,
+        selection_settings.max_tiles,
+    );
+    
+    // Prefetching hands over the tiles the view needs now
+    osm_data.view_tiles = foreground_tiles
+        .iter()
+        .map(|&(x, y, zoom, _)| TileKey::new(TileSource::Focus, x, y, zoom))
+        .collect();
+
     // Load foreground tiles
     if !foreground_tiles.is_empty() {
-        debug_log!(debug_settings, "Loading {} foreground tiles", foreground_tiles.len());
+        debug_log!(debug_settings, "Selected {} foreground tiles", foreground_tiles.len());
         
-        // Convert to the format expected by load_tiles
-        let fg_tiles: Vec<(u32, u32, u32, i32)> = foreground_tiles
-            .into_iter()
-            .map(|(x, y, z, p, _)| (x, y, z, p))
-            .collect();
-            
         load_tiles(
+            commands,
             osm_data,
+            tile_registry,
+            map_layers,
+            requested_events,
+            current_time,
             tokio_runtime,
             debug_settings,
-            &fg_tiles,
+            &foreground_tiles,
             16, // Increased concurrent loads for smoother loading
             false, // Not background
         );
@@ -305,121 +199,40 @@ fn generate_adaptive_tiles(
     if !background_tiles.is_empty() {
         debug_log!(debug_settings, "Loading {} background tiles", background_tiles.len());
         
-        // Convert to the format expected by load_tiles
-        let bg_tiles: Vec<(u32, u32, u32, i32)> = background_tiles
-            .into_iter()
-            .map(|(x, y, z, p, _)| (x, y, z, p))
-            .collect();
-            
         load_tiles(
+            commands,
             osm_data,
+            tile_registry,
+            map_layers,
+            requested_events,
+            current_time,
             tokio_runtime,
             debug_settings,
-            &bg_tiles,
+            &background_tiles,
             4, // Limit concurrent loads
             true, // Background tiles
         );
     }
 }
 
-// Helper function to remove duplicate tiles, preferring higher zoom (detail) levels
-fn dedup_tiles(tiles: &mut Vec<(u32, u32, u32, i32, bool)>) {
-    // Sort by coordinates and background flag
-    tiles.sort_by(|a, b| {
-        // Compare background flag first (group backgrounds together)
-        a.4.cmp(&b.4)
-        // Then by coordinates
-        .then(a.0.cmp(&b.0))
-        .then(a.1.cmp(&b.1))
-        // Then by zoom level in DESCENDING order (higher zoom = more detail)
-        .then(b.2.cmp(&a.2))
-    });
-    
-    // Dedup by coordinates - this keeps the first occurrence which will be 
-    // the highest zoom level (most detailed) version
-    let mut i = 0;
-    while i < tiles.len() {
-        let mut j = i + 1;
-        while j < tiles.len() {
-            // Check if tiles refer to the same area
-            if is_same_area(tiles[i].0, tiles[i].1, tiles[i].2, 
-                           tiles[j].0, tiles[j].1, tiles[j].2) &&
-               tiles[i].4 == tiles[j].4 { // And same background status
-                // Remove the duplicate (lower zoom version)
-                tiles.remove(j);
-            } else {
-                j += 1;
-            }
-        }
-        i += 1;
-    }
-    
-    // Resort by priority
-    tiles.sort_by_key(|&(_, _, _, priority, _)| priority);
-}
-
-// Helper function to check if two tiles refer to the same geographic area
-// A higher zoom tile (z2) is contained within a lower zoom tile (z1) if its coordinates
-// are derived from the lower zoom tile's coordinates
-fn is_same_area(x1: u32, y1: u32, z1: u32, x2: u32, y2: u32, z2: u32) -> bool {
-    // First check if tiles are exactly the same
-    if x1 == x2 && y1 == y2 && z1 == z2 {
-        return true;
-    }
-    
-    // If zoom levels are the same but coordinates differ, they're different areas
-    if z1 == z2 {
-        return false;
-    }
-    
-    // Handle case where one tile is at a higher zoom level than the other
-    if z1 < z2 {
-        // z1 is the lower zoom (larger tile)
-        // Check if the higher zoom tile (x2,y2,z2) is contained within (x1,y1,z1)
-        let zoom_diff = z2 - z1;
-        let factor = 1 << zoom_diff; // 2^zoom_diff
-        
-        // Calculate the expected range of higher zoom tiles that would fit in the lower zoom tile
-        let min_x2 = x1 * factor;
-        let min_y2 = y1 * factor;
-        let max_x2 = min_x2 + factor - 1;
-        let max_y2 = min_y2 + factor - 1;
-        
-        // Check if the higher zoom tile is within this range
-        return x2 >= min_x2 && x2 <= max_x2 && y2 >= min_y2 && y2 <= max_y2;
-    } else {
-        // z2 is the lower zoom (larger tile) - reverse the check
-        let zoom_diff = z1 - z2;
-        let factor = 1 << zoom_diff; // 2^zoom_diff
-        
-        // Calculate the expected range of higher zoom tiles that would fit in the lower zoom tile
-        let min_x1 = x2 * factor;
-        let min_y1 = y2 * factor;
-        let max_x1 = min_x1 + factor - 1;
-        let max_y1 = min_y1 + factor - 1;
-        
-        // Check if the higher zoom tile is within this range
-        return x1 >= min_x1 && x1 <= max_x1 && y1 >= min_y1 && y1 <= max_y1;
-    }
-}
-
 // Function to handle the actual tile loading logic (shared between adaptive and background systems)
-fn load_tiles(
+// Each new tile gets an entity in the Requested state right away, the mesh is added once it loads
+#[allow(clippy::too_many_arguments)]
+pub fn load_tiles(
+    commands: &mut Commands,
     osm_data: &mut OSMData,
+    tile_registry: &mut TileRegistry,
+    map_layers: &MapLayers,
+    requested_events: &mut EventWriter<TileRequested>,
+    current_time: f32,
     tokio_runtime: &TokioRuntime,
     debug_settings: &DebugSettings,
-    tiles_to_load: &[(u32, u32, u32, i32)], // (x, y, zoom, priority)
+    tiles_to_load: &[(i32, u32, u32, i32)], // (x, y, zoom, priority)
     max_concurrent_loads: usize,
     is_background: bool,
 ) {
     let mut concurrent_loads = 0;
-
-    // Get appropriate tracking list based on tile type
-    let loaded_tiles = if is_background {
-        &mut osm_data.loaded_background_tiles
-    } else {
-        &mut osm_data.loaded_tiles
-    };
+    let source = if is_background { TileSource::Background } else { TileSource::Focus };
 
     // Process tiles in order of priority
     for &(tile_x, tile_y, tile_zoom, _) in tiles_to_load {
@@ -428,294 +241,558 @@ fn load_tiles(
             break;
         }
 
-        // Check if tile is already loaded or pending
-        let already_pending = osm_data.pending_tiles.lock().iter().any(
-            |(x, y, z, _, bg)| *x == tile_x && *y == tile_y && *z == tile_zoom && *bg == is_background
-        );
+        // Skip tiles that are already requested or loaded
+        let key = TileKey::new(source, tile_x, tile_y, tile_zoom);
+        if tile_registry.contains(&key) {
+            continue;
+        }
+        concurrent_loads += 1;
 
-        if !loaded_tiles.contains(&(tile_x, tile_y, tile_zoom)) && !already_pending {
-            // Mark as loaded to prevent duplicate requests
-            loaded_tiles.push((tile_x, tile_y, tile_zoom));
-            concurrent_loads += 1;
+        request_tile(
+            commands,
+            osm_data,
+            tile_registry,
+            map_layers,
+            requested_events,
+            current_time,
+            tokio_runtime,
+            debug_settings,
+            key,
+        );
+    }
+}
 
-            // Clone the pending_tiles for the async task
-            let pending_tiles = osm_data.pending_tiles.clone();
-            let tile = OSMTile::new(tile_x, tile_y, tile_zoom);
+// Spawn the entity for a new tile without a mesh, register it and start loading it
+// Returns the entity and a handle that cancels the load
+#[allow(clippy::too_many_arguments)]
+pub fn request_tile(
+    commands: &mut Commands,
+    osm_data: &OSMData,
+    tile_registry: &mut TileRegistry,
+    map_layers: &MapLayers,
+    requested_events: &mut EventWriter<TileRequested>,
+    last_used: f32,
+    tokio_runtime: &TokioRuntime,
+    debug_settings: &DebugSettings,
+    key: TileKey,
+) -> (Entity, AbortHandle) {
+    let is_background = key.source == TileSource::Background;
+
+    // Log what we're loading
+    debug_log!(debug_settings, "Loading {} tile: {}, {}, zoom {}", 
+              if is_background { "background" } else { "focus" }, 
+              key.x, key.y, key.zoom);
+
+    let mut entity_builder = commands.spawn((
+        Name::new(format!("Tile {},{}, zoom {}", key.x, key.y, key.zoom)),
+        TileCoords {
+            x: key.x,
+            y: key.y,
+            zoom: key.zoom,
+            last_used,
+        },
+        TileState::Requested,
+    ));
+    
+    // Add background component if this is a background tile
+    if is_background {
+        entity_builder.insert(BackgroundTile);
+    }
+    
+    let entity = entity_builder.id();
+    tile_registry.insert(key, entity);
+    requested_events.send(TileRequested { entity, key });
+
+    let load = spawn_tile_load(
+        tokio_runtime,
+        osm_data.pending_tiles.clone(),
+        entity,
+        OSMTile::new(key.x, key.y, key.zoom),
+        map_layers,
+        0,
+        osm_data.texture_formats,
+        debug_settings.debug_mode,
+    );
+    (entity, load)
+}
 
-            // Log what we're loading
-            debug_log!(debug_settings, "Loading {} tile: {}, {}, zoom {}", 
-                      if is_background { "background" } else { "focus" }, 
-                      tile_x, tile_y, tile_zoom);
-            
-            // Use debug flag for async task
-            let debug_mode = debug_settings.debug_mode;
-
-            // Spawn async task to load the tile image using the Tokio runtime
-            tokio_runtime.0.spawn(async move {
-                match load_tile_image(&tile).await {
-                    Ok(image) => {
-                        if debug_mode {
-                            info!("Successfully loaded {} tile: {}, {}, zoom {}", 
-                                 if is_background { "background" } else { "focus" },
-                                 tile.x, tile.y, tile.z);
-                        }
-                        pending_tiles.lock().push((tile.x, tile.y, tile.z, Some(image), is_background));
-                    },
-                    Err(e) => {
-                        if debug_mode {
-                            info!("Failed to load {} tile: {}, {}, zoom {} - using fallback. Error: {}", 
-                                 if is_background { "background" } else { "focus" },
-                                 tile.x, tile.y, tile.z, e);
-                        }
-                        pending_tiles.lock().push((tile.x, tile.y, tile.z, None, is_background)); // None means use fallback
+// Spawn an async task that downloads the tile in every layer, composites and decodes it,
+// and reports its progress tagged with the layer generation of `map_layers`
+// `retries` is the number of earlier failed attempts for this tile; the returned handle
+// cancels the task
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_tile_load(
+    tokio_runtime: &TokioRuntime,
+    pending_tiles: Arc<Mutex<Vec<(u64, TileProgress)>>>,
+    entity: Entity,
+    tile: OSMTile,
+    map_layers: &MapLayers,
+    retries: u32,
+    texture_formats: CompressedImageFormats,
+    debug_mode: bool,
+) -> AbortHandle {
+    let layers = map_layers.active_at(tile.z);
+    let generation = map_layers.generation;
+    let report = move |progress| pending_tiles.lock().push((generation, progress));
+
+    // Spawn async task to load the tile image using the Tokio runtime
+    tokio_runtime.0.spawn(async move {
+        report(TileProgress::Downloading(entity));
+
+        // Layers that fail are left out, the tile only fails when all of them do
+        let mut sources = Vec::with_capacity(layers.len());
+        let mut last_error = None;
+        for layer in layers {
+            match fetch_tile_bytes(&tile, &layer).await {
+                Ok(bytes) => sources.push((layer, bytes)),
+                Err(e) => {
+                    if debug_mode {
+                        info!("Failed to load {} layer of tile: {}, {}, zoom {} - {}",
+                             layer.name, tile.x, tile.y, tile.z, e);
                     }
+                    last_error = Some(e);
                 }
-            });
+            }
         }
-    }
+
+        let result = match last_error {
+            Some(e) if sources.is_empty() => Err(e),
+            fetch_error => {
+                report(TileProgress::Decoding(entity));
+                // Decoding and the texture conversion are CPU heavy, so keep them off the async workers
+                let decode_tile = OSMTile::new(tile.x, tile.y, tile.z);
+                tokio::task::spawn_blocking(move || {
+                    decode_tile_texture(&decode_tile, &sources, texture_formats)
+                })
+                .await
+                .unwrap_or_else(|e| Err(e.into()))
+                .map(|(texture, decode_error)| (texture, decode_error.or(fetch_error)))
+            },
+        };
+
+        match result {
+            Ok((texture, None)) => {
+                if debug_mode {
+                    info!("Successfully loaded tile: {}, {}, zoom {}", tile.x, tile.y, tile.z);
+                }
+                report(TileProgress::Loaded(entity, texture));
+            },
+            Ok((texture, Some(e))) => {
+                if debug_mode {
+                    info!("Loaded tile: {}, {}, zoom {} without some layers - will retry. Error: {}",
+                         tile.x, tile.y, tile.z, e);
+                }
+                report(TileProgress::Incomplete(entity, texture, e.to_string(), retries));
+            },
+            Err(e) => {
+                if debug_mode {
+                    info!("Failed to load tile: {}, {}, zoom {} - using fallback. Error: {}", 
+                         tile.x, tile.y, tile.z, e);
+                }
+                report(TileProgress::Failed(entity, e.to_string(), retries));
+            }
+        }
+    })
+    .abort_handle()
 }
 
-// This system processes any pending tiles and creates entities for them
+// This system applies the progress of the async loaders to the tile entities,
+// adding meshes for loaded tiles and fallbacks for failed ones
+// Loaded tiles are applied within a per-frame budget, the rest wait for the next frame
+#[allow(clippy::too_many_arguments)]
 pub fn apply_pending_tiles(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     mut images: ResMut<Assets<Image>>,
-    mut osm_data: ResMut<OSMData>,
+    osm_data: Res<OSMData>,
+    map_layers: Res<MapLayers>,
+    mut tile_registry: ResMut<TileRegistry>,
     debug_settings: Res<DebugSettings>,
-    time: Res<Time>,
+    upload_settings: Res<TileUploadSettings>,
+    floating_origin: Res<FloatingOrigin>,
+    mut loaded_events: EventWriter<TileLoaded>,
+    mut failed_events: EventWriter<TileFailed>,
+    mut tile_query: Query<(&TileCoords, &mut TileState, Has<BackgroundTile>)>,
 ) {
     // Take pending tiles
     let mut pending = osm_data.pending_tiles.lock();
     let pending_tiles: Vec<_> = pending.drain(..).collect();
     drop(pending);
 
-    // Get current time for tile usage tracking
-    let current_time = time.elapsed_secs();
+    let started = Instant::now();
+    let max_upload_time = Duration::from_secs_f32(upload_settings.max_upload_millis.max(0.0) / 1000.0);
+    let mut uploads = 0;
+    let mut deferred = Vec::new();
 
-    // Process each pending tile
-    for (x, y, z, image_opt, is_background) in pending_tiles {
-        let tile = OSMTile::new(x, y, z);
-        
-        // Create entity with either the loaded image or a fallback
-        let entity = match image_opt {
-            Some(image) => {
+    // Process each progress report
+    for (generation, progress) in pending_tiles {
+        // Loads for an older layer stack were superseded by the reload that replaced it
+        if generation < map_layers.generation {
+            continue;
+        }
+
+        // Out of budget - keep the loaded tile, in order, for the next frame
+        // The first upload always goes ahead, so a tight budget still makes progress
+        if matches!(progress, TileProgress::Loaded(..) | TileProgress::Incomplete(..))
+            && (!deferred.is_empty()
+                || (uploads > 0
+                    && (uploads >= upload_settings.max_uploads_per_frame
+                        || started.elapsed() >= max_upload_time)))
+        {
+            deferred.push((generation, progress));
+            continue;
+        }
+
+        let entity = match &progress {
+            TileProgress::Downloading(entity)
+            | TileProgress::Decoding(entity)
+            | TileProgress::Loaded(entity, _)
+            | TileProgress::Incomplete(entity, ..)
+            | TileProgress::Failed(entity, _, _) => *entity,
+        };
+
+        // Tiles evicted while loading are no longer interesting
+        let Ok((tile_coords, mut state, is_background)) = tile_query.get_mut(entity) else {
+            continue;
+        };
+        let Some(key) = tile_registry.key_of(entity) else {
+            continue;
+        };
+        if *state == TileState::Evicting {
+            continue;
+        }
+        let tile = OSMTile::new(tile_coords.x, tile_coords.y, tile_coords.zoom);
+
+        // Tiles with layers left out show like loaded ones, then are retried like failed ones
+        let loaded_state = match &progress {
+            TileProgress::Incomplete(_, _, reason, retries) => TileState::Failed { reason: reason.clone(), retries: *retries },
+            _ => TileState::Ready,
+        };
+
+        match progress {
+            TileProgress::Downloading(_) => *state = TileState::Downloading,
+            TileProgress::Decoding(_) => *state = TileState::Decoding,
+            TileProgress::Loaded(_, texture) | TileProgress::Incomplete(_, texture, ..) => {
                 debug_log!(debug_settings, "Creating {} tile: {}, {}, zoom {}", 
-                          if is_background { "background" } else { "focus" }, x, y, z);
+                          if is_background { "background" } else { "focus" }, tile.x, tile.y, tile.z);
                 
-                // Standard tile creation with current time included
+                uploads += 1;
                 create_tile_mesh(
-                    &mut commands,
+                    &mut commands.entity(entity),
                     &mut meshes,
                     &mut materials,
                     &mut images,
                     &tile,
-                    image,
-                    current_time,
-                    is_background
-                )
+                    texture,
+                    is_background,
+                    &floating_origin,
+                );
+                *state = loaded_state;
+                tile_registry.set_ready(&key);
+                loaded_events.send(TileLoaded { entity, key });
             },
-            None => {
-                debug_log!(debug_settings, "Creating fallback entity for {} tile: {}, {}, zoom {}", 
-                          if is_background { "background" } else { "focus" }, x, y, z);
+            TileProgress::Failed(_, reason, retries) => {
+                debug_log!(debug_settings, "Creating fallback for {} tile: {}, {}, zoom {}", 
+                          if is_background { "background" } else { "focus" }, tile.x, tile.y, tile.z);
                 
-                // Standard fallback with current time included
-                create_fallback_tile_mesh(
-                    &mut commands,
-                    &mut meshes,
-                    &mut materials,
-                    &tile,
-                    current_time,
-                    is_background
-                )
+                // Only the first failure needs a fallback mesh, retries keep showing it
+                if retries == 0 {
+                    create_fallback_tile_mesh(
+                        &mut commands.entity(entity),
+                        &mut meshes,
+                        &mut materials,
+                        &tile,
+                        is_background,
+                        &floating_origin,
+                    );
+                }
+                failed_events.send(TileFailed { entity, key, reason: reason.clone(), retries });
+                *state = TileState::Failed { reason, retries };
             }
+        }
+    }
+
+    // Put the deferred tiles back in front of anything the loaders reported meanwhile
+    if !deferred.is_empty() {
+        debug_log!(debug_settings, "Deferring {} loaded tiles to the next frame", deferred.len());
+        osm_data.pending_tiles.lock().splice(0..0, deferred);
+    }
+}
+
+// This system periodically requests failed tiles again, up to a limited number of retries
+pub fn retry_failed_tiles(
+    osm_data: Res<OSMData>,
+    map_layers: Res<MapLayers>,
+    tokio_runtime: Res<TokioRuntime>,
+    debug_settings: Res<DebugSettings>,
+    time: Res<Time>,
+    mut retry_timer: Local<f32>,
+    mut tile_query: Query<(Entity, &TileCoords, &mut TileState)>,
+) {
+    // Attempts after the first one, and the wait between rounds of retries
+    const MAX_TILE_RETRIES: u32 = 3;
+    const RETRY_INTERVAL: f32 = 5.0; // Seconds
+
+    *retry_timer += time.delta_secs();
+    if *retry_timer < RETRY_INTERVAL {
+        return;
+    }
+    *retry_timer = 0.0;
+
+    for (entity, tile_coords, mut state) in tile_query.iter_mut() {
+        let TileState::Failed { retries, .. } = *state else {
+            continue;
         };
+        if retries >= MAX_TILE_RETRIES {
+            continue;
+        }
 
-        // Add to appropriate list of active tiles
-        if is_background {
-            osm_data.background_tiles.push((x, y, z, entity));
-        } else {
-            osm_data.tiles.push((x, y, z, entity));
+        debug_log!(debug_settings, "Retrying tile: {}, {}, zoom {} (retry {})", 
+                  tile_coords.x, tile_coords.y, tile_coords.zoom, retries + 1);
+        *state = TileState::Requested;
+        spawn_tile_load(
+            &tokio_runtime,
+            osm_data.pending_tiles.clone(),
+            entity,
+            OSMTile::new(tile_coords.x, tile_coords.y, tile_coords.zoom),
+            &map_layers,
+            retries + 1,
+            osm_data.texture_formats,
+            debug_settings.debug_mode,
+        );
+    }
+}
+
+// Start evicting a tile: forget it in the registry right away so it can be requested
+// again, and leave the despawn to despawn_evicted_tiles
+pub fn evict_tile(
+    entity: Entity,
+    state: &mut TileState,
+    tile_registry: &mut TileRegistry,
+    evicted_events: &mut EventWriter<TileEvicted>,
+) {
+    *state = TileState::Evicting;
+    if let Some(key) = tile_registry.remove_entity(entity) {
+        evicted_events.send(TileEvicted { entity, key });
+    }
+}
+
+// This system despawns tiles that are being evicted
+pub fn despawn_evicted_tiles(
+    mut commands: Commands,
+    tile_query: Query<(Entity, &TileState)>,
+) {
+    for (entity, state) in tile_query.iter() {
+        if *state == TileState::Evicting {
+            commands.entity(entity).despawn_recursive();
         }
     }
 }
 
 // This system updates which tiles are visible and marks the last time they were seen
 pub fn update_visible_tiles(
-    mut tile_query: Query<(&mut TileCoords, &Transform, Entity)>,
-    camera_query: Query<&Transform, With<Camera3d>>,
+    mut tile_query: Query<(&mut TileCoords, &TileState, Entity)>,
+    camera_query: Query<(&Transform, &Camera, &Projection), With<Camera3d>>,
+    tile_registry: Res<TileRegistry>,
+    selection_settings: Res<TileSelectionSettings>,
+    floating_origin: Res<FloatingOrigin>,
+    globe_morph: Res<GlobeMorph>,
     time: Res<Time>,
-    mut commands: Commands,
 ) {
-    if let Ok(camera_transform) = camera_query.get_single() {
+    if let Ok((camera_transform, camera, projection)) = camera_query.get_single() {
         let current_time = time.elapsed_secs();
         
-        // Get camera position and forward direction
-        let camera_pos = camera_transform.translation;
-        let camera_forward = camera_transform.forward();
-        
-        // Create list of entities to despawn
-        let mut to_despawn = Vec::new();
+        // Build the same view the tile selection uses
+        let (fov, viewport_height) = view_parameters(camera, projection);
+        let frustum = camera_frustum(camera_transform, projection);
+        let globe = GlobeFrame::new(globe_morph.anchor);
+        let view = SelectionView {
+            frustum: &frustum,
+            floating_origin: &floating_origin,
+            camera_pos: camera_transform.translation,
+            fov,
+            viewport_height,
+            globe: &globe,
+            morph_factor: globe_morph.factor as f64,
+        };
         
         // Update all tiles
-        for (mut tile_coords, tile_transform, entity) in tile_query.iter_mut() {
-            let tile_pos = tile_transform.translation;
-            
-            // Calculate the vector from camera to tile center
-            let to_tile = tile_pos - camera_pos;
-            let distance = to_tile.length();
+        for (mut tile_coords, state, entity) in tile_query.iter_mut() {
+            if *state == TileState::Evicting {
+                continue;
+            }
             
             // For tiles to be visible, they should be:
-            // 1. Within a reasonable distance (based on zoom level)
-            // 2. Roughly within the camera's field of view
-            
-            // Calculate max visible distance based on zoom and allow larger view area
-            let zoom_factor = 1.0 + 0.7 * (MAX_ZOOM_LEVEL - tile_coords.zoom) as f32;
-            let max_distance = 75.0 * zoom_factor; // Increased from 50.0 to 75.0 for wider view
-            
-            // Use a wider angle check (more permissive) to avoid gaps at edges
-            let forward_dot = camera_forward.dot(to_tile.normalize());
-            
-            // Is the tile visible? More permissive check
-            // Forward dot > -0.3 means roughly within ~110 degree field of view (instead of 90)
-            let is_visible = distance < max_distance && forward_dot > -0.3;
+            // 1. Inside the camera frustum, where they are drawn (flat or on the globe)
+            // 2. Not so detailed that coarser tiles have replaced them - a quarter of the
+            //    allowed error keeps tiles up to two zoom levels finer than needed
+            // 3. Not hidden under finer tiles that have fully loaded on top of them
+            let bounds = view.tile_bounds(tile_coords.x, tile_coords.y, tile_coords.zoom);
+            let too_detailed = view.screen_space_error(&bounds, tile_coords.zoom)
+                < selection_settings.max_screen_space_error * 0.25;
+            let replaced = tile_registry
+                .key_of(entity)
+                .is_some_and(|key| tile_registry.is_covered_by_children(&key));
+            let is_visible = view.is_visible(&bounds) && !too_detailed && !replaced;
             
+            // Update last used time if visible - unused tiles stay resident until
+            // enforce_tile_residency needs their memory
             if is_visible {
-                // Update last used time if visible
-                tile_coords.last_used = current_time;
-            } else {
-                // Tile is not visible
-                let time_since_used = current_time - tile_coords.last_used;
-                
-                // After 1.5 seconds of being outside view, remove non-background tiles
-                // Slightly increased from 1.0 to 1.5 to prevent rapid flickering at edges
-                if time_since_used > 1.5 && tile_coords.zoom > 6 {
-                    to_despawn.push(entity);
-                }
+                tile_coords.last_used = current_time.max(tile_coords.last_used);
             }
         }
-        
-        // Despawn entities outside view
-        for entity in to_despawn {
-            commands.entity(entity).despawn_recursive();
-        }
     }
 }
 
-// This system periodically cleans up tiles that haven't been visible for a while
-pub fn cleanup_old_tiles(
-    mut commands: Commands,
-    mut osm_data: ResMut<OSMData>,
+// This system keeps the GPU memory used by tiles within the residency budget
+// When over budget, tiles are evicted by priority: long unused, far away and detailed tiles go first.
+// Tiles in use right now (visible, or prefetched for the coming seconds) are never evicted.
+// Tiles still loading occupy nothing yet, but are candidates like any other.
+pub fn enforce_tile_residency(
+    mut tile_residency: ResMut<TileResidency>,
+    mut tile_registry: ResMut<TileRegistry>,
     debug_settings: Res<DebugSettings>,
     time: Res<Time>,
-    tile_query: Query<(Entity, &TileCoords)>,
+    mut evicted_events: EventWriter<TileEvicted>,
+    camera_query: Query<&WorldPosition, With<Camera3d>>,
+    mut tile_query: Query<(Entity, &TileCoords, Option<&TileMemory>, &mut TileState)>,
 ) {
-    // Update total time
-    osm_data.total_time += time.delta_secs();
+    // Weights of the eviction score, per second unused, per camera height of distance
+    // and for going from the lowest to the highest zoom level
+    const IDLE_WEIGHT: f32 = 1.0;
+    const DISTANCE_WEIGHT: f32 = 1.0;
+    const ZOOM_WEIGHT: f32 = 5.0;
+
+    let current_time = time.elapsed_secs();
 
-    // Run cleanup more frequently - every 1 second
-    if osm_data.total_time % 1.0 > 0.05 {
+    // Add up what is resident now
+    tile_residency.used_bytes = tile_query
+        .iter()
+        .filter(|(_, _, _, state)| **state != TileState::Evicting)
+        .map(|(_, _, memory, _)| memory.map_or(0, TileMemory::total))
+        .sum();
+    if tile_residency.used_bytes <= tile_residency.budget_bytes {
         return;
     }
 
-    // How long a tile can be unused before being unloaded (in seconds)
-    // More aggressive cleanup for detailed tiles
-    const FOCUS_TILE_TIMEOUT: f32 = 3.0;      // Much shorter timeout for focus tiles
-    const BACKGROUND_TILE_TIMEOUT: f32 = 30.0; // Background tiles can stay longer
-    
-    let current_time = time.elapsed_secs();
-
-    let mut focus_tiles_to_remove = Vec::new();
-    let mut background_tiles_to_remove = Vec::new();
-    let mut focus_indices_to_remove = Vec::new();
-    let mut background_indices_to_remove = Vec::new();
+    let Ok(camera_world) = camera_query.get_single() else {
+        return;
+    };
+    let camera_world = camera_world.0;
+    // In world units like the distances it divides
+    let height = camera_world.y.max(1.0) as f32;
 
-    // Check all tiles in the system
-    for (entity, tile_coords) in tile_query.iter() {
-        let time_since_used = current_time - tile_coords.last_used;
-        let is_background = tile_coords.zoom <= BACKGROUND_ZOOM_LEVEL;
-        
-        // Apply different timeouts based on tile type
-        let timeout = if is_background { 
-            BACKGROUND_TILE_TIMEOUT 
-        } else { 
-            // Scale timeout by zoom level - higher zoom (more detailed) = shorter timeout
-            let zoom_factor = (MAX_ZOOM_LEVEL - tile_coords.zoom) as f32 / MAX_ZOOM_LEVEL as f32;
-            FOCUS_TILE_TIMEOUT * (1.0 + zoom_factor * 5.0) // 3-15 seconds depending on zoom
-        };
+    // Score the tiles that may go, highest score first
+    let mut candidates: Vec<(Entity, f32)> = tile_query
+        .iter()
+        .filter(|(_, tile_coords, _, state)| **state != TileState::Evicting && tile_coords.last_used < current_time)
+        .map(|(entity, tile_coords, _, _)| {
+            let (corner_x, corner_z) = tile_to_world(tile_coords.x, tile_coords.y, tile_coords.zoom);
+            let half_size = tile_size_world(tile_coords.zoom) * 0.5;
+            let distance = DVec3::new(corner_x + half_size, 0.0, corner_z + half_size).distance(camera_world) as f32;
+
+            let idle = current_time - tile_coords.last_used;
+            let zoom = tile_coords.zoom as f32 / MAX_ZOOM_LEVEL as f32;
+            let score = idle * IDLE_WEIGHT + distance / height * DISTANCE_WEIGHT + zoom * ZOOM_WEIGHT;
+            (entity, score)
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.1.total_cmp(&a.1));
 
-        // Check if the timeout has been exceeded
-        if time_since_used > timeout {
-            if is_background {
-                // Check if it's a background tile
-                if let Some(idx) = osm_data.background_tiles.iter().position(|&(x, y, z, e)|
-                    x == tile_coords.x && y == tile_coords.y && z == tile_coords.zoom && e == entity) {
-                    background_tiles_to_remove.push(entity);
-                    background_indices_to_remove.push(idx);
-                }
-            } else {
-                // Check if it's a focus tile
-                if let Some(idx) = osm_data.tiles.iter().position(|&(x, y, z, e)|
-                    x == tile_coords.x && y == tile_coords.y && z == tile_coords.zoom && e == entity) {
-                    focus_tiles_to_remove.push(entity);
-                    focus_indices_to_remove.push(idx);
-                }
-            }
+    // Evict until the budget fits again
+    let mut evicted = 0;
+    for (entity, _) in candidates {
+        if tile_residency.used_bytes <= tile_residency.budget_bytes {
+            break;
+        }
+        if let Ok((_, _, memory, mut state)) = tile_query.get_mut(entity) {
+            tile_residency.used_bytes = tile_residency.used_bytes.saturating_sub(memory.map_or(0, TileMemory::total));
+            evict_tile(entity, &mut state, &mut tile_registry, &mut evicted_events);
+            evicted += 1;
         }
     }
 
-    // Sort indices in reverse order so we can remove without changing other indices
-    focus_indices_to_remove.sort_by(|a, b| b.cmp(a));
-    background_indices_to_remove.sort_by(|a, b| b.cmp(a));
+    debug_log!(debug_settings, "Evicted {} tiles to stay within the budget: {} of {} MiB used", 
+              evicted, tile_residency.used_bytes / (1024 * 1024), tile_residency.budget_bytes / (1024 * 1024));
+}
 
-    // Remove focus tiles from our tracking list
-    for &idx in &focus_indices_to_remove {
-        if idx < osm_data.tiles.len() {
-            osm_data.tiles.remove(idx);
-        }
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TILE_BYTES: usize = 100;
 
-    // Remove background tiles from our tracking list
-    for &idx in &background_indices_to_remove {
-        if idx < osm_data.background_tiles.len() {
-            osm_data.background_tiles.remove(idx);
+    fn spawn_tile(app: &mut App, x: i32, memory: Option<TileMemory>, state: TileState) -> Entity {
+        let mut tile = app.world_mut().spawn((TileCoords { x, y: 512, zoom: 10, last_used: -1.0 }, state));
+        if let Some(memory) = memory {
+            tile.insert(memory);
         }
+        let entity = tile.id();
+        app.world_mut()
+            .resource_mut::<TileRegistry>()
+            .insert(TileKey::new(TileSource::Focus, x, 512, 10), entity);
+        entity
     }
 
-    // Count the number of tiles to be removed
-    let focus_removed = focus_tiles_to_remove.len();
-    let background_removed = background_tiles_to_remove.len();
-
-    // Now despawn entities after we've updated our tracking data
-    for entity in focus_tiles_to_remove.into_iter().chain(background_tiles_to_remove) {
-        commands.entity(entity).despawn_recursive();
+    #[test]
+    fn residency_evicts_the_farthest_tiles_until_the_budget_fits() {
+        let mut app = App::new();
+        app.add_event::<TileEvicted>()
+            .insert_resource(Time::<()>::default())
+            .insert_resource(TileResidency { budget_bytes: 2 * TILE_BYTES + TILE_BYTES / 2, used_bytes: 0 })
+            .init_resource::<TileRegistry>()
+            .init_resource::<DebugSettings>()
+            .add_systems(Update, enforce_tile_residency);
+
+        // The camera hovers a tile's width above the middle of tile 512
+        let (corner_x, corner_z) = tile_to_world(512, 512, 10);
+        let size = tile_size_world(10);
+        let camera = DVec3::new(corner_x + size * 0.5, size, corner_z + size * 0.5);
+        app.world_mut().spawn((Camera3d::default(), WorldPosition(camera)));
+
+        let memory = TileMemory { texture_bytes: TILE_BYTES - 10, mesh_bytes: 10 };
+        let near = spawn_tile(&mut app, 512, Some(memory), TileState::Ready);
+        let middle = spawn_tile(&mut app, 514, Some(memory), TileState::Ready);
+        let far = spawn_tile(&mut app, 530, Some(memory), TileState::Ready);
+        let loading = spawn_tile(&mut app, 513, None, TileState::Downloading);
+
+        app.update();
+
+        let world = app.world();
+        assert_eq!(world.resource::<TileResidency>().used_bytes, 2 * TILE_BYTES);
+        assert_eq!(world.get::<TileState>(far), Some(&TileState::Evicting));
+        for kept in [near, middle, loading] {
+            assert_ne!(world.get::<TileState>(kept), Some(&TileState::Evicting));
+        }
+        let registry = world.resource::<TileRegistry>();
+        assert!(!registry.contains(&TileKey::new(TileSource::Focus, 530, 512, 10)));
+        assert!(registry.contains(&TileKey::new(TileSource::Focus, 512, 512, 10)));
     }
 
-    // Also clean up the loaded_tiles lists periodically to prevent them from growing too large
-    // Keep entries for currently loaded tiles
-    let active_focus_coords: Vec<(u32, u32, u32)> = osm_data.tiles
-        .iter()
-        .map(|&(x, y, z, _)| (x, y, z))
-        .collect();
-    
-    let active_background_coords: Vec<(u32, u32, u32)> = osm_data.background_tiles
-        .iter()
-        .map(|&(x, y, z, _)| (x, y, z))
-        .collect();
-    
-    // Remove entries from loaded_tiles that are no longer needed
-    osm_data.loaded_tiles.retain(|coords| active_focus_coords.contains(coords));
-    osm_data.loaded_background_tiles.retain(|coords| active_background_coords.contains(coords));
-
-    // Log cleanup results if any tiles were removed
-    if focus_removed > 0 || background_removed > 0 {
-        debug_log!(debug_settings, "Cleaned up {} unused focus tiles and {} background tiles", 
-                  focus_removed, background_removed);
+    #[test]
+    fn residency_leaves_tiles_alone_within_the_budget() {
+        let mut app = App::new();
+        app.add_event::<TileEvicted>()
+            .insert_resource(Time::<()>::default())
+            .insert_resource(TileResidency { budget_bytes: 2 * TILE_BYTES, used_bytes: 0 })
+            .init_resource::<TileRegistry>()
+            .init_resource::<DebugSettings>()
+            .add_systems(Update, enforce_tile_residency);
+        app.world_mut().spawn((Camera3d::default(), WorldPosition(DVec3::new(0.0, 1000.0, 0.0))));
+
+        let memory = TileMemory { texture_bytes: TILE_BYTES, mesh_bytes: 0 };
+        let tiles = [
+            spawn_tile(&mut app, 0, Some(memory), TileState::Ready),
+            spawn_tile(&mut app, 1, Some(memory), TileState::Ready),
+            spawn_tile(&mut app, 2, None, TileState::Requested),
+        ];
+
+        app.update();
+
+        assert_eq!(app.world().resource::<TileResidency>().used_bytes, 2 * TILE_BYTES);
+        for tile in tiles {
+            assert_ne!(app.world().get::<TileState>(tile), Some(&TileState::Evicting));
+        }
     }
 }
-
-// The auto_detect_zoom_level system is no longer needed as our adaptive system handles zoom levels
-// Keep this system empty as a placeholder in case other systems depend on it being registered
-pub fn auto_detect_zoom_level(_: ResMut<OSMData>, _: Query<&Transform, With<Camera3d>>, _: Commands, _: Res<DebugSettings>) {
-    // Intentionally empty - zoom level detection is now handled in process_tiles
-} 
\ No newline at end of file
@@ -0,0 +1,256 @@
+use bevy::prelude::*;
+use bevy::math::DVec3;
+use bevy::window::FileDragAndDrop;
+use std::fs;
+use std::path::Path;
+use crate::components::WorldPosition;
+use crate::resources::{
+    ActionState, CameraPathPlayer, FloatingOrigin, FlyToState, GlobeMorph, GpxTrack, InputAction, MouseLookState,
+    OrbitCamera, TrackPlayback,
+};
+use crate::events::FlyToRequested;
+use crate::utils::geodesy::{LatLon, destination_point, haversine_distance};
+use crate::utils::gpx::track_from_gpx;
+use crate::utils::projection::{GlobeFrame, lat_lon_to_world, altitude_to_world, WORLD_SIZE};
+
+// Playback speed limits, in track seconds per real second
+const MIN_SPEED: f64 = 0.25;
+const MAX_SPEED: f64 = 1024.0;
+// Rate at which the follow camera turns towards the direction of travel, per second
+const HEADING_RATE: f64 = 2.0;
+// Radius of the position marker as a fraction of its distance to the camera
+const MARKER_SIZE: f32 = 0.01;
+const TRACK_COLOR: Color = Color::srgb(1.0, 0.35, 0.1);
+const PLAYED_COLOR: Color = Color::srgb(1.0, 0.8, 0.2);
+// Altitude a loaded track is viewed from, as a multiple of the diagonal of its bounds, and
+// the lowest one for short tracks (metres)
+const TRACK_VIEW_ALTITUDE: f64 = 1.2;
+const MIN_TRACK_VIEW_ALTITUDE: f64 = 500.0;
+
+/// Read the GPX file given at launch
+pub fn load_track(
+    mut track: ResMut<GpxTrack>,
+    mut playback: ResMut<TrackPlayback>,
+    mut fly_to_events: EventWriter<FlyToRequested>,
+) {
+    let Some(path) = track.path.clone() else {
+        return;
+    };
+    match read_track(&path) {
+        Ok(loaded) => replace_track(&mut track, &mut playback, &mut fly_to_events, loaded, &path),
+        Err(e) => warn!("Failed to read GPX track {}: {}", path.display(), e),
+    }
+}
+
+/// Load a GPX file dropped on the window in place of the current track
+pub fn import_dropped_track(
+    mut drop_events: EventReader<FileDragAndDrop>,
+    mut track: ResMut<GpxTrack>,
+    mut playback: ResMut<TrackPlayback>,
+    mut fly_to_events: EventWriter<FlyToRequested>,
+) {
+    for event in drop_events.read() {
+        let FileDragAndDrop::DroppedFile { path_buf, .. } = event else {
+            continue;
+        };
+        if !path_buf.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("gpx")) {
+            continue;
+        }
+
+        match read_track(path_buf) {
+            Ok(loaded) => replace_track(&mut track, &mut playback, &mut fly_to_events, loaded, path_buf),
+            Err(e) => warn!("Failed to import GPX track {}: {}", path_buf.display(), e),
+        }
+    }
+}
+
+/// Play and pause the track (T), slow it down or speed it up ([ and ]) and let the camera
+/// follow it (F)
+pub fn track_actions(
+    action_state: Res<ActionState>,
+    track: Res<GpxTrack>,
+    mut playback: ResMut<TrackPlayback>,
+    mut fly_to: ResMut<FlyToState>,
+    mut orbit_camera: ResMut<OrbitCamera>,
+) {
+    if track.is_empty() {
+        return;
+    }
+
+    if action_state.just_pressed(InputAction::ToggleTrackPlayback) {
+        playback.playing = !playback.playing;
+        // Playing again from the end starts over
+        if playback.playing && playback.time >= track.duration() {
+            playback.time = 0.0;
+        }
+    }
+
+    if action_state.just_pressed(InputAction::SlowerTrack) {
+        playback.speed = (playback.speed / 2.0).max(MIN_SPEED);
+        info!("Track playback speed: {}x", playback.speed);
+    }
+    if action_state.just_pressed(InputAction::FasterTrack) {
+        playback.speed = (playback.speed * 2.0).min(MAX_SPEED);
+        info!("Track playback speed: {}x", playback.speed);
+    }
+
+    if action_state.just_pressed(InputAction::ToggleTrackFollow) {
+        playback.follow = !playback.follow;
+        if playback.follow {
+            // The follow camera owns the camera until it is switched off
+            fly_to.flight = None;
+            playback.camera_heading = None;
+        } else {
+            // Let the orbit camera pick up from the current pose if the globe is in view
+            orbit_camera.active = false;
+        }
+        info!("Follow track: {}", if playback.follow { "ON" } else { "OFF" });
+    }
+}
+
+/// Move the playback position along the track
+pub fn advance_track_playback(time: Res<Time>, track: Res<GpxTrack>, mut playback: ResMut<TrackPlayback>) {
+    if !playback.playing {
+        return;
+    }
+
+    playback.time += time.delta_secs_f64() * playback.speed;
+    if playback.time >= track.duration() {
+        playback.time = track.duration();
+        playback.playing = false;
+        info!("Track playback finished");
+    }
+}
+
+/// Keep the camera behind and above the playback position, looking at it
+#[allow(clippy::too_many_arguments)]
+pub fn follow_track_camera(
+    time: Res<Time>,
+    track: Res<GpxTrack>,
+    floating_origin: Res<FloatingOrigin>,
+    globe_morph: Res<GlobeMorph>,
+    path_player: Res<CameraPathPlayer>,
+    mut playback: ResMut<TrackPlayback>,
+    mut mouse_look_state: ResMut<MouseLookState>,
+    mut camera_query: Query<(&mut Transform, &WorldPosition), With<Camera3d>>,
+) {
+    // A playing camera path wins, so paths can be recorded around a track
+    if !playback.is_following() || path_player.is_playing() {
+        return;
+    }
+    let Some(sample) = track.sample(playback.time) else {
+        return;
+    };
+    let Ok((mut transform, camera_world)) = camera_query.get_single_mut() else {
+        return;
+    };
+
+    // Ease towards the direction of travel, turning the short way round, and hold the
+    // heading while standing still
+    let target_heading = sample.bearing.map(|bearing| bearing + playback.heading_offset);
+    let heading = match (playback.camera_heading, target_heading) {
+        (Some(current), Some(target)) => {
+            let change = (target - current + 180.0).rem_euclid(360.0) - 180.0;
+            current + change * (1.0 - (-HEADING_RATE * time.delta_secs_f64()).exp())
+        }
+        (current, target) => current.or(target).unwrap_or(0.0),
+    }
+    .rem_euclid(360.0);
+    playback.camera_heading = Some(heading);
+
+    // Look at the drawn track, in the world copy the camera is in
+    let mut target = track.to_world(sample.position, sample.elevation, playback.line_height);
+    target.x -= WORLD_SIZE * ((target.x - camera_world.0.x) / WORLD_SIZE).round();
+    let behind = destination_point(sample.position, heading + 180.0, playback.follow_distance);
+    let (behind_x, behind_z) = lat_lon_to_world(behind.lat, behind.lon);
+    let height = altitude_to_world(playback.follow_height, sample.position.lat);
+    let mut camera = DVec3::new(behind_x, target.y + height, behind_z);
+    camera.x -= WORLD_SIZE * ((camera.x - target.x) / WORLD_SIZE).round();
+    // Over the bent map, like the drawn track
+    let globe = GlobeFrame::new(globe_morph.anchor);
+    let target = globe.morph(target, globe_morph.factor as f64);
+    let camera = globe.morph(camera, globe_morph.factor as f64);
+
+    transform.translation = floating_origin.world_to_render(camera);
+    transform.look_at(floating_origin.world_to_render(target), Vec3::Y);
+    // The fly and map cameras continue from these angles when following stops
+    let (yaw, pitch, _) = transform.rotation.to_euler(EulerRot::YXZ);
+    mouse_look_state.yaw = yaw;
+    mouse_look_state.pitch = pitch;
+    mouse_look_state.mouse_motion = Vec2::ZERO;
+}
+
+/// Draw the track as a line over the map, brighter where it has been played, with a
+/// marker at the playback position
+pub fn draw_track(
+    mut gizmos: Gizmos,
+    track: Res<GpxTrack>,
+    playback: Res<TrackPlayback>,
+    floating_origin: Res<FloatingOrigin>,
+    globe_morph: Res<GlobeMorph>,
+    camera_query: Query<&WorldPosition, With<Camera3d>>,
+) {
+    let Some((position, elevation)) = track.position_at(playback.time) else {
+        return;
+    };
+    let Ok(camera_world) = camera_query.get_single() else {
+        return;
+    };
+
+    // Draw the track in the world copy the camera is in, bent like the tiles
+    let current = track.to_world(position, elevation, playback.line_height);
+    let copy_offset = DVec3::X * WORLD_SIZE * ((current.x - camera_world.0.x) / WORLD_SIZE).round();
+    let globe = GlobeFrame::new(globe_morph.anchor);
+    let to_render = |world: DVec3| {
+        floating_origin.world_to_render(globe.morph(world - copy_offset, globe_morph.factor as f64))
+    };
+    let point_render = |index: usize| {
+        let point = &track.points[index];
+        to_render(track.to_world(point.position, point.elevation, playback.line_height))
+    };
+
+    let current_render = to_render(current);
+    let next = track.points.partition_point(|point| point.time <= playback.time);
+    gizmos.linestrip((0..next).map(point_render).chain([current_render]), PLAYED_COLOR);
+    gizmos.linestrip([current_render].into_iter().chain((next..track.points.len()).map(point_render)), TRACK_COLOR);
+
+    let camera_render = floating_origin.world_to_render(camera_world.0);
+    let radius = camera_render.distance(current_render) * MARKER_SIZE;
+    gizmos.sphere(Isometry3d::from_translation(current_render), radius, PLAYED_COLOR);
+}
+
+fn read_track(path: &Path) -> Result<GpxTrack, anyhow::Error> {
+    track_from_gpx(&fs::read_to_string(path)?)
+}
+
+fn replace_track(
+    track: &mut GpxTrack,
+    playback: &mut TrackPlayback,
+    fly_to_events: &mut EventWriter<FlyToRequested>,
+    mut loaded: GpxTrack,
+    path: &Path,
+) {
+    info!(
+        "Loaded GPX track {:?} with {} points ({:.1} km, {:.0} s) from {}",
+        loaded.name, loaded.points.len(), loaded.length() / 1000.0, loaded.duration(), path.display(),
+    );
+
+    // Look down on the track from high enough to see all of it
+    if let Some(bounds) = loaded.bounds() {
+        let center = bounds.center();
+        let diagonal = haversine_distance(LatLon::new(bounds.south, bounds.west), LatLon::new(bounds.north, bounds.east));
+        fly_to_events.send(FlyToRequested {
+            lat: center.lat,
+            lon: center.lon,
+            altitude: (diagonal * TRACK_VIEW_ALTITUDE).max(MIN_TRACK_VIEW_ALTITUDE),
+            heading: None,
+            pitch: Some(-std::f32::consts::FRAC_PI_2),
+        });
+    }
+
+    loaded.path = Some(path.to_path_buf());
+    *track = loaded;
+    playback.playing = false;
+    playback.time = 0.0;
+    playback.camera_heading = None;
+}
@@ -1,6 +1,16 @@
 use bevy::prelude::*;
-use crate::components::{ZoomLevelText, TileCountText, FpsCounterText, TileCoords};
-use crate::systems::tiles;
+use bevy::ui::{FocusPolicy, RelativeCursorPosition};
+use crate::components::{
+    ZoomLevelText, TileCountText, FpsCounterText, LayersText, BookmarkPanel, BookmarkButton, TrackTimelinePanel,
+    TrackTimeline, TrackTimelineFill, TrackTimelineText, LivePositionText, TileState, WorldPosition, InspectPopup,
+    InspectCopyButton, InspectCloseButton,
+};
+use crate::resources::{
+    TileResidency, ZoomPolicy, MapLayers, Bookmarks, GpxTrack, TrackPlayback, PositionFeed, LivePosition, FixStatus,
+    MapInspection, InputBindings, InputAction,
+};
+use crate::events::{FlyToRequested, CopyInspectionRequested};
+use crate::utils::projection::world_altitude;
 
 /// Sets up the UI elements for the game
 pub fn setup_ui(mut commands: Commands) {
@@ -55,36 +65,69 @@ pub fn setup_ui(mut commands: Commands) {
         BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.5)),
         FpsCounterText,
     ));
+    
+    // Spawn map layer list (below FPS counter)
+    commands.spawn((
+        Text::new("Layers:"),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(100.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+        // Set a background color to make text more visible
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.5)),
+        LayersText,
+    ));
 }
 
 /// Updates the zoom level text based on the camera's current position
 pub fn update_zoom_level_text(
     mut text_query: Query<&mut Text, With<ZoomLevelText>>,
-    camera_query: Query<(&Transform, &Camera), With<Camera3d>>,
+    zoom_policy: Res<ZoomPolicy>,
+    camera_query: Query<&WorldPosition, With<Camera3d>>,
 ) {
-    let (transform, _) = if let Ok(cam) = camera_query.get_single() {
+    let world_position = if let Ok(cam) = camera_query.get_single() {
         cam
     } else {
         return;
     };
 
-    // Function is in the same module, we can access it directly
-    let zoom_level = tiles::calculate_base_zoom_level(transform.translation.y);
+    // Show the level the tile systems use, rather than recomputing it from the height
+    let zoom_level = zoom_policy.current;
+    let altitude = world_altitude(world_position.0);
 
     if let Ok(mut text) = text_query.get_single_mut() {
-        text.0 = format!("Zoom: {}", zoom_level);
+        text.0 = format!("Zoom: {} | Alt: {:.0} m", zoom_level, altitude);
     }
 }
 
 /// Updates the tile count text with the number of tiles currently in the scene
 pub fn update_tile_count_text(
     mut text_query: Query<&mut Text, With<TileCountText>>,
-    tile_query: Query<&TileCoords>,
+    tile_query: Query<&TileState>,
+    tile_residency: Res<TileResidency>,
 ) {
-    let tile_count = tile_query.iter().count();
+    // Count tiles by lifecycle state
+    let mut ready = 0;
+    let mut loading = 0;
+    let mut failed = 0;
+    for state in tile_query.iter() {
+        match state {
+            TileState::Ready => ready += 1,
+            TileState::Requested | TileState::Downloading | TileState::Decoding => loading += 1,
+            TileState::Failed { .. } => failed += 1,
+            TileState::Evicting => {}
+        }
+    }
     
     if let Ok(mut text) = text_query.get_single_mut() {
-        text.0 = format!("Tiles: {}", tile_count);
+        text.0 = format!(
+            "Tiles: {} ({} loading, {} failed) | {} / {} MiB",
+            ready, loading, failed,
+            tile_residency.used_bytes / (1024 * 1024),
+            tile_residency.budget_bytes / (1024 * 1024),
+        );
     }
 }
 
@@ -99,3 +142,394 @@ pub fn update_fps_counter(
         text.0 = format!("FPS: {:.1}", fps);
     }
 }
+
+/// Updates the map layer list with the toggle key and state of every layer
+pub fn update_layers_text(
+    mut text_query: Query<&mut Text, With<LayersText>>,
+    map_layers: Res<MapLayers>,
+) {
+    if !map_layers.is_changed() {
+        return;
+    }
+
+    let layers: Vec<String> = map_layers
+        .layers
+        .iter()
+        .enumerate()
+        .map(|(index, layer)| {
+            let state = if layer.enabled {
+                format!("{:.0}%", layer.opacity * 100.0)
+            } else {
+                "off".to_string()
+            };
+            format!("[F{}] {} {}", index + 1, layer.name, state)
+        })
+        .collect();
+
+    if let Ok(mut text) = text_query.get_single_mut() {
+        text.0 = format!("Layers: {}", layers.join(" | "));
+    }
+}
+
+/// Sets up the bookmark list in the top right corner
+pub fn setup_bookmark_panel(mut commands: Commands) {
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            right: Val::Px(10.0),
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(2.0),
+            ..default()
+        },
+        // Set a background color to make text more visible
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.5)),
+        BookmarkPanel,
+    ));
+}
+
+/// Rebuilds the bookmark list whenever the bookmarks change
+pub fn update_bookmark_panel(
+    mut commands: Commands,
+    bookmarks: Res<Bookmarks>,
+    panel_query: Query<Entity, With<BookmarkPanel>>,
+) {
+    if !bookmarks.is_changed() {
+        return;
+    }
+    let Ok(panel) = panel_query.get_single() else {
+        return;
+    };
+
+    commands.entity(panel).despawn_descendants().with_children(|parent| {
+        parent.spawn(Text::new("Bookmarks [B] save [,/.] previous/next [Del] delete [E] export"));
+        for (index, bookmark) in bookmarks.bookmarks.iter().enumerate() {
+            let selected = bookmarks.selected == Some(index);
+            parent
+                .spawn((
+                    Button,
+                    Node {
+                        padding: UiRect::horizontal(Val::Px(4.0)),
+                        ..default()
+                    },
+                    BackgroundColor(if selected {
+                        Color::srgba(0.2, 0.4, 0.8, 0.8)
+                    } else {
+                        Color::NONE
+                    }),
+                    BookmarkButton(index),
+                ))
+                .with_child(Text::new(format!(
+                    "{} ({:.4}, {:.4}, {:.0} m)",
+                    bookmark.name, bookmark.pose.lat, bookmark.pose.lon, bookmark.pose.altitude,
+                )));
+        }
+    });
+}
+
+/// Flies to a bookmark when its entry in the list is clicked
+pub fn bookmark_button_clicks(
+    interaction_query: Query<(&Interaction, &BookmarkButton), Changed<Interaction>>,
+    mut bookmarks: ResMut<Bookmarks>,
+    mut fly_to_events: EventWriter<FlyToRequested>,
+) {
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        if let Some(bookmark) = bookmarks.bookmarks.get(button.0) {
+            fly_to_events.send(FlyToRequested::to_pose(&bookmark.pose));
+            bookmarks.selected = Some(button.0);
+        }
+    }
+}
+
+/// Sets up the GPX track timeline along the bottom, hidden until a track is loaded
+pub fn setup_track_timeline(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(10.0),
+                left: Val::Percent(20.0),
+                width: Val::Percent(60.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(4.0),
+                padding: UiRect::all(Val::Px(4.0)),
+                display: Display::None,
+                ..default()
+            },
+            // Set a background color to make text more visible
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.5)),
+            TrackTimelinePanel,
+        ))
+        .with_children(|parent| {
+            parent.spawn((Text::new(""), TrackTimelineText));
+            // The bar is a button, so it can be clicked and dragged to seek
+            parent
+                .spawn((
+                    Button,
+                    Node {
+                        width: Val::Percent(100.0),
+                        height: Val::Px(12.0),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgba(1.0, 1.0, 1.0, 0.2)),
+                    RelativeCursorPosition::default(),
+                    TrackTimeline,
+                ))
+                .with_child((
+                    Node {
+                        width: Val::Percent(0.0),
+                        height: Val::Percent(100.0),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgb(1.0, 0.8, 0.2)),
+                    TrackTimelineFill,
+                ));
+        });
+}
+
+/// Updates the track timeline with the playback position, speed and name of the track
+pub fn update_track_timeline(
+    track: Res<GpxTrack>,
+    playback: Res<TrackPlayback>,
+    mut panel_query: Query<&mut Node, (With<TrackTimelinePanel>, Without<TrackTimelineFill>)>,
+    mut fill_query: Query<&mut Node, With<TrackTimelineFill>>,
+    mut text_query: Query<&mut Text, With<TrackTimelineText>>,
+) {
+    if !track.is_changed() && !playback.is_changed() {
+        return;
+    }
+    let Ok(mut panel) = panel_query.get_single_mut() else {
+        return;
+    };
+
+    panel.display = if track.is_empty() { Display::None } else { Display::Flex };
+    let duration = track.duration();
+    let progress = if duration > 0.0 { playback.time / duration } else { 0.0 };
+    if let Ok(mut fill) = fill_query.get_single_mut() {
+        fill.width = Val::Percent((progress * 100.0) as f32);
+    }
+
+    if let Ok(mut text) = text_query.get_single_mut() {
+        text.0 = format!(
+            "{} {} / {} {}x{} | [T] play [[/]] speed [F] follow",
+            if track.name.is_empty() { "Track" } else { &track.name },
+            format_duration(playback.time),
+            format_duration(duration),
+            playback.speed,
+            if playback.playing { "" } else { " (paused)" },
+        );
+    }
+}
+
+/// Seeks the track playback to the time under the cursor while the timeline is pressed
+pub fn track_timeline_seek(
+    timeline_query: Query<(&Interaction, &RelativeCursorPosition), With<TrackTimeline>>,
+    track: Res<GpxTrack>,
+    mut playback: ResMut<TrackPlayback>,
+) {
+    for (interaction, cursor) in timeline_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        if let Some(position) = cursor.normalized {
+            playback.time = position.x.clamp(0.0, 1.0) as f64 * track.duration();
+        }
+    }
+}
+
+// Hours, minutes and seconds, leaving out the hours when there are none
+fn format_duration(seconds: f64) -> String {
+    let seconds = seconds.max(0.0) as u64;
+    if seconds >= 3600 {
+        format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+/// Sets up the live position text (below the map layer list), shown when a position
+/// source is configured
+pub fn setup_live_position_text(mut commands: Commands, feed: Res<PositionFeed>) {
+    if feed.address.is_none() {
+        return;
+    }
+    commands.spawn((
+        Text::new("GPS: connecting"),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(130.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+        // Set a background color to make text more visible
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.5)),
+        LivePositionText,
+    ));
+}
+
+/// Updates the live position text with the fix, its accuracy and how long it has been lost
+pub fn update_live_position_text(
+    mut text_query: Query<(&mut Text, &mut TextColor), With<LivePositionText>>,
+    live: Res<LivePosition>,
+    time: Res<Time>,
+) {
+    let Ok((mut text, mut color)) = text_query.get_single_mut() else {
+        return;
+    };
+
+    let position = match live.fix {
+        Some((position, _)) => {
+            let accuracy = live.accuracy.map_or(String::new(), |accuracy| format!(" ±{:.0} m", accuracy));
+            let altitude = live.altitude.map_or(String::new(), |altitude| format!(" | {:.0} m", altitude));
+            let speed = live.speed.map_or(String::new(), |speed| format!(" | {:.0} km/h", speed * 3.6));
+            format!("{:.5}, {:.5}{}{}{}", position.lat, position.lon, accuracy, altitude, speed)
+        }
+        None => String::new(),
+    };
+    let (status, status_color) = match live.status {
+        FixStatus::Disconnected => ("no connection".to_string(), Color::srgb(1.0, 0.4, 0.4)),
+        FixStatus::Waiting => ("waiting for fix".to_string(), Color::srgb(1.0, 0.8, 0.3)),
+        FixStatus::Fix => ("fix".to_string(), Color::WHITE),
+        FixStatus::Lost { since } => (
+            format!("fix lost {:.0} s ago", time.elapsed_secs_f64() - since),
+            Color::srgb(1.0, 0.4, 0.4),
+        ),
+    };
+
+    text.0 = format!(
+        "GPS: {} {} | [L] follow {}",
+        status, position, if live.follow { "on" } else { "off" },
+    );
+    color.0 = status_color;
+}
+
+// Gap between the clicked point and the corner of the inspect popup
+const INSPECT_POPUP_OFFSET: f32 = 12.0;
+
+/// Sets up the popup for inspecting the map, hidden until a point is clicked
+pub fn setup_inspect_popup(mut commands: Commands) {
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(2.0),
+            padding: UiRect::all(Val::Px(4.0)),
+            display: Display::None,
+            ..default()
+        },
+        // Set a background color to make text more visible
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.7)),
+        // Clicks on the popup are not clicks on the map behind it
+        Interaction::default(),
+        FocusPolicy::Block,
+        InspectPopup,
+    ));
+}
+
+/// Rebuilds the inspect popup next to the clicked point whenever another one is inspected
+pub fn update_inspect_popup(
+    mut commands: Commands,
+    inspection: Res<MapInspection>,
+    bindings: Res<InputBindings>,
+    windows: Query<&Window>,
+    mut popup_query: Query<(Entity, &mut Node), With<InspectPopup>>,
+) {
+    if !inspection.is_changed() {
+        return;
+    }
+    let Ok((popup, mut node)) = popup_query.get_single_mut() else {
+        return;
+    };
+    let Some(current) = &inspection.current else {
+        node.display = Display::None;
+        return;
+    };
+
+    // Open towards the middle of the window, so the popup stays on screen
+    node.display = Display::Flex;
+    let size = windows.get_single().map_or(Vec2::ZERO, |window| window.size());
+    let point = current.screen_position;
+    (node.left, node.right) = if point.x < size.x / 2.0 {
+        (Val::Px(point.x + INSPECT_POPUP_OFFSET), Val::Auto)
+    } else {
+        (Val::Auto, Val::Px(size.x - point.x + INSPECT_POPUP_OFFSET))
+    };
+    (node.top, node.bottom) = if point.y < size.y / 2.0 {
+        (Val::Px(point.y + INSPECT_POPUP_OFFSET), Val::Auto)
+    } else {
+        (Val::Auto, Val::Px(size.y - point.y + INSPECT_POPUP_OFFSET))
+    };
+
+    commands.entity(popup).despawn_descendants().with_children(|parent| {
+        parent.spawn(Text::new(format!("{:.6}, {:.6}", current.position.lat, current.position.lon)));
+        parent.spawn(Text::new(current.dms()));
+        parent.spawn(Text::new(format!("Tile {}", current.tile_path())));
+        let bounds = current.bounds;
+        parent.spawn((
+            Text::new(format!(
+                "lat {:.5}..{:.5}, lon {:.5}..{:.5}",
+                bounds.south, bounds.north, bounds.west, bounds.east,
+            )),
+            TextFont::from_font_size(12.0),
+        ));
+        for layer in &current.layers {
+            parent.spawn((
+                Text::new(format!("{}: {}", layer.name, layer.url)),
+                TextFont::from_font_size(12.0),
+            ));
+        }
+        for layer in current.layers.iter().filter(|layer| !layer.attribution.is_empty()) {
+            parent.spawn((
+                Text::new(layer.attribution.clone()),
+                TextFont::from_font_size(12.0),
+                TextColor(Color::srgb(0.8, 0.8, 0.8)),
+            ));
+        }
+
+        parent
+            .spawn(Node {
+                column_gap: Val::Px(8.0),
+                ..default()
+            })
+            .with_children(|buttons| {
+                let copy = match bindings.key_label(InputAction::CopyInspection) {
+                    Some(key) => format!("Copy [{}]", key),
+                    None => "Copy".to_string(),
+                };
+                spawn_popup_button(buttons, &copy, InspectCopyButton);
+                spawn_popup_button(buttons, "Close", InspectCloseButton);
+            });
+    });
+}
+
+fn spawn_popup_button(parent: &mut ChildBuilder, label: &str, marker: impl Component) {
+    parent
+        .spawn((
+            Button,
+            Node {
+                padding: UiRect::horizontal(Val::Px(4.0)),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.2, 0.4, 0.8, 0.8)),
+            marker,
+        ))
+        .with_child(Text::new(label));
+}
+
+/// Copies or closes the inspected point when a popup button is clicked
+pub fn inspect_popup_clicks(
+    copy_query: Query<&Interaction, (Changed<Interaction>, With<InspectCopyButton>)>,
+    close_query: Query<&Interaction, (Changed<Interaction>, With<InspectCloseButton>)>,
+    mut inspection: ResMut<MapInspection>,
+    mut copy_events: EventWriter<CopyInspectionRequested>,
+) {
+    if copy_query.iter().any(|interaction| *interaction == Interaction::Pressed) {
+        copy_events.send(CopyInspectionRequested);
+    }
+    if close_query.iter().any(|interaction| *interaction == Interaction::Pressed) {
+        inspection.current = None;
+    }
+}
@@ -1,7 +1,11 @@
 use bevy::prelude::*;
+use crate::resources::{CameraMode, ActionState, InputAction};
 
-/// Grab the mouse cursor when the app starts
-pub fn grab_mouse(mut windows: Query<&mut Window>) {
+/// Grab the mouse cursor when the app starts, unless the map camera needs it free
+pub fn grab_mouse(camera_mode: Res<CameraMode>, mut windows: Query<&mut Window>) {
+    if !camera_mode.uses_mouse_look() {
+        return;
+    }
     if let Ok(mut window) = windows.get_single_mut() {
         window.cursor_options.visible = false;
         window.cursor_options.grab_mode = bevy::window::CursorGrabMode::Locked;
@@ -9,11 +13,13 @@ pub fn grab_mouse(mut windows: Query<&mut Window>) {
 }
 
 /// Toggle cursor grab with Escape key
+/// Only for the fly and walk cameras - the map camera always leaves the cursor free
 pub fn toggle_cursor_grab(
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+    action_state: Res<ActionState>,
+    camera_mode: Res<CameraMode>,
     mut windows: Query<&mut Window>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::Escape) {
+    if action_state.just_pressed(InputAction::ToggleCursorGrab) && camera_mode.uses_mouse_look() {
         if let Ok(mut window) = windows.get_single_mut() {
             match window.cursor_options.grab_mode {
                 bevy::window::CursorGrabMode::None => {
@@ -0,0 +1,588 @@
+// Launch options from the config file and the command line
+//
+// The config file (vibers.toml in the working directory unless --config says otherwise):
+//
+//     [start]
+//     lat = 53.2194
+//     lon = 6.5665
+//     altitude = 1500.0   # metres
+//     heading = 0.0       # degrees clockwise from north
+//     pitch = -85.0       # degrees, negative looks down
+//
+//     [session]
+//     enabled = true
+//     path = "session.toml"
+//
+//     [bookmarks]
+//     path = "bookmarks.geojson"
+//     export_path = "bookmarks_export.geojson"   # written with the export key (E)
+//
+//     [camera_path]
+//     path = "camera_path.json"
+//     keyframe_spacing = 3.0   # seconds between authored keyframes
+//
+//     [track]                  # GPX track playback
+//     speed = 1.0              # track seconds per real second
+//     follow_distance = 300.0  # metres behind the position
+//     follow_height = 150.0    # metres above the position
+//     heading_offset = 0.0     # degrees clockwise from the direction of travel
+//     line_height = 5.0        # metres above the map
+//
+//     [position_feed]          # live position source, off without an address
+//     address = "127.0.0.1:2947"
+//     format = "gpsd"          # or "nmea"
+//     smoothing = 1.0          # seconds the camera takes to catch up
+//     fix_timeout = 3.0        # seconds without a position before the fix counts as lost
+//     camera_height = 1.7      # metres above the map
+//     follow_course = true     # turn the camera in the direction of travel
+//
+//     [input.keys]          # replaces the default keys of the listed actions
+//     move_forward = ["KeyW", "ArrowUp"]
+//
+//     [input.gamepad]       # same for gamepad buttons
+//     move_up = ["RightTrigger2", "South"]
+//
+//     [controls]            # see ControlSettings
+//     look_sensitivity = 0.002
+//     stick_exponent = 2.0
+//     eye_height = 1.7      # metres above the ground in walk mode
+//
+// Command line options override the file: --lat, --lon, --altitude, --heading, --pitch,
+// --fly-to <lat,lon[,altitude]> (fly there from the start view), --config <file>,
+// --session <file>, --no-session, --bookmarks <file>, --export-bookmarks <file>,
+// --camera-path <file>, --play-camera-path, --exit-after-playback, --gpx <file>,
+// --nmea <host:port> and --gpsd <host:port>.
+
+use bevy::prelude::*;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use std::fs;
+use std::path::PathBuf;
+use bevy::utils::HashMap;
+use crate::utils::position_source::PositionFormat;
+use crate::events::FlyToRequested;
+use crate::resources::{
+    CameraPose, StartView, Session, SessionSettings, InputAction, InputBindings, ControlSettings, Bookmarks,
+    CameraPath, CameraPathPlayer, GpxTrack, TrackPlayback, PositionFeed,
+    parse_key_code, parse_gamepad_button,
+};
+
+const DEFAULT_CONFIG_PATH: &str = "vibers.toml";
+
+// The sections of the config file, each read on its own
+#[derive(Default)]
+struct ConfigFile {
+    start: CameraPose,
+    session: SessionConfig,
+    bookmarks: BookmarksConfig,
+    camera_path: CameraPathConfig,
+    track: TrackConfig,
+    position_feed: PositionFeedConfig,
+    input: InputConfig,
+    controls: ControlSettings,
+}
+
+// Names of the bound keys and buttons by action name, checked when the bindings are made
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct InputConfig {
+    keys: HashMap<String, Vec<String>>,
+    gamepad: HashMap<String, Vec<String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+struct BookmarksConfig {
+    path: PathBuf,
+    export_path: PathBuf,
+}
+
+impl Default for BookmarksConfig {
+    fn default() -> Self {
+        Self {
+            path: Bookmarks::default().path,
+            export_path: Bookmarks::default().export_path,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+struct CameraPathConfig {
+    path: PathBuf,
+    keyframe_spacing: f64,
+}
+
+impl Default for CameraPathConfig {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from("camera_path.json"),
+            keyframe_spacing: CameraPathPlayer::default().keyframe_spacing,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+struct TrackConfig {
+    speed: f64,
+    follow_distance: f64,
+    follow_height: f64,
+    heading_offset: f64,
+    line_height: f64,
+}
+
+impl Default for TrackConfig {
+    fn default() -> Self {
+        let defaults = TrackPlayback::default();
+        Self {
+            speed: defaults.speed,
+            follow_distance: defaults.follow_distance,
+            follow_height: defaults.follow_height,
+            heading_offset: defaults.heading_offset,
+            line_height: defaults.line_height,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+struct PositionFeedConfig {
+    address: Option<String>,
+    format: PositionFormat,
+    smoothing: f64,
+    fix_timeout: f64,
+    camera_height: f64,
+    follow_course: bool,
+}
+
+impl Default for PositionFeedConfig {
+    fn default() -> Self {
+        let defaults = PositionFeed::default();
+        Self {
+            address: defaults.address,
+            format: defaults.format,
+            smoothing: defaults.smoothing,
+            fix_timeout: defaults.fix_timeout,
+            camera_height: defaults.camera_height,
+            follow_course: defaults.follow_course,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+struct SessionConfig {
+    enabled: bool,
+    path: PathBuf,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        let defaults = SessionSettings::default();
+        Self {
+            enabled: defaults.enabled,
+            path: defaults.path,
+        }
+    }
+}
+
+/// Everything the app is configured with at launch
+pub struct LaunchOptions {
+    pub start_view: StartView,
+    pub session_settings: SessionSettings,
+    pub bookmarks: Bookmarks,
+    pub camera_path: CameraPath,
+    pub camera_path_player: CameraPathPlayer,
+    pub track: GpxTrack,
+    pub track_playback: TrackPlayback,
+    pub position_feed: PositionFeed,
+    pub input_bindings: InputBindings,
+    pub control_settings: ControlSettings,
+}
+
+/// Work out the launch options from the config file, the command line and the last session
+///
+/// A start location given on the command line wins over the restored session, which in turn
+/// wins over the start location in the config file.
+pub fn load_launch_options() -> LaunchOptions {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let config_path = option_value(&args, "--config").map(PathBuf::from);
+    let config = read_config(config_path.clone().unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG_PATH)), config_path.is_some());
+
+    let mut home = checked_pose(config.start).unwrap_or_else(|| {
+        warn!("Ignoring the start location in the config file: out of range");
+        CameraPose::default()
+    });
+    let mut explicit_start = false;
+    let mut fly_to = None;
+    let mut session_settings = SessionSettings {
+        path: config.session.path,
+        enabled: config.session.enabled,
+        restored: None,
+    };
+    let mut bookmarks = Bookmarks {
+        path: config.bookmarks.path,
+        export_path: config.bookmarks.export_path,
+        ..default()
+    };
+    let mut camera_path = CameraPath {
+        path: config.camera_path.path,
+        ..default()
+    };
+    let mut camera_path_player = CameraPathPlayer {
+        keyframe_spacing: config.camera_path.keyframe_spacing,
+        ..default()
+    };
+    let mut track = GpxTrack::default();
+    let track_playback = TrackPlayback {
+        speed: config.track.speed,
+        follow_distance: config.track.follow_distance,
+        follow_height: config.track.follow_height,
+        heading_offset: config.track.heading_offset,
+        line_height: config.track.line_height,
+        ..default()
+    };
+    let mut position_feed = PositionFeed {
+        address: config.position_feed.address,
+        format: config.position_feed.format,
+        smoothing: config.position_feed.smoothing,
+        fix_timeout: config.position_feed.fix_timeout,
+        camera_height: config.position_feed.camera_height,
+        follow_course: config.position_feed.follow_course,
+        ..default()
+    };
+
+    let mut args_iter = args.iter();
+    while let Some(arg) = args_iter.next() {
+        match arg.as_str() {
+            "--lat" | "--lon" | "--altitude" | "--heading" | "--pitch" => {
+                let Some(value) = args_iter.next()
+                    .and_then(|value| value.parse::<f64>().ok())
+                    .filter(|value| value.is_finite())
+                else {
+                    warn!("Ignoring {}: expected a number", arg);
+                    continue;
+                };
+                let in_range = match arg.as_str() {
+                    "--lat" => value.abs() <= 90.0,
+                    "--lon" => value.abs() <= 180.0,
+                    "--altitude" => value > 0.0,
+                    _ => true,
+                };
+                if !in_range {
+                    warn!("Ignoring {} {}: out of range", arg, value);
+                    continue;
+                }
+                match arg.as_str() {
+                    "--lat" => home.lat = value,
+                    "--lon" => home.lon = value,
+                    "--altitude" => home.altitude = value,
+                    "--heading" => home.heading = value as f32,
+                    _ => home.pitch = clamp_pitch(value as f32),
+                }
+                explicit_start = true;
+            }
+            "--fly-to" => {
+                match args_iter.next().and_then(|value| parse_fly_to(value)) {
+                    Some(target) => fly_to = Some(target),
+                    None => warn!("Ignoring --fly-to: expected lat,lon or lat,lon,altitude"),
+                }
+            }
+            "--session" => {
+                if let Some(path) = args_iter.next() {
+                    session_settings.path = PathBuf::from(path);
+                }
+            }
+            "--no-session" => session_settings.enabled = false,
+            "--bookmarks" => {
+                if let Some(path) = args_iter.next() {
+                    bookmarks.path = PathBuf::from(path);
+                }
+            }
+            "--export-bookmarks" => {
+                if let Some(path) = args_iter.next() {
+                    bookmarks.export_path = PathBuf::from(path);
+                }
+            }
+            "--camera-path" => {
+                if let Some(path) = args_iter.next() {
+                    camera_path.path = PathBuf::from(path);
+                }
+            }
+            "--play-camera-path" => camera_path_player.play_at_launch = true,
+            "--exit-after-playback" => camera_path_player.exit_after_playback = true,
+            "--gpx" => {
+                if let Some(path) = args_iter.next() {
+                    track.path = Some(PathBuf::from(path));
+                }
+            }
+            "--nmea" | "--gpsd" => {
+                if let Some(address) = args_iter.next() {
+                    position_feed.address = Some(address.clone());
+                    position_feed.format = if arg == "--nmea" { PositionFormat::Nmea } else { PositionFormat::Gpsd };
+                }
+            }
+            // Already read above
+            "--config" => {
+                args_iter.next();
+            }
+            _ => warn!("Ignoring unknown argument {}", arg),
+        }
+    }
+
+    if session_settings.enabled && session_settings.path.exists() {
+        match Session::load(&session_settings.path) {
+            Ok(session) => session_settings.restored = Some(session),
+            Err(e) => warn!("Failed to read session {}: {}", session_settings.path.display(), e),
+        }
+    }
+
+    let restored_camera = session_settings.restored.as_ref().and_then(|session| {
+        let pose = checked_pose(session.camera);
+        if pose.is_none() {
+            warn!("Ignoring the camera of session {}: out of range", session_settings.path.display());
+        }
+        pose
+    });
+    let initial = match restored_camera {
+        Some(camera) if !explicit_start => camera,
+        _ => home,
+    };
+
+    // Without an altitude the flight keeps the one it starts at
+    let fly_to = fly_to.map(|(lat, lon, altitude)| FlyToRequested {
+        lat,
+        lon,
+        altitude: altitude.unwrap_or(initial.altitude),
+        heading: None,
+        pitch: None,
+    });
+
+    LaunchOptions {
+        start_view: StartView { home, initial, fly_to },
+        session_settings,
+        bookmarks,
+        camera_path,
+        camera_path_player,
+        track,
+        track_playback,
+        position_feed,
+        input_bindings: input_bindings(&config.input),
+        control_settings: config.controls,
+    }
+}
+
+// Default bindings with the actions listed in the config file bound as given there
+fn input_bindings(config: &InputConfig) -> InputBindings {
+    let mut bindings = InputBindings::default();
+    for (action, names) in &config.keys {
+        if let Some(action) = parse_action(action) {
+            bindings.keys.insert(action, parse_names(names, parse_key_code, "key"));
+        }
+    }
+    for (action, names) in &config.gamepad {
+        if let Some(action) = parse_action(action) {
+            bindings.gamepad_buttons.insert(action, parse_names(names, parse_gamepad_button, "gamepad button"));
+        }
+    }
+    bindings
+}
+
+// An action by its name in the config file, such as "move_forward"
+fn parse_action(name: &str) -> Option<InputAction> {
+    let action = toml::Value::String(name.to_string()).try_into().ok();
+    if action.is_none() {
+        warn!("Ignoring bindings for unknown action {} in config", name);
+    }
+    action
+}
+
+fn parse_names<T>(names: &[String], parse: fn(&str) -> Option<T>, kind: &str) -> Vec<T> {
+    names.iter()
+        .filter_map(|name| {
+            let parsed = parse(name);
+            if parsed.is_none() {
+                warn!("Ignoring unknown {} {} in config", kind, name);
+            }
+            parsed
+        })
+        .collect()
+}
+
+// The config file is optional, unless one was asked for on the command line
+fn read_config(path: PathBuf, required: bool) -> ConfigFile {
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) => {
+            if required {
+                warn!("Failed to read config {}: {}", path.display(), e);
+            }
+            return ConfigFile::default();
+        }
+    };
+    match text.parse::<toml::Table>() {
+        Ok(table) => parse_config(table, &path.display().to_string()),
+        Err(e) => {
+            warn!("Failed to parse config {}: {}", path.display(), e);
+            ConfigFile::default()
+        }
+    }
+}
+
+// Sections are read one by one, so a mistake in one of them leaves the others as configured
+fn parse_config(table: toml::Table, source: &str) -> ConfigFile {
+    let mut config = ConfigFile::default();
+    for (name, value) in table {
+        match name.as_str() {
+            "start" => config.start = parse_section(&name, value, source),
+            "session" => config.session = parse_section(&name, value, source),
+            "bookmarks" => config.bookmarks = parse_section(&name, value, source),
+            "camera_path" => config.camera_path = parse_section(&name, value, source),
+            "track" => config.track = parse_section(&name, value, source),
+            "position_feed" => config.position_feed = parse_section(&name, value, source),
+            "input" => config.input = parse_section(&name, value, source),
+            "controls" => config.controls = parse_section(&name, value, source),
+            _ => warn!("Ignoring unknown section [{}] in config {}", name, source),
+        }
+    }
+    config
+}
+
+fn parse_section<T: DeserializeOwned + Default>(name: &str, value: toml::Value, source: &str) -> T {
+    value.try_into().unwrap_or_else(|e| {
+        warn!("Using the defaults for [{}], failed to parse it in config {}: {}", name, source, e);
+        T::default()
+    })
+}
+
+// A fly-to target as "lat,lon" or "lat,lon,altitude" in degrees and metres
+fn parse_fly_to(value: &str) -> Option<(f64, f64, Option<f64>)> {
+    let numbers = value
+        .split(',')
+        .map(|part| part.trim().parse::<f64>().ok().filter(|number| number.is_finite()))
+        .collect::<Option<Vec<f64>>>()?;
+    let target = match numbers[..] {
+        [lat, lon] => (lat, lon, None),
+        [lat, lon, altitude] if altitude > 0.0 => (lat, lon, Some(altitude)),
+        _ => return None,
+    };
+    (target.0.abs() <= 90.0 && target.1.abs() <= 180.0).then_some(target)
+}
+
+// Steepest the cameras look up or down, in radians
+const MAX_PITCH: f32 = 1.5;
+
+// Pitch in degrees within what mouse look allows
+fn clamp_pitch(pitch: f32) -> f32 {
+    pitch.clamp(-MAX_PITCH.to_degrees(), MAX_PITCH.to_degrees())
+}
+
+// A pose that can be put on the map, with its pitch clamped, or `None` if it can't
+fn checked_pose(pose: CameraPose) -> Option<CameraPose> {
+    let valid = pose.lat.is_finite() && pose.lat.abs() <= 90.0
+        && pose.lon.is_finite() && pose.lon.abs() <= 180.0
+        && pose.altitude.is_finite() && pose.altitude > 0.0
+        && pose.heading.is_finite()
+        && pose.pitch.is_finite();
+    valid.then(|| CameraPose { pitch: clamp_pitch(pose.pitch), ..pose })
+}
+
+fn option_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == name)
+        .and_then(|index| args.get(index + 1))
+        .map(String::as_str)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> ConfigFile {
+        parse_config(text.parse().unwrap(), "test")
+    }
+
+    #[test]
+    fn a_broken_section_leaves_the_others_as_configured() {
+        let config = parse(
+            r#"
+            [start]
+            lat = 48.85
+            lon = 2.35
+
+            [track]
+            speed = "fast"
+
+            [position_feed]
+            address = "127.0.0.1:2947"
+            fix_timeout = "soon"
+
+            [no_such_section]
+            answer = 42
+            "#,
+        );
+
+        assert_eq!(config.start.lat, 48.85);
+        assert_eq!(config.start.lon, 2.35);
+        assert_eq!(config.track.speed, TrackPlayback::default().speed);
+        assert_eq!(config.position_feed.address, None);
+    }
+
+    #[test]
+    fn unknown_actions_are_skipped() {
+        let config = parse(
+            r#"
+            [input.keys]
+            move_forward = ["ArrowUp"]
+            teleport = ["KeyT"]
+
+            [input.gamepad]
+            fly_home = ["South"]
+            "#,
+        );
+        let bindings = input_bindings(&config.input);
+
+        assert_eq!(bindings.keys[&InputAction::MoveForward], vec![KeyCode::ArrowUp]);
+        assert_eq!(bindings.gamepad_buttons[&InputAction::FlyHome], vec![GamepadButton::South]);
+        // Actions the file doesn't mention keep their defaults
+        assert_eq!(bindings.keys[&InputAction::FlyHome], vec![KeyCode::KeyH]);
+    }
+
+    #[test]
+    fn fly_to_targets_parse_with_and_without_altitude() {
+        assert_eq!(parse_fly_to("53.2194,6.5665"), Some((53.2194, 6.5665, None)));
+        assert_eq!(parse_fly_to("-33.86, 151.21, 2500"), Some((-33.86, 151.21, Some(2500.0))));
+    }
+
+    #[test]
+    fn bad_fly_to_targets_are_rejected() {
+        for value in ["53.2", "53.2,6.5,100,1", "north,6.5", "91,0", "0,181", "53.2,6.5,-10", "NaN,0", ""] {
+            assert_eq!(parse_fly_to(value), None, "{}", value);
+        }
+    }
+
+    #[test]
+    fn poses_out_of_range_are_rejected() {
+        let pose = CameraPose::default();
+        assert_eq!(checked_pose(pose), Some(pose));
+        for bad in [
+            CameraPose { lat: 91.0, ..pose },
+            CameraPose { lon: -180.5, ..pose },
+            CameraPose { altitude: 0.0, ..pose },
+            CameraPose { altitude: f64::INFINITY, ..pose },
+            CameraPose { lat: f64::NAN, ..pose },
+            CameraPose { heading: f32::NAN, ..pose },
+        ] {
+            assert_eq!(checked_pose(bad), None, "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn pose_pitch_is_clamped_to_mouse_look() {
+        let pose = CameraPose { pitch: -120.0, ..CameraPose::default() };
+        assert_eq!(checked_pose(pose).map(|pose| pose.pitch), Some(-MAX_PITCH.to_degrees()));
+        assert_eq!(clamp_pitch(30.0), 30.0);
+    }
+}
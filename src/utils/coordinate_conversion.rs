@@ -1,45 +1,59 @@
-use crate::resources::constants::{DEFAULT_ZOOM_LEVEL, max_tile_index};
+use crate::resources::constants::max_tile_index;
+use crate::utils::projection::{tile_size_world, HALF_WORLD_SIZE};
 
 /// Convert camera world coordinates to OSM tile coordinates
-pub fn world_to_tile_coords(x: f32, z: f32, zoom: u32) -> (u32, u32) {
+/// X is wrapped into the valid range, so this always names a real tile
+pub fn world_to_tile_coords(x: f64, z: f64, zoom: u32) -> (u32, u32) {
+    let (tile_x, tile_y) = world_to_unwrapped_tile_coords(x, z, zoom);
+    (wrap_tile_x(tile_x, zoom), tile_y)
+}
+
+/// Convert world coordinates to OSM tile coordinates without wrapping X
+///
+/// The world repeats horizontally: columns left of 0 or right of the last tile
+/// belong to the neighbouring copies of the world. Y is clamped to the valid range.
+pub fn world_to_unwrapped_tile_coords(x: f64, z: f64, zoom: u32) -> (i32, u32) {
     // OSM tile coordinate system has (0,0) at northwest corner
     // X increases eastward, Y increases southward
-    // Our world coordinate system has:
+    // Our world coordinate system is Web Mercator in metres:
     // - X increases eastward (same as OSM X)
     // - Z increases southward (maps directly to OSM Y)
+    // - The world square runs from -HALF_WORLD_SIZE to HALF_WORLD_SIZE on both axes,
+    //   and repeats east and west of that along X
 
     // OSM zoom level scaling - at each level, number of tiles doubles in each dimension
     // At zoom level 0, the world is 1 tile
     // At zoom level 1, the world is 2x2 tiles
     // At zoom level 2, the world is 4x4 tiles
-    // And so on - each zoom level multiplies tile count by 2^(zoom difference)
+    // And so on - so a tile at zoom z is WORLD_SIZE / 2^z metres wide
 
-    // Our world coordinates are based on tile indexes at DEFAULT_ZOOM_LEVEL
-    // Scale them to match the requested zoom level
-
-    // For example, if DEFAULT_ZOOM_LEVEL is 13 and zoom is 14:
-    // - Each DEFAULT_ZOOM_LEVEL tile becomes 2x2 tiles at zoom 14
-    // - So we multiply coordinates by 2
-
-    // If DEFAULT_ZOOM_LEVEL is 13 and zoom is 12:
-    // - Each 2x2 tile block at DEFAULT_ZOOM_LEVEL becomes 1 tile at zoom 12
-    // - So we divide coordinates by 2
-
-    let zoom_difference = zoom as i32 - DEFAULT_ZOOM_LEVEL as i32;
-    let scale_factor = 2_f32.powi(zoom_difference);
-
-    // Scale world coordinates to the target zoom level
-    let scaled_x = x * scale_factor;
-    let scaled_z = z * scale_factor;
+    // Shift the origin to the northwest corner and divide by the tile size
+    let tile_size = tile_size_world(zoom);
+    let scaled_x = (x + HALF_WORLD_SIZE) / tile_size;
+    let scaled_z = (z + HALF_WORLD_SIZE) / tile_size;
 
     // Get the tile X,Y coordinates at this zoom level
-    let tile_x = scaled_x.floor() as u32;
-    let tile_y = scaled_z.floor() as u32;
+    // World coordinates are f64 so precision holds even at the highest zoom levels
+    let tile_x = scaled_x.floor() as i32;
+    let tile_y = scaled_z.floor().max(0.0) as u32;
 
-    // Clamp to valid tile range for this zoom level
-    let max_index = max_tile_index(zoom);
-    let tile_x = tile_x.clamp(0, max_index);
-    let tile_y = tile_y.clamp(0, max_index);
+    // Clamp Y to valid tile range for this zoom level - there is nothing beyond the poles
+    let tile_y = tile_y.clamp(0, max_tile_index(zoom));
 
     (tile_x, tile_y)
-} 
\ No newline at end of file
+}
+
+/// Wrap an unwrapped tile column into 0..2^zoom, e.g. for tile requests and cache keys
+pub fn wrap_tile_x(x: i32, zoom: u32) -> u32 {
+    x.rem_euclid(1 << zoom) as u32
+}
+
+/// World coordinates of the northwest corner of an OSM tile - the inverse of `world_to_tile_coords`
+/// X may be unwrapped to address a tile in a neighbouring copy of the world
+pub fn tile_to_world(x: i32, y: u32, zoom: u32) -> (f64, f64) {
+    let tile_size = tile_size_world(zoom);
+    (
+        x as f64 * tile_size - HALF_WORLD_SIZE,
+        y as f64 * tile_size - HALF_WORLD_SIZE,
+    )
+}
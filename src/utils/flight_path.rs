@@ -0,0 +1,132 @@
+// Smooth and efficient zooming and panning after van Wijk and Nuij (2003)
+//
+// The view travels through a space where panning is cheap when zoomed out, so far jumps
+// zoom out first, pan, and zoom back in, while short hops stay (nearly) level.
+
+// Trade-off between zooming and panning; sqrt(2) is the value recommended by the paper
+const DEFAULT_RHO: f64 = std::f64::consts::SQRT_2;
+// Distances below this are treated as a pure zoom
+const MIN_PAN_DISTANCE: f64 = 1e-6;
+
+/// Optimal zoom and pan path between two views, each given by the width of ground in
+/// view (any unit) and separated by a ground distance in the same unit
+pub struct SmoothZoomPath {
+    rho: f64,
+    start_width: f64,
+    end_width: f64,
+    distance: f64,
+    r0: f64,
+    length: f64,
+}
+
+impl SmoothZoomPath {
+    pub fn new(start_width: f64, end_width: f64, distance: f64) -> Self {
+        let rho = DEFAULT_RHO;
+        let start_width = start_width.max(f64::EPSILON);
+        let end_width = end_width.max(f64::EPSILON);
+
+        if distance < MIN_PAN_DISTANCE {
+            return Self {
+                rho,
+                start_width,
+                end_width,
+                distance: 0.0,
+                r0: 0.0,
+                length: (end_width / start_width).ln().abs() / rho,
+            };
+        }
+
+        let rho2 = rho * rho;
+        let b = |width: f64, sign: f64| {
+            (end_width * end_width - start_width * start_width + sign * rho2 * rho2 * distance * distance)
+                / (2.0 * width * rho2 * distance)
+        };
+        let r = |b: f64| (-b + (b * b + 1.0).sqrt()).ln();
+        let r0 = r(b(start_width, 1.0));
+        let r1 = r(b(end_width, -1.0));
+
+        Self {
+            rho,
+            start_width,
+            end_width,
+            distance,
+            r0,
+            length: (r1 - r0) / rho,
+        }
+    }
+
+    /// Length of the path in the paper's path units, a measure for how long the flight should take
+    pub fn length(&self) -> f64 {
+        self.length
+    }
+
+    /// Ground distance travelled from the start and width in view at a fraction (0.0 - 1.0) of the path
+    pub fn at(&self, fraction: f64) -> (f64, f64) {
+        let s = fraction.clamp(0.0, 1.0) * self.length;
+
+        if self.distance == 0.0 {
+            let direction = if self.end_width < self.start_width { -1.0 } else { 1.0 };
+            return (0.0, self.start_width * (direction * self.rho * s).exp());
+        }
+
+        let rho2 = self.rho * self.rho;
+        let angle = self.rho * s + self.r0;
+        let travelled = self.start_width / rho2 * (self.r0.cosh() * angle.tanh() - self.r0.sinh());
+        let width = self.start_width * self.r0.cosh() / angle.cosh();
+        (travelled, width)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() <= expected.abs() * 1e-6 + 1e-6, "{} instead of {}", actual, expected);
+    }
+
+    #[test]
+    fn paths_start_and_end_at_the_given_views() {
+        for (start_width, end_width, distance) in [(1000.0, 1000.0, 5000.0), (100.0, 20_000.0, 1e6), (5e5, 10.0, 300.0)] {
+            let path = SmoothZoomPath::new(start_width, end_width, distance);
+            let (travelled, width) = path.at(0.0);
+            assert_close(travelled, 0.0);
+            assert_close(width, start_width);
+            let (travelled, width) = path.at(1.0);
+            assert_close(travelled, distance);
+            assert_close(width, end_width);
+        }
+    }
+
+    #[test]
+    fn far_jumps_zoom_out_on_the_way() {
+        let path = SmoothZoomPath::new(1000.0, 1000.0, 1e6);
+        let (travelled, width) = path.at(0.5);
+        assert_close(travelled, 5e5);
+        assert!(width > 10_000.0);
+    }
+
+    #[test]
+    fn pure_zooms_stay_in_place() {
+        let path = SmoothZoomPath::new(100.0, 10_000.0, 0.0);
+        assert_close(path.length(), 100f64.ln() / DEFAULT_RHO);
+        assert_eq!(path.at(0.5).0, 0.0);
+        assert_close(path.at(0.5).1, 1000.0);
+        assert_close(path.at(1.0).1, 10_000.0);
+    }
+
+    #[test]
+    fn length_grows_with_distance_and_is_the_same_both_ways() {
+        let near = SmoothZoomPath::new(1000.0, 2000.0, 1e4);
+        let far = SmoothZoomPath::new(1000.0, 2000.0, 1e6);
+        assert!(far.length() > near.length());
+        assert_close(SmoothZoomPath::new(2000.0, 1000.0, 1e6).length(), far.length());
+    }
+
+    #[test]
+    fn fractions_outside_the_path_are_clamped() {
+        let path = SmoothZoomPath::new(1000.0, 3000.0, 1e5);
+        assert_eq!(path.at(-1.0), path.at(0.0));
+        assert_eq!(path.at(2.0), path.at(1.0));
+    }
+}
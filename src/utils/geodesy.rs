@@ -0,0 +1,442 @@
+// Geographic positions, Web Mercator, slippy map tiles, distances and bounds
+//
+// Everything here works on plain latitudes, longitudes and Mercator metres, so the module
+// stands on its own; `projection` and the rest of the world space code build on it.
+
+use std::f64::consts::PI;
+use bevy::math::DVec3;
+use crate::resources::constants::max_tile_index;
+
+/// Spherical Earth radius used by Web Mercator (EPSG:3857), in metres
+pub const EARTH_RADIUS: f64 = 6_378_137.0;
+
+/// Latitude limit of the Web Mercator square, in degrees
+pub const MAX_LATITUDE: f64 = 85.051_128_779_806_59;
+
+// Half the width of the Web Mercator square, in metres
+const HALF_MERCATOR_SIZE: f64 = PI * EARTH_RADIUS;
+
+/// Mean Earth radius used for great-circle calculations, in metres
+pub const MEAN_EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// WGS84 ellipsoid semi-major axis, in metres
+pub const WGS84_A: f64 = 6_378_137.0;
+
+/// WGS84 ellipsoid flattening
+pub const WGS84_F: f64 = 1.0 / 298.257_223_563;
+
+/// WGS84 ellipsoid semi-minor axis, in metres
+pub const WGS84_B: f64 = WGS84_A * (1.0 - WGS84_F);
+
+/// A geographic position in WGS84 degrees
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LatLon {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+impl LatLon {
+    pub fn new(lat: f64, lon: f64) -> Self {
+        Self { lat, lon }
+    }
+
+    /// Same position with the longitude normalised to [-180, 180)
+    pub fn normalized(self) -> Self {
+        Self::new(self.lat, normalize_longitude(self.lon))
+    }
+}
+
+/// Wrap a longitude in degrees into [-180, 180)
+pub fn normalize_longitude(lon: f64) -> f64 {
+    (lon + 180.0).rem_euclid(360.0) - 180.0
+}
+
+// ---------------------------------------------------------------------------
+// Web Mercator (EPSG:3857)
+// ---------------------------------------------------------------------------
+
+/// Project a position to Web Mercator easting/northing in metres
+pub fn lat_lon_to_mercator(position: LatLon) -> (f64, f64) {
+    let lat = position.lat.clamp(-MAX_LATITUDE, MAX_LATITUDE).to_radians();
+    let easting = EARTH_RADIUS * position.lon.to_radians();
+    let northing = EARTH_RADIUS * (PI / 4.0 + lat / 2.0).tan().ln();
+    (easting, northing)
+}
+
+/// Unproject Web Mercator easting/northing in metres to a position
+pub fn mercator_to_lat_lon(easting: f64, northing: f64) -> LatLon {
+    let lon = (easting / EARTH_RADIUS).to_degrees();
+    let lat = (2.0 * (northing / EARTH_RADIUS).exp().atan() - PI / 2.0).to_degrees();
+    LatLon::new(lat, lon)
+}
+
+/// Convert Web Mercator easting/northing to world X/Z (Z points south)
+pub fn mercator_to_world(easting: f64, northing: f64) -> (f64, f64) {
+    (easting, -northing)
+}
+
+/// Convert world X/Z to Web Mercator easting/northing
+pub fn world_to_mercator(x: f64, z: f64) -> (f64, f64) {
+    (x, -z)
+}
+
+/// Convert a position to world X/Z
+pub fn geo_to_world(position: LatLon) -> (f64, f64) {
+    let (easting, northing) = lat_lon_to_mercator(position);
+    mercator_to_world(easting, northing)
+}
+
+/// Convert world X/Z to a position
+/// The longitude is normalised, since the world repeats east and west of the antimeridian
+pub fn world_to_geo(x: f64, z: f64) -> LatLon {
+    let (easting, northing) = world_to_mercator(x, z);
+    mercator_to_lat_lon(easting, northing).normalized()
+}
+
+// ---------------------------------------------------------------------------
+// Slippy map tiles
+// ---------------------------------------------------------------------------
+
+// Edge length of a tile at a zoom level, in Mercator metres
+fn tile_size(zoom: u32) -> f64 {
+    2.0 * HALF_MERCATOR_SIZE / 2_f64.powi(zoom as i32)
+}
+
+/// OSM tile containing a position at the given zoom level
+pub fn lat_lon_to_tile(position: LatLon, zoom: u32) -> (u32, u32) {
+    let (easting, northing) = lat_lon_to_mercator(position);
+    let tile_size = tile_size(zoom);
+    // Tile columns count east from the antimeridian and rows south from the top of the square
+    let x = ((easting + HALF_MERCATOR_SIZE) / tile_size).floor() as i64;
+    let y = ((-northing + HALF_MERCATOR_SIZE) / tile_size).floor().max(0.0) as u32;
+    (x.rem_euclid(1 << zoom) as u32, y.min(max_tile_index(zoom)))
+}
+
+/// Position of the northwest corner of an OSM tile
+/// Not normalised, so the eastern edge of the last column reports +180 rather than -180
+pub fn tile_to_lat_lon(x: u32, y: u32, zoom: u32) -> LatLon {
+    let tile_size = tile_size(zoom);
+    mercator_to_lat_lon(x as f64 * tile_size - HALF_MERCATOR_SIZE, HALF_MERCATOR_SIZE - y as f64 * tile_size)
+}
+
+/// Geographic bounds of an OSM tile
+pub fn tile_bounds(x: u32, y: u32, zoom: u32) -> GeoBounds {
+    let north_west = tile_to_lat_lon(x, y, zoom);
+    let south_east = tile_to_lat_lon(x + 1, y + 1, zoom);
+    GeoBounds::new(south_east.lat, north_west.lon, north_west.lat, south_east.lon)
+}
+
+// ---------------------------------------------------------------------------
+// Distances and bearings
+// ---------------------------------------------------------------------------
+
+/// Great-circle distance between two positions in metres (haversine formula)
+pub fn haversine_distance(from: LatLon, to: LatLon) -> f64 {
+    let lat1 = from.lat.to_radians();
+    let lat2 = to.lat.to_radians();
+    let d_lat = lat2 - lat1;
+    let d_lon = (to.lon - from.lon).to_radians();
+
+    let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+    2.0 * MEAN_EARTH_RADIUS * a.sqrt().min(1.0).asin()
+}
+
+/// Ellipsoidal distance between two positions on WGS84 in metres (Vincenty inverse formula)
+///
+/// Returns `None` when the iteration does not converge, which can happen for
+/// nearly antipodal points; fall back to `haversine_distance` in that case.
+pub fn vincenty_distance(from: LatLon, to: LatLon) -> Option<f64> {
+    let l = (to.lon - from.lon).to_radians();
+    let u1 = ((1.0 - WGS84_F) * from.lat.to_radians().tan()).atan();
+    let u2 = ((1.0 - WGS84_F) * to.lat.to_radians().tan()).atan();
+    let (sin_u1, cos_u1) = u1.sin_cos();
+    let (sin_u2, cos_u2) = u2.sin_cos();
+
+    let mut lambda = l;
+    for _ in 0..200 {
+        let (sin_lambda, cos_lambda) = lambda.sin_cos();
+        let sin_sigma = ((cos_u2 * sin_lambda).powi(2)
+            + (cos_u1 * sin_u2 - sin_u1 * cos_u2 * cos_lambda).powi(2))
+        .sqrt();
+        if sin_sigma == 0.0 {
+            return Some(0.0); // Coincident points
+        }
+        let cos_sigma = sin_u1 * sin_u2 + cos_u1 * cos_u2 * cos_lambda;
+        let sigma = sin_sigma.atan2(cos_sigma);
+        let sin_alpha = cos_u1 * cos_u2 * sin_lambda / sin_sigma;
+        let cos_sq_alpha = 1.0 - sin_alpha * sin_alpha;
+        let cos_2sigma_m = if cos_sq_alpha != 0.0 {
+            cos_sigma - 2.0 * sin_u1 * sin_u2 / cos_sq_alpha
+        } else {
+            0.0 // Equatorial line
+        };
+        let c = WGS84_F / 16.0 * cos_sq_alpha * (4.0 + WGS84_F * (4.0 - 3.0 * cos_sq_alpha));
+        let previous_lambda = lambda;
+        lambda = l + (1.0 - c) * WGS84_F * sin_alpha
+            * (sigma + c * sin_sigma * (cos_2sigma_m + c * cos_sigma * (-1.0 + 2.0 * cos_2sigma_m * cos_2sigma_m)));
+
+        if (lambda - previous_lambda).abs() < 1e-12 {
+            let u_sq = cos_sq_alpha * (WGS84_A * WGS84_A - WGS84_B * WGS84_B) / (WGS84_B * WGS84_B);
+            let a = 1.0 + u_sq / 16384.0 * (4096.0 + u_sq * (-768.0 + u_sq * (320.0 - 175.0 * u_sq)));
+            let b = u_sq / 1024.0 * (256.0 + u_sq * (-128.0 + u_sq * (74.0 - 47.0 * u_sq)));
+            let delta_sigma = b * sin_sigma
+                * (cos_2sigma_m + b / 4.0
+                    * (cos_sigma * (-1.0 + 2.0 * cos_2sigma_m * cos_2sigma_m)
+                        - b / 6.0 * cos_2sigma_m * (-3.0 + 4.0 * sin_sigma * sin_sigma)
+                            * (-3.0 + 4.0 * cos_2sigma_m * cos_2sigma_m)));
+            return Some(WGS84_B * a * (sigma - delta_sigma));
+        }
+    }
+
+    None
+}
+
+/// Initial great-circle bearing from one position to another, in degrees clockwise from north [0, 360)
+pub fn initial_bearing(from: LatLon, to: LatLon) -> f64 {
+    let lat1 = from.lat.to_radians();
+    let lat2 = to.lat.to_radians();
+    let d_lon = (to.lon - from.lon).to_radians();
+
+    let y = d_lon.sin() * lat2.cos();
+    let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * d_lon.cos();
+    y.atan2(x).to_degrees().rem_euclid(360.0)
+}
+
+/// Position reached by travelling a distance in metres along a great circle from a start bearing
+pub fn destination_point(start: LatLon, bearing: f64, distance: f64) -> LatLon {
+    let lat1 = start.lat.to_radians();
+    let lon1 = start.lon.to_radians();
+    let bearing = bearing.to_radians();
+    let angular_distance = distance / MEAN_EARTH_RADIUS;
+
+    let lat2 = (lat1.sin() * angular_distance.cos()
+        + lat1.cos() * angular_distance.sin() * bearing.cos())
+    .asin();
+    let lon2 = lon1
+        + (bearing.sin() * angular_distance.sin() * lat1.cos())
+            .atan2(angular_distance.cos() - lat1.sin() * lat2.sin());
+
+    LatLon::new(lat2.to_degrees(), normalize_longitude(lon2.to_degrees()))
+}
+
+// ---------------------------------------------------------------------------
+// Earth-centred, Earth-fixed (ECEF)
+// ---------------------------------------------------------------------------
+
+/// Convert a position and ellipsoidal height in metres to WGS84 ECEF coordinates in metres
+///
+/// ECEF axes: X through (0°, 0°), Y through (0°, 90°E), Z through the north pole.
+pub fn geodetic_to_ecef(position: LatLon, height: f64) -> DVec3 {
+    let e2 = WGS84_F * (2.0 - WGS84_F);
+    let (sin_lat, cos_lat) = position.lat.to_radians().sin_cos();
+    let (sin_lon, cos_lon) = position.lon.to_radians().sin_cos();
+    let prime_vertical = WGS84_A / (1.0 - e2 * sin_lat * sin_lat).sqrt();
+
+    DVec3::new(
+        (prime_vertical + height) * cos_lat * cos_lon,
+        (prime_vertical + height) * cos_lat * sin_lon,
+        (prime_vertical * (1.0 - e2) + height) * sin_lat,
+    )
+}
+
+/// Express an ECEF offset in the local east/north/up frame at a reference position
+pub fn ecef_to_enu(offset: DVec3, reference: LatLon) -> DVec3 {
+    let (sin_lat, cos_lat) = reference.lat.to_radians().sin_cos();
+    let (sin_lon, cos_lon) = reference.lon.to_radians().sin_cos();
+
+    DVec3::new(
+        -sin_lon * offset.x + cos_lon * offset.y,
+        -sin_lat * cos_lon * offset.x - sin_lat * sin_lon * offset.y + cos_lat * offset.z,
+        cos_lat * cos_lon * offset.x + cos_lat * sin_lon * offset.y + sin_lat * offset.z,
+    )
+}
+
+// ---------------------------------------------------------------------------
+// Bounding boxes
+// ---------------------------------------------------------------------------
+
+/// Axis-aligned geographic bounding box in degrees
+///
+/// Boxes do not cross the antimeridian: `west <= east` always holds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GeoBounds {
+    pub south: f64,
+    pub west: f64,
+    pub north: f64,
+    pub east: f64,
+}
+
+impl GeoBounds {
+    pub fn new(south: f64, west: f64, north: f64, east: f64) -> Self {
+        Self {
+            south: south.min(north),
+            west: west.min(east),
+            north: south.max(north),
+            east: west.max(east),
+        }
+    }
+
+    /// Smallest box containing all given positions, or `None` for an empty iterator
+    pub fn from_points(points: impl IntoIterator<Item = LatLon>) -> Option<Self> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        let mut bounds = Self::new(first.lat, first.lon, first.lat, first.lon);
+        for point in points {
+            bounds.extend(point);
+        }
+        Some(bounds)
+    }
+
+    /// Grow the box so it contains the position
+    pub fn extend(&mut self, point: LatLon) {
+        self.south = self.south.min(point.lat);
+        self.north = self.north.max(point.lat);
+        self.west = self.west.min(point.lon);
+        self.east = self.east.max(point.lon);
+    }
+
+    pub fn center(&self) -> LatLon {
+        LatLon::new((self.south + self.north) / 2.0, (self.west + self.east) / 2.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use crate::resources::constants::MAX_ZOOM_LEVEL;
+    use crate::utils::coordinate_conversion::{world_to_tile_coords, world_to_unwrapped_tile_coords, wrap_tile_x, tile_to_world};
+    use crate::utils::projection::{tile_size_world, WORLD_SIZE};
+
+    fn lat() -> impl Strategy<Value = f64> {
+        -MAX_LATITUDE + 1e-6..MAX_LATITUDE - 1e-6
+    }
+
+    fn lon() -> impl Strategy<Value = f64> {
+        -180.0..180.0
+    }
+
+    fn contains(bounds: &GeoBounds, point: LatLon) -> bool {
+        (bounds.south..=bounds.north).contains(&point.lat) && (bounds.west..=bounds.east).contains(&point.lon)
+    }
+
+    proptest! {
+        #[test]
+        fn lat_lon_round_trips_through_world(lat in lat(), lon in lon()) {
+            let (x, z) = geo_to_world(LatLon::new(lat, lon));
+            let back = world_to_geo(x, z);
+            prop_assert!((back.lat - lat).abs() < 1e-9);
+            prop_assert!((back.lon - lon).abs() < 1e-9);
+        }
+
+        #[test]
+        fn lat_lon_round_trips_through_mercator(lat in lat(), lon in lon()) {
+            let (easting, northing) = lat_lon_to_mercator(LatLon::new(lat, lon));
+            let (x, z) = mercator_to_world(easting, northing);
+            let (easting, northing) = world_to_mercator(x, z);
+            let back = mercator_to_lat_lon(easting, northing);
+            prop_assert!((back.lat - lat).abs() < 1e-9);
+            prop_assert!((back.lon - lon).abs() < 1e-9);
+        }
+
+        #[test]
+        fn world_point_lies_inside_its_tile(lat in lat(), lon in lon(), zoom in 0..=MAX_ZOOM_LEVEL) {
+            let (x, z) = geo_to_world(LatLon::new(lat, lon));
+            let (tile_x, tile_y) = world_to_tile_coords(x, z, zoom);
+            let (corner_x, corner_z) = tile_to_world(tile_x as i32, tile_y, zoom);
+            let size = tile_size_world(zoom);
+            // Allow a hair of slack for points exactly on a tile edge
+            let slack = size * 1e-9;
+            prop_assert!(x >= corner_x - slack && x < corner_x + size + slack);
+            prop_assert!(z >= corner_z - slack && z < corner_z + size + slack);
+        }
+
+        #[test]
+        fn tile_lookup_repeats_every_world_width(
+            lat in lat(), lon in lon(), zoom in 0..=MAX_ZOOM_LEVEL, copy in -3..=3i32,
+        ) {
+            let (x, z) = geo_to_world(LatLon::new(lat, lon));
+            let shifted_x = x + copy as f64 * WORLD_SIZE;
+            prop_assert_eq!(world_to_tile_coords(shifted_x, z, zoom), world_to_tile_coords(x, z, zoom));
+            let (unwrapped_x, _) = world_to_unwrapped_tile_coords(shifted_x, z, zoom);
+            let (tile_x, _) = world_to_tile_coords(x, z, zoom);
+            prop_assert_eq!(unwrapped_x as i64, tile_x as i64 + copy as i64 * (1i64 << zoom));
+            prop_assert_eq!(wrap_tile_x(unwrapped_x, zoom), tile_x);
+        }
+
+        #[test]
+        fn tile_corner_round_trips(zoom in 0..=MAX_ZOOM_LEVEL, fx in 0.0..1.0f64, fy in 0.0..1.0f64) {
+            let max_index = max_tile_index(zoom);
+            let tile_x = (fx * max_index as f64) as u32;
+            let tile_y = (fy * max_index as f64) as u32;
+            // The tile centre maps back to the same tile
+            let half = tile_size_world(zoom) / 2.0;
+            let (corner_x, corner_z) = tile_to_world(tile_x as i32, tile_y, zoom);
+            prop_assert_eq!(world_to_tile_coords(corner_x + half, corner_z + half, zoom), (tile_x, tile_y));
+            let bounds = tile_bounds(tile_x, tile_y, zoom);
+            prop_assert_eq!(lat_lon_to_tile(bounds.center(), zoom), (tile_x, tile_y));
+            prop_assert!(contains(&bounds, bounds.center()));
+            // The corner computed from Mercator metres matches the world space one
+            let corner = tile_to_lat_lon(tile_x, tile_y, zoom);
+            let (x, z) = geo_to_world(corner);
+            prop_assert!((x - corner_x).abs() < 1e-6 && (z - corner_z).abs() < 1e-6);
+        }
+
+        #[test]
+        fn destination_inverts_distance_and_bearing(
+            lat1 in -80.0..80.0f64, lon1 in lon(), lat2 in -80.0..80.0f64, lon2 in lon(),
+        ) {
+            let from = LatLon::new(lat1, lon1);
+            let to = LatLon::new(lat2, lon2);
+            let distance = haversine_distance(from, to);
+            prop_assume!(distance > 1.0 && distance < 19_000_000.0);
+            let reached = destination_point(from, initial_bearing(from, to), distance);
+            prop_assert!(haversine_distance(reached, to) < 1.0);
+        }
+
+        #[test]
+        fn vincenty_agrees_with_haversine(
+            lat1 in -80.0..80.0f64, lon1 in lon(), lat2 in -80.0..80.0f64, lon2 in lon(),
+        ) {
+            let from = LatLon::new(lat1, lon1);
+            let to = LatLon::new(lat2, lon2);
+            if let Some(ellipsoidal) = vincenty_distance(from, to) {
+                let spherical = haversine_distance(from, to);
+                // The sphere is within about 0.5% of the ellipsoid
+                prop_assert!((ellipsoidal - spherical).abs() <= spherical * 0.006 + 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn known_distance_between_groningen_and_amsterdam() {
+        let groningen = LatLon::new(53.2194, 6.5665);
+        let amsterdam = LatLon::new(52.3676, 4.9041);
+        let distance = vincenty_distance(groningen, amsterdam).unwrap();
+        assert!((distance - 145_000.0).abs() < 2_000.0, "distance was {distance}");
+        let bearing = initial_bearing(groningen, amsterdam);
+        assert!((225.0..240.0).contains(&bearing), "bearing was {bearing}");
+    }
+
+    #[test]
+    fn ecef_offsets_point_up_at_the_reference() {
+        let groningen = LatLon::new(53.2194, 6.5665);
+        let ground = geodetic_to_ecef(groningen, 0.0);
+        let above = geodetic_to_ecef(groningen, 1_000.0);
+        let enu = ecef_to_enu(above - ground, groningen);
+        assert!((enu - DVec3::new(0.0, 0.0, 1_000.0)).length() < 1e-6, "enu was {enu:?}");
+    }
+
+    #[test]
+    fn bounds_contain_the_points_they_are_made_of() {
+        let groningen = LatLon::new(53.2194, 6.5665);
+        let amsterdam = LatLon::new(52.3676, 4.9041);
+        let bounds = GeoBounds::from_points([groningen, amsterdam]).unwrap();
+        assert!(contains(&bounds, groningen) && contains(&bounds, amsterdam));
+        assert!(contains(&bounds, bounds.center()));
+        assert!(!contains(&bounds, LatLon::new(51.0, 5.5)));
+        assert!(GeoBounds::from_points([]).is_none());
+        assert!(contains(&tile_bounds(4245, 2660, 13), groningen));
+        assert_eq!(lat_lon_to_tile(groningen, 13), (4245, 2660));
+    }
+}
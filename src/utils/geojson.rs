@@ -0,0 +1,148 @@
+// Bookmarks as GeoJSON (RFC 7946): a FeatureCollection of Points
+//
+// Each point holds [longitude, latitude, altitude in metres]; the name, heading and pitch
+// (degrees) are properties. Any GeoJSON point can be imported, so viewpoints can
+// also be prepared in other GIS tools.
+
+use serde::{Deserialize, Serialize};
+use crate::resources::{Bookmark, CameraPose};
+
+#[derive(Serialize, Deserialize)]
+struct FeatureCollection {
+    #[serde(rename = "type")]
+    kind: String,
+    features: Vec<Feature>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Feature {
+    #[serde(rename = "type")]
+    kind: String,
+    geometry: Option<Geometry>,
+    // May be null as well as missing
+    #[serde(default)]
+    properties: Option<Properties>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Geometry {
+    #[serde(rename = "type")]
+    kind: String,
+    // Only points are read, so other geometries (with nested coordinates) are skipped unparsed
+    #[serde(default, deserialize_with = "point_coordinates")]
+    coordinates: Vec<f64>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct Properties {
+    name: Option<String>,
+    heading: Option<f32>,
+    pitch: Option<f32>,
+}
+
+fn point_coordinates<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Vec<f64>, D::Error> {
+    let value = serde_json::Value::deserialize(deserializer)?;
+    Ok(serde_json::from_value(value).unwrap_or_default())
+}
+
+pub fn bookmarks_to_geojson(bookmarks: &[Bookmark]) -> Result<String, anyhow::Error> {
+    let collection = FeatureCollection {
+        kind: "FeatureCollection".to_string(),
+        features: bookmarks
+            .iter()
+            .map(|bookmark| Feature {
+                kind: "Feature".to_string(),
+                geometry: Some(Geometry {
+                    kind: "Point".to_string(),
+                    coordinates: vec![bookmark.pose.lon, bookmark.pose.lat, bookmark.pose.altitude],
+                }),
+                properties: Some(Properties {
+                    name: Some(bookmark.name.clone()),
+                    heading: Some(bookmark.pose.heading),
+                    pitch: Some(bookmark.pose.pitch),
+                }),
+            })
+            .collect(),
+    };
+    Ok(serde_json::to_string_pretty(&collection)?)
+}
+
+/// Read the points of a GeoJSON FeatureCollection as bookmarks
+///
+/// Points without an altitude, heading or pitch get those of the default start view;
+/// unnamed points are named after their position.
+pub fn bookmarks_from_geojson(text: &str) -> Result<Vec<Bookmark>, anyhow::Error> {
+    let collection: FeatureCollection = serde_json::from_str(text)?;
+    if collection.kind != "FeatureCollection" {
+        anyhow::bail!("expected a FeatureCollection, found {}", collection.kind);
+    }
+
+    let defaults = CameraPose::default();
+    let bookmarks = collection
+        .features
+        .into_iter()
+        .filter_map(|feature| {
+            let geometry = feature.geometry.filter(|geometry| geometry.kind == "Point")?;
+            let (lon, lat) = (*geometry.coordinates.first()?, *geometry.coordinates.get(1)?);
+            let properties = feature.properties.unwrap_or_default();
+            let pose = CameraPose {
+                lat,
+                lon,
+                altitude: geometry.coordinates.get(2).copied().unwrap_or(defaults.altitude),
+                heading: properties.heading.unwrap_or(defaults.heading),
+                pitch: properties.pitch.unwrap_or(defaults.pitch),
+            };
+            let name = properties.name.unwrap_or_else(|| format!("{:.5}, {:.5}", lat, lon));
+            Some(Bookmark { name, pose })
+        })
+        .collect();
+    Ok(bookmarks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bookmarks_survive_a_round_trip() {
+        let bookmarks = vec![
+            Bookmark {
+                name: "Martinitoren".to_string(),
+                pose: CameraPose { lat: 53.2193, lon: 6.5682, altitude: 350.0, heading: 45.0, pitch: -30.0 },
+            },
+            Bookmark {
+                name: "Across the antimeridian".to_string(),
+                pose: CameraPose { lat: -17.7134, lon: -179.9, altitude: 12_000.0, heading: 270.5, pitch: -85.0 },
+            },
+        ];
+
+        let text = bookmarks_to_geojson(&bookmarks).unwrap();
+        assert_eq!(bookmarks_from_geojson(&text).unwrap(), bookmarks);
+    }
+
+    #[test]
+    fn points_without_properties_get_defaults() {
+        let text = r#"{
+            "type": "FeatureCollection",
+            "features": [
+                { "type": "Feature", "geometry": { "type": "Point", "coordinates": [6.5, 53.2] }, "properties": null },
+                { "type": "Feature", "geometry": { "type": "Point", "coordinates": [4.9, 52.4, 800.0] } },
+                { "type": "Feature", "geometry": { "type": "LineString", "coordinates": [[0, 0], [1, 1]] }, "properties": {} },
+                { "type": "Feature", "geometry": null, "properties": { "name": "Nowhere" } }
+            ]
+        }"#;
+
+        let bookmarks = bookmarks_from_geojson(text).unwrap();
+        let defaults = CameraPose::default();
+        assert_eq!(bookmarks.len(), 2);
+        assert_eq!(bookmarks[0].name, "53.20000, 6.50000");
+        assert_eq!(bookmarks[0].pose, CameraPose { lat: 53.2, lon: 6.5, ..defaults });
+        assert_eq!(bookmarks[1].pose.altitude, 800.0);
+    }
+
+    #[test]
+    fn only_feature_collections_are_read() {
+        assert!(bookmarks_from_geojson(r#"{ "type": "Feature", "features": [] }"#).is_err());
+    }
+}
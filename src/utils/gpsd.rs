@@ -0,0 +1,102 @@
+// gpsd JSON reports
+//
+// After the WATCH command gpsd streams one JSON object per line. Only TPV (time, position,
+// velocity) reports are read; their mode says whether there is a fix.
+
+use serde::Deserialize;
+use crate::utils::geodesy::LatLon;
+use crate::utils::position_source::{PositionFix, PositionProtocol, PositionReport};
+
+const WATCH: &str = "?WATCH={\"enable\":true,\"json\":true};\n";
+
+#[derive(Deserialize)]
+struct Report {
+    class: String,
+    #[serde(default)]
+    mode: u8,
+    lat: Option<f64>,
+    lon: Option<f64>,
+    // Newer gpsd versions split the altitude into altHAE and altMSL
+    #[serde(rename = "altMSL")]
+    alt_msl: Option<f64>,
+    alt: Option<f64>,
+    speed: Option<f64>,
+    track: Option<f64>,
+    eph: Option<f64>,
+    epx: Option<f64>,
+    epy: Option<f64>,
+}
+
+pub struct GpsdProtocol;
+
+impl PositionProtocol for GpsdProtocol {
+    fn handshake(&self) -> Option<&'static str> {
+        Some(WATCH)
+    }
+
+    fn parse_line(&self, line: &str) -> Option<PositionReport> {
+        let report: Report = serde_json::from_str(line).ok()?;
+        if report.class != "TPV" {
+            return None;
+        }
+        // Mode 0 is unknown and 1 is no fix; 2 and 3 are 2D and 3D fixes
+        if report.mode < 2 {
+            return Some(PositionReport::NoFix);
+        }
+
+        let accuracy = report.eph.or_else(|| Some(report.epx?.hypot(report.epy?)));
+        Some(PositionReport::Fix(PositionFix {
+            position: LatLon::new(report.lat?, report.lon?),
+            altitude: report.alt_msl.or(report.alt),
+            speed: report.speed,
+            course: report.track,
+            accuracy,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tpv_with_a_3d_fix() {
+        let line = r#"{"class":"TPV","device":"/dev/ttyACM0","mode":3,"time":"2024-05-01T10:20:30.000Z","ept":0.005,"lat":51.4779,"lon":-0.0015,"altHAE":95.2,"altMSL":49.8,"alt":49.8,"epx":3.0,"epy":4.0,"epv":7.5,"track":12.3,"speed":1.5,"climb":0.0}"#;
+        let Some(PositionReport::Fix(fix)) = GpsdProtocol.parse_line(line) else {
+            panic!("no fix");
+        };
+        assert_eq!(fix.position, LatLon::new(51.4779, -0.0015));
+        assert_eq!(fix.altitude, Some(49.8));
+        assert_eq!(fix.speed, Some(1.5));
+        assert_eq!(fix.course, Some(12.3));
+        // Without eph the accuracy comes from the error along both axes
+        assert_eq!(fix.accuracy, Some(5.0));
+    }
+
+    #[test]
+    fn older_reports_give_alt_and_eph() {
+        let line = r#"{"class":"TPV","mode":2,"lat":10.0,"lon":20.0,"alt":30.0,"eph":8.0}"#;
+        let Some(PositionReport::Fix(fix)) = GpsdProtocol.parse_line(line) else {
+            panic!("no fix");
+        };
+        assert_eq!(fix.altitude, Some(30.0));
+        assert_eq!(fix.accuracy, Some(8.0));
+        assert_eq!((fix.speed, fix.course), (None, None));
+    }
+
+    #[test]
+    fn tpv_without_a_fix_says_so() {
+        assert_eq!(GpsdProtocol.parse_line(r#"{"class":"TPV","device":"/dev/ttyACM0","mode":1}"#), Some(PositionReport::NoFix));
+        assert_eq!(GpsdProtocol.parse_line(r#"{"class":"TPV"}"#), Some(PositionReport::NoFix));
+    }
+
+    #[test]
+    fn other_reports_and_broken_lines_are_dropped() {
+        assert_eq!(GpsdProtocol.parse_line(r#"{"class":"VERSION","release":"3.25","rev":"3.25","proto_major":3,"proto_minor":15}"#), None);
+        assert_eq!(GpsdProtocol.parse_line(r#"{"class":"SKY","device":"/dev/ttyACM0","hdop":0.9}"#), None);
+        // A fix without a position cannot be used
+        assert_eq!(GpsdProtocol.parse_line(r#"{"class":"TPV","mode":3,"lat":51.0}"#), None);
+        assert_eq!(GpsdProtocol.parse_line(r#"{"class":"TPV","mode":3,"lat":"#), None);
+        assert_eq!(GpsdProtocol.parse_line(""), None);
+    }
+}
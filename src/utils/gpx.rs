@@ -0,0 +1,192 @@
+// GPS tracks from GPX 1.0 / 1.1 files
+//
+// The points of every track segment (trkpt) are read in file order as one track. Route
+// points (rtept) are only read from files without any, as a route next to a track is
+// usually the plan of it and joining the two would draw a line between them. Times are
+// ISO 8601 / RFC 3339 timestamps; tracks without them, like planned routes, get times as
+// if travelled at a constant speed so they can be replayed too.
+
+use anyhow::anyhow;
+use crate::resources::{GpxTrack, TrackPoint};
+use crate::utils::geodesy::{LatLon, haversine_distance};
+
+// Speed in metres per second assumed for points without a time
+const UNTIMED_SPEED: f64 = 10.0;
+
+pub fn track_from_gpx(text: &str) -> Result<GpxTrack, anyhow::Error> {
+    let document = roxmltree::Document::parse(text)?;
+    let root = document.root_element();
+    if root.tag_name().name() != "gpx" {
+        return Err(anyhow!("not a GPX file"));
+    }
+
+    let name = root
+        .descendants()
+        .find(|node| node.has_tag_name("name") && node.parent().is_some_and(|parent| {
+            matches!(parent.tag_name().name(), "trk" | "rte" | "metadata")
+        }))
+        .and_then(|node| node.text())
+        .map(|name| name.trim().to_string())
+        .unwrap_or_default();
+
+    let point_tag = if root.descendants().any(|node| node.has_tag_name("trkpt")) { "trkpt" } else { "rtept" };
+    let mut points = Vec::new();
+    let mut timestamps = Vec::new();
+    for node in root.descendants().filter(|node| node.has_tag_name(point_tag)) {
+        let coordinate = |attribute: &str| node.attribute(attribute).and_then(|value| value.trim().parse::<f64>().ok());
+        let (Some(lat), Some(lon)) = (coordinate("lat"), coordinate("lon")) else {
+            return Err(anyhow!("point without lat and lon at byte {}", node.range().start));
+        };
+        let child_text = |name: &str| node.children().find(|child| child.has_tag_name(name)).and_then(|child| child.text());
+
+        points.push(TrackPoint {
+            position: LatLon::new(lat, lon),
+            elevation: child_text("ele").and_then(|ele| ele.trim().parse().ok()).unwrap_or(0.0),
+            time: 0.0,
+        });
+        timestamps.push(child_text("time").and_then(parse_timestamp));
+    }
+    if points.is_empty() {
+        return Err(anyhow!("no track or route points"));
+    }
+
+    if timestamps.iter().all(Option::is_some) {
+        let start = timestamps[0].unwrap_or_default();
+        let mut previous = 0.0;
+        for (point, timestamp) in points.iter_mut().zip(&timestamps) {
+            // Clocks jumping back would make the track run backwards
+            previous = (timestamp.unwrap_or_default() - start).max(previous);
+            point.time = previous;
+        }
+    } else {
+        for index in 1..points.len() {
+            let distance = haversine_distance(points[index - 1].position, points[index].position);
+            points[index].time = points[index - 1].time + distance / UNTIMED_SPEED;
+        }
+    }
+
+    Ok(GpxTrack::new(name, points))
+}
+
+/// Seconds since the Unix epoch for an RFC 3339 timestamp like "2024-05-01T10:20:30Z"
+/// or "2024-05-01T12:20:30.5+02:00"
+fn parse_timestamp(text: &str) -> Option<f64> {
+    let text = text.trim();
+    let (date, time) = text.split_once(['T', 't', ' '])?;
+
+    let mut date_parts = date.splitn(3, '-');
+    let year: i64 = date_parts.next()?.parse().ok()?;
+    let month: u32 = date_parts.next()?.parse().ok()?;
+    let day: u32 = date_parts.next()?.parse().ok()?;
+
+    // Split off the zone: "Z", "+hh:mm" or "-hh:mm"; a missing zone is taken as UTC
+    let (clock, offset_seconds) = if let Some(clock) = time.strip_suffix(['Z', 'z']) {
+        (clock, 0)
+    } else if let Some(index) = time.rfind(['+', '-']) {
+        let (clock, zone) = time.split_at(index);
+        let sign = if zone.starts_with('-') { -1 } else { 1 };
+        let (hours, minutes) = zone[1..].split_once(':').unwrap_or((&zone[1..], "0"));
+        (clock, sign * (hours.parse::<i64>().ok()? * 3600 + minutes.parse::<i64>().ok()? * 60))
+    } else {
+        (time, 0)
+    };
+
+    let mut clock_parts = clock.splitn(3, ':');
+    let hours: i64 = clock_parts.next()?.parse().ok()?;
+    let minutes: i64 = clock_parts.next()?.parse().ok()?;
+    let seconds: f64 = clock_parts.next().unwrap_or("0").parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let days = days_from_civil(year, month, day);
+    Some((days * 86_400 + hours * 3600 + minutes * 60 - offset_seconds) as f64 + seconds)
+}
+
+// Days since 1970-01-01 in the proleptic Gregorian calendar (Howard Hinnant's algorithm)
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn days_count_from_the_unix_epoch_across_leap_years() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(1969, 12, 31), -1);
+        assert_eq!(days_from_civil(2000, 2, 29), 11_016);
+        assert_eq!(days_from_civil(2000, 3, 1), 11_017);
+        // 1900 is not a leap year, 2000 and 2024 are
+        assert_eq!(days_from_civil(1900, 3, 1) - days_from_civil(1900, 2, 28), 1);
+        assert_eq!(days_from_civil(2024, 3, 1) - days_from_civil(2024, 2, 28), 2);
+        assert_eq!(days_from_civil(2024, 1, 1) - days_from_civil(2023, 1, 1), 365);
+        assert_eq!(days_from_civil(2025, 1, 1) - days_from_civil(2024, 1, 1), 366);
+    }
+
+    #[test]
+    fn timestamps_read_utc_and_offsets() {
+        // 2024-05-01T10:20:30Z, from `date -u -d 2024-05-01T10:20:30Z +%s`
+        let utc = 1_714_558_830.0;
+        assert_eq!(parse_timestamp("2024-05-01T10:20:30Z"), Some(utc));
+        assert_eq!(parse_timestamp(" 2024-05-01t10:20:30z "), Some(utc));
+        assert_eq!(parse_timestamp("2024-05-01T12:20:30+02:00"), Some(utc));
+        assert_eq!(parse_timestamp("2024-05-01T05:50:30-04:30"), Some(utc));
+        assert_eq!(parse_timestamp("2024-05-01T10:20:30"), Some(utc));
+    }
+
+    #[test]
+    fn timestamps_keep_fractional_seconds() {
+        assert_eq!(parse_timestamp("2024-05-01T10:20:30.5Z"), Some(1_714_558_830.5));
+        assert_eq!(parse_timestamp("2024-05-01T12:20:30.25+02:00"), Some(1_714_558_830.25));
+    }
+
+    #[test]
+    fn timestamps_cross_a_leap_day() {
+        let before = parse_timestamp("2024-02-28T23:00:00Z").unwrap();
+        let after = parse_timestamp("2024-03-01T01:00:00+01:00").unwrap();
+        // 25 hours, as the 29th is in between
+        assert_eq!(after - before, 25.0 * 3600.0);
+    }
+
+    #[test]
+    fn bad_timestamps_are_rejected() {
+        assert_eq!(parse_timestamp("2024-13-01T00:00:00Z"), None);
+        assert_eq!(parse_timestamp("2024-05-01"), None);
+        assert_eq!(parse_timestamp("yesterday"), None);
+    }
+
+    #[test]
+    fn tracks_are_preferred_over_routes() {
+        let gpx = r#"<gpx version="1.1">
+            <rte><rtept lat="10" lon="10"/><rtept lat="11" lon="11"/></rte>
+            <trk><name>Ride</name><trkseg>
+                <trkpt lat="1" lon="2"><time>2024-05-01T10:00:00Z</time></trkpt>
+                <trkpt lat="1.001" lon="2"><time>2024-05-01T10:00:30Z</time></trkpt>
+            </trkseg></trk>
+        </gpx>"#;
+        let track = track_from_gpx(gpx).unwrap();
+        assert_eq!(track.name, "Ride");
+        assert_eq!(track.points.len(), 2);
+        assert_eq!(track.points[0].position, LatLon::new(1.0, 2.0));
+        assert_eq!(track.points[1].time, 30.0);
+    }
+
+    #[test]
+    fn routes_without_times_are_travelled_at_a_constant_speed() {
+        let gpx = r#"<gpx version="1.1"><rte>
+            <rtept lat="0" lon="0"/><rtept lat="0" lon="0.01"/>
+        </rte></gpx>"#;
+        let track = track_from_gpx(gpx).unwrap();
+        assert_eq!(track.points.len(), 2);
+        let distance = haversine_distance(track.points[0].position, track.points[1].position);
+        assert!((track.points[1].time - distance / UNTIMED_SPEED).abs() < 1e-9);
+    }
+}
@@ -1,4 +1,16 @@
 pub mod coordinate_conversion;
+pub mod geodesy;
 pub mod logging;
+pub mod projection;
+pub mod tile_selection;
+pub mod picking;
+pub mod flight_path;
+pub mod config;
+pub mod geojson;
+pub mod spline;
+pub mod gpx;
+pub mod position_source;
+pub mod nmea;
+pub mod gpsd;
 
 // These are imported directly where needed 
\ No newline at end of file
@@ -0,0 +1,153 @@
+// NMEA 0183 position sentences
+//
+// GGA gives the position, fix quality, altitude and HDOP; RMC gives the position, speed and
+// course. Sentences from any talker (GP, GN, GL, ...) are accepted, and sentences with a
+// checksum that does not match are dropped. Receivers that mix binary (UBX) messages into
+// the stream can leave bytes in front of a sentence, so a sentence starts at its last '$'.
+
+use crate::utils::geodesy::LatLon;
+use crate::utils::position_source::{PositionFix, PositionProtocol, PositionReport};
+
+// Metres per knot
+const KNOTS: f64 = 0.514_444;
+// Typical user equivalent range error of a GPS receiver in metres, which HDOP scales into
+// a horizontal accuracy
+const RANGE_ERROR: f64 = 5.0;
+
+pub struct NmeaProtocol;
+
+impl PositionProtocol for NmeaProtocol {
+    fn parse_line(&self, line: &str) -> Option<PositionReport> {
+        let fields = checked_fields(line)?;
+        let kind = fields.first()?.get(2..)?;
+        let field = |index: usize| fields.get(index).copied().filter(|field| !field.is_empty());
+        let number = |index: usize| field(index).and_then(|field| field.parse::<f64>().ok());
+
+        match kind {
+            // $GPGGA,time,lat,N,lon,E,quality,satellites,hdop,altitude,M,...
+            "GGA" => {
+                if field(6).is_none_or(|quality| quality == "0") {
+                    return Some(PositionReport::NoFix);
+                }
+                Some(PositionReport::Fix(PositionFix {
+                    position: position(field(2)?, field(3)?, field(4)?, field(5)?)?,
+                    altitude: number(9),
+                    speed: None,
+                    course: None,
+                    accuracy: number(8).map(|hdop| hdop * RANGE_ERROR),
+                }))
+            }
+            // $GPRMC,time,status,lat,N,lon,E,knots,course,date,...
+            "RMC" => {
+                if field(2) != Some("A") {
+                    return Some(PositionReport::NoFix);
+                }
+                Some(PositionReport::Fix(PositionFix {
+                    position: position(field(3)?, field(4)?, field(5)?, field(6)?)?,
+                    altitude: None,
+                    speed: number(7).map(|knots| knots * KNOTS),
+                    course: number(8),
+                    accuracy: None,
+                }))
+            }
+            _ => None,
+        }
+    }
+}
+
+// The comma separated fields of a sentence, with the talker and type first, if the sentence
+// is well formed and its checksum (when there is one) matches
+fn checked_fields(line: &str) -> Option<Vec<&str>> {
+    let body = line.trim();
+    let body = &body[body.rfind(['$', '!'])? + 1..];
+    let body = match body.split_once('*') {
+        Some((body, checksum)) => {
+            let expected = u8::from_str_radix(checksum.get(..2)?, 16).ok()?;
+            if body.bytes().fold(0, |sum, byte| sum ^ byte) != expected {
+                return None;
+            }
+            body
+        }
+        None => body,
+    };
+    Some(body.split(',').collect())
+}
+
+// Latitude as ddmm.mmmm and longitude as dddmm.mmmm, with their hemispheres
+fn position(lat: &str, north_south: &str, lon: &str, east_west: &str) -> Option<LatLon> {
+    let lat = degrees_minutes(lat, 2)? * if north_south == "S" { -1.0 } else { 1.0 };
+    let lon = degrees_minutes(lon, 3)? * if east_west == "W" { -1.0 } else { 1.0 };
+    Some(LatLon::new(lat, lon))
+}
+
+fn degrees_minutes(text: &str, degree_digits: usize) -> Option<f64> {
+    let degrees: f64 = text.get(..degree_digits)?.parse().ok()?;
+    let minutes: f64 = text.get(degree_digits..)?.parse().ok()?;
+    Some(degrees + minutes / 60.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fix(line: &str) -> PositionFix {
+        match NmeaProtocol.parse_line(line) {
+            Some(PositionReport::Fix(fix)) => fix,
+            report => panic!("{line}: {report:?}"),
+        }
+    }
+
+    #[test]
+    fn gga_gives_position_altitude_and_accuracy() {
+        let fix = fix("$GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*47");
+        assert!((fix.position.lat - (48.0 + 7.038 / 60.0)).abs() < 1e-9);
+        assert!((fix.position.lon - (11.0 + 31.0 / 60.0)).abs() < 1e-9);
+        assert_eq!(fix.altitude, Some(545.4));
+        assert_eq!(fix.accuracy, Some(0.9 * RANGE_ERROR));
+        assert_eq!((fix.speed, fix.course), (None, None));
+    }
+
+    #[test]
+    fn rmc_gives_speed_and_course() {
+        let fix = fix("$GPRMC,123519,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W*6A");
+        assert!((fix.speed.unwrap() - 22.4 * KNOTS).abs() < 1e-9);
+        assert_eq!(fix.course, Some(84.4));
+        assert_eq!(fix.altitude, None);
+    }
+
+    #[test]
+    fn southern_and_western_hemispheres_are_negative() {
+        let fix = fix("$GPGGA,123519,3351.000,S,15112.000,W,1,08,0.9,10.0,M,,M,,");
+        assert!((fix.position.lat + 33.85).abs() < 1e-9);
+        assert!((fix.position.lon + 151.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn empty_fields_are_missing_values() {
+        // A GN talker with no altitude
+        let fix = fix("$GNGGA,092725.00,4717.11399,N,00833.91590,E,1,08,1.01,,M,48.0,M,,*69");
+        assert_eq!(fix.altitude, None);
+        assert_eq!(fix.accuracy, Some(1.01 * RANGE_ERROR));
+    }
+
+    #[test]
+    fn sentences_without_a_fix_say_so() {
+        assert_eq!(NmeaProtocol.parse_line("$GPGGA,,,,,,0,00,99.99,,,,,,*48"), Some(PositionReport::NoFix));
+        assert_eq!(NmeaProtocol.parse_line("$GNRMC,,V,,,,,,,,,,N*4D"), Some(PositionReport::NoFix));
+    }
+
+    #[test]
+    fn bad_checksums_and_other_lines_are_dropped() {
+        assert_eq!(NmeaProtocol.parse_line("$GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*48"), None);
+        assert_eq!(NmeaProtocol.parse_line("$GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*ZZ"), None);
+        assert_eq!(NmeaProtocol.parse_line("$GPGSV,3,1,11,03,03,111,00,04,15,270,00,06,01,010,00,13,06,292,00*74"), None);
+        assert_eq!(NmeaProtocol.parse_line(""), None);
+        assert_eq!(NmeaProtocol.parse_line("garbage"), None);
+    }
+
+    #[test]
+    fn sentences_after_binary_bytes_are_read() {
+        let line = "\u{b5}b\u{fffd}\u{fffd}$GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*47";
+        assert_eq!(fix(line).altitude, Some(545.4));
+    }
+}
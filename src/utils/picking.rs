@@ -0,0 +1,132 @@
+use bevy::prelude::*;
+use bevy::ecs::system::SystemParam;
+use bevy::picking::mesh_picking::ray_cast::{MeshRayCast, RayCastSettings, RayCastVisibility};
+use crate::components::TileCoords;
+
+// Rays that point less steeply down than this are treated as missing the ground,
+// so picking near the horizon doesn't return points absurdly far away
+const MIN_GROUND_RAY_SLOPE: f32 = 0.01;
+// Height above a position from which the ground under it is searched, so ground that
+// rises above the camera is still found
+const GROUND_PROBE_HEIGHT: f32 = 10_000.0;
+
+/// Ray through a position in the viewport (logical pixels, origin top left), using the
+/// given camera transform rather than the GlobalTransform that lags a frame behind
+pub fn viewport_ray(camera: &Camera, transform: &Transform, viewport_position: Vec2) -> Option<Ray3d> {
+    camera
+        .viewport_to_world(&GlobalTransform::from(*transform), viewport_position)
+        .ok()
+}
+
+/// Where a ray hits the flat ground plane at render-space y = 0
+pub fn ray_ground_intersection(ray: Ray3d) -> Option<Vec3> {
+    if ray.direction.y > -MIN_GROUND_RAY_SLOPE {
+        return None;
+    }
+    let t = -ray.origin.y / ray.direction.y;
+    (t > 0.0).then(|| ray.get_point(t))
+}
+
+/// Samples the height of the ground from the tile meshes, so anything with elevation in
+/// its tiles is stood on rather than passed through
+#[derive(SystemParam)]
+pub struct GroundSampler<'w, 's> {
+    ray_cast: MeshRayCast<'w, 's>,
+    tiles: Query<'w, 's, (), With<TileCoords>>,
+}
+
+impl GroundSampler<'_, '_> {
+    /// Render-space height of the top of the ground under a render-space position, or of
+    /// the flat ground plane where no tile is loaded yet
+    pub fn height_under(&mut self, position: Vec3) -> f32 {
+        let origin = Vec3::new(position.x, position.y.max(0.0) + GROUND_PROBE_HEIGHT, position.z);
+        let is_tile = |entity| self.tiles.contains(entity);
+        // Tiles below the camera are usually out of view, so any visible tile counts
+        let settings = RayCastSettings::default()
+            .with_visibility(RayCastVisibility::Visible)
+            .with_filter(&is_tile);
+        self.ray_cast
+            .cast_ray(Ray3d::new(origin, Dir3::NEG_Y), &settings)
+            .first()
+            .map_or(0.0, |(_, hit)| hit.point.y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::render::camera::{camera_system, ManualTextureViews};
+    use bevy::window::{PrimaryWindow, WindowCreated, WindowResized, WindowScaleFactorChanged};
+
+    const VIEWPORT: Vec2 = Vec2::new(800.0, 600.0);
+
+    // A camera with its projection and viewport computed for a primary window
+    fn camera() -> Camera {
+        let mut app = App::new();
+        app.add_event::<WindowResized>()
+            .add_event::<WindowCreated>()
+            .add_event::<WindowScaleFactorChanged>()
+            .add_event::<AssetEvent<Image>>()
+            .init_resource::<Assets<Image>>()
+            .init_resource::<ManualTextureViews>()
+            .add_systems(Update, camera_system::<Projection>);
+        let mut window = Window::default();
+        window.resolution.set(VIEWPORT.x, VIEWPORT.y);
+        app.world_mut().spawn((window, PrimaryWindow));
+        let camera = app.world_mut().spawn((Camera::default(), Projection::default())).id();
+        app.update();
+        app.world().get::<Camera>(camera).unwrap().clone()
+    }
+
+    fn ray(origin: Vec3, direction: Vec3) -> Ray3d {
+        Ray3d::new(origin, Dir3::new(direction).unwrap())
+    }
+
+    #[test]
+    fn viewport_ray_uses_the_given_transform() {
+        let camera = camera();
+        let transform = Transform::from_xyz(10.0, 100.0, -20.0).looking_to(Vec3::NEG_Y, Vec3::NEG_Z);
+
+        let ray = viewport_ray(&camera, &transform, VIEWPORT / 2.0).unwrap();
+        assert!(ray.origin.xz().distance(Vec2::new(10.0, -20.0)) < 1e-3);
+        assert!(ray.direction.dot(Vec3::NEG_Y) > 0.9999);
+
+        let ground = ray_ground_intersection(ray).unwrap();
+        assert!(ground.distance(Vec3::new(10.0, 0.0, -20.0)) < 1e-2);
+    }
+
+    #[test]
+    fn viewport_ray_leans_towards_the_cursor() {
+        let camera = camera();
+        let transform = Transform::from_xyz(0.0, 100.0, 0.0).looking_to(Vec3::NEG_Y, Vec3::NEG_Z);
+
+        // Right of the middle of the screen is +x, the top is -z for this camera
+        let right = viewport_ray(&camera, &transform, Vec2::new(VIEWPORT.x, VIEWPORT.y / 2.0)).unwrap();
+        let top = viewport_ray(&camera, &transform, Vec2::new(VIEWPORT.x / 2.0, 0.0)).unwrap();
+        assert!(ray_ground_intersection(right).unwrap().x > 1.0);
+        assert!(ray_ground_intersection(top).unwrap().z < -1.0);
+    }
+
+    #[test]
+    fn downward_rays_hit_the_ground_plane() {
+        let hit = ray_ground_intersection(ray(Vec3::new(0.0, 10.0, 0.0), Vec3::new(1.0, -1.0, 0.0))).unwrap();
+        assert!(hit.distance(Vec3::new(10.0, 0.0, 0.0)) < 1e-4);
+    }
+
+    #[test]
+    fn rays_near_the_horizon_miss_the_ground() {
+        let origin = Vec3::new(0.0, 10.0, 0.0);
+        // Just too shallow, and just steep enough
+        let shallow = Vec3::new(1.0, -MIN_GROUND_RAY_SLOPE * 0.9, 0.0);
+        let steep = Vec3::new(1.0, -MIN_GROUND_RAY_SLOPE * 1.1, 0.0);
+        assert!(ray_ground_intersection(ray(origin, shallow)).is_none());
+        assert!(ray_ground_intersection(ray(origin, steep)).is_some());
+        assert!(ray_ground_intersection(ray(origin, Vec3::X)).is_none());
+        assert!(ray_ground_intersection(ray(origin, Vec3::Y)).is_none());
+    }
+
+    #[test]
+    fn rays_from_below_the_ground_miss_it() {
+        assert!(ray_ground_intersection(ray(Vec3::new(0.0, -5.0, 0.0), Vec3::NEG_Y)).is_none());
+    }
+}
@@ -0,0 +1,62 @@
+// Live device positions from a line based stream
+//
+// A position source is a TCP connection to something that streams positions as lines of
+// text: a GPS receiver or phone app sending NMEA 0183 sentences, or a gpsd daemon sending
+// its JSON reports. Each protocol turns lines into position reports; the connection and
+// everything after it are the same for all of them.
+
+use serde::Deserialize;
+use crate::utils::geodesy::LatLon;
+use crate::utils::gpsd::GpsdProtocol;
+use crate::utils::nmea::NmeaProtocol;
+
+/// A position measured by the device
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PositionFix {
+    pub position: LatLon,
+    /// Altitude above sea level in metres
+    pub altitude: Option<f64>,
+    /// Speed over ground in metres per second
+    pub speed: Option<f64>,
+    /// Course over ground in degrees clockwise from north
+    pub course: Option<f64>,
+    /// Estimated horizontal error in metres
+    pub accuracy: Option<f64>,
+}
+
+/// What a line of the stream says about the position
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PositionReport {
+    Fix(PositionFix),
+    /// The device is reporting, but does not know where it is
+    NoFix,
+}
+
+/// A line based protocol that positions are read from
+pub trait PositionProtocol: Send + Sync {
+    /// Text to send after connecting, for protocols that have to be asked to start streaming
+    fn handshake(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Parse one line, `None` for lines without anything about the position
+    fn parse_line(&self, line: &str) -> Option<PositionReport>;
+}
+
+/// The protocols a position source can speak
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PositionFormat {
+    #[default]
+    Nmea,
+    Gpsd,
+}
+
+impl PositionFormat {
+    pub fn protocol(&self) -> Box<dyn PositionProtocol> {
+        match self {
+            PositionFormat::Nmea => Box::new(NmeaProtocol),
+            PositionFormat::Gpsd => Box::new(GpsdProtocol),
+        }
+    }
+}
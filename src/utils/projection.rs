@@ -0,0 +1,226 @@
+use std::f64::consts::PI;
+use bevy::math::{DMat2, DVec2, DVec3};
+use crate::utils::geodesy::{LatLon, lat_lon_to_mercator, mercator_to_lat_lon, mercator_to_world, world_to_mercator, geodetic_to_ecef, ecef_to_enu};
+pub use crate::utils::geodesy::{EARTH_RADIUS, MAX_LATITUDE};
+
+/// Width (and height) of the square Web Mercator world, in metres at the equator
+pub const WORLD_SIZE: f64 = 2.0 * PI * EARTH_RADIUS;
+
+/// Half the world size; Mercator easting/northing run from -HALF_WORLD_SIZE to HALF_WORLD_SIZE
+pub const HALF_WORLD_SIZE: f64 = WORLD_SIZE / 2.0;
+
+/// Standard OSM tile size in pixels
+pub const TILE_SIZE_PIXELS: f64 = 256.0;
+
+// World space is Web Mercator in metres:
+// - X = easting (increasing eastward, same as OSM tile X)
+// - Z = negated northing (increasing southward, same as OSM tile Y)
+// - Y = up, in the same stretched Mercator units so the scene stays conformal
+//
+// Mercator stretches everything by 1/cos(latitude), so one world unit is only one true
+// metre at the equator. Use `mercator_scale` to convert real distances and altitudes.
+
+/// Convert latitude/longitude in degrees to world X/Z
+pub fn lat_lon_to_world(lat: f64, lon: f64) -> (f64, f64) {
+    let (easting, northing) = lat_lon_to_mercator(LatLon::new(lat, lon));
+    mercator_to_world(easting, northing)
+}
+
+/// Convert world X/Z back to latitude/longitude in degrees
+pub fn world_to_lat_lon(x: f64, z: f64) -> (f64, f64) {
+    let (easting, northing) = world_to_mercator(x, z);
+    let position = mercator_to_lat_lon(easting, northing);
+    (position.lat, position.lon)
+}
+
+/// Number of world units per true metre at the given latitude (degrees)
+pub fn mercator_scale(lat: f64) -> f64 {
+    1.0 / lat.clamp(-MAX_LATITUDE, MAX_LATITUDE).to_radians().cos()
+}
+
+/// Convert a real altitude in metres above ground to a world-space height at a latitude
+pub fn altitude_to_world(altitude: f64, lat: f64) -> f64 {
+    altitude * mercator_scale(lat)
+}
+
+/// Real altitude in metres above ground for a world-space position
+pub fn world_altitude(world: DVec3) -> f64 {
+    let (lat, _) = world_to_lat_lon(world.x, world.z);
+    world.y / mercator_scale(lat)
+}
+
+/// Distance in metres from a camera at the given altitude to the horizon of a spherical earth
+pub fn horizon_distance(altitude: f64) -> f64 {
+    let altitude = altitude.max(0.0);
+    (altitude * (2.0 * EARTH_RADIUS + altitude)).sqrt()
+}
+
+/// Edge length of a tile at the given zoom level, in world units
+pub fn tile_size_world(zoom: u32) -> f64 {
+    WORLD_SIZE / 2_f64.powi(zoom as i32)
+}
+
+/// True ground resolution of a tile texel in metres per pixel at a latitude
+pub fn ground_resolution(lat: f64, zoom: u32) -> f64 {
+    tile_size_world(zoom) / TILE_SIZE_PIXELS / mercator_scale(lat)
+}
+
+/// Size of one screen pixel on the ground straight below a camera, in world units
+pub fn world_units_per_pixel(height: f64, fov_y: f64, viewport_height: f64) -> f64 {
+    2.0 * height.max(0.0) * (fov_y / 2.0).tan() / viewport_height.max(1.0)
+}
+
+/// Fractional zoom level whose texels match the given on-screen resolution
+///
+/// The Mercator scale factor cancels out here: both the tile texels and the
+/// screen pixels are measured in the same stretched world units.
+pub fn zoom_for_resolution(world_units_per_pixel: f64) -> f64 {
+    (WORLD_SIZE / (TILE_SIZE_PIXELS * world_units_per_pixel.max(f64::EPSILON))).log2()
+}
+
+// Most steps `GlobeFrame::unmorph_ground` takes; it usually needs fewer than five
+const UNMORPH_ITERATIONS: usize = 50;
+// How close (world units) the morphed guess of `GlobeFrame::unmorph_ground` has to come
+const UNMORPH_TOLERANCE: f64 = 1e-3;
+
+/// Frame for wrapping the flat Mercator world onto the WGS84 ellipsoid
+///
+/// The globe touches the flat plane at the anchor with the same tangent plane and
+/// scale, so points near the anchor barely move when blending between the two.
+pub struct GlobeFrame {
+    anchor: DVec3,
+    reference: LatLon,
+    reference_ecef: DVec3,
+    scale: f64,
+}
+
+impl GlobeFrame {
+    /// Create a frame touching the flat plane at a world position on the ground
+    pub fn new(anchor: DVec3) -> Self {
+        let (lat, lon) = world_to_lat_lon(anchor.x, anchor.z);
+        let reference = LatLon::new(lat, lon);
+        Self {
+            anchor: DVec3::new(anchor.x, 0.0, anchor.z),
+            reference,
+            reference_ecef: geodetic_to_ecef(reference, 0.0),
+            scale: mercator_scale(lat),
+        }
+    }
+
+    /// Position of a flat world point on the globe, in world units
+    pub fn to_globe(&self, world: DVec3) -> DVec3 {
+        let (lat, lon) = world_to_lat_lon(world.x, world.z);
+        let height = world.y / self.scale;
+        let offset = geodetic_to_ecef(LatLon::new(lat, lon), height) - self.reference_ecef;
+        let enu = ecef_to_enu(offset, self.reference);
+        // East maps to +X, north to -Z and up to +Y, scaled to match the plane at the anchor
+        self.anchor + DVec3::new(enu.x, enu.z, -enu.y) * self.scale
+    }
+
+    /// Blend a flat world point towards its globe position (0.0 = flat, 1.0 = globe)
+    pub fn morph(&self, world: DVec3, factor: f64) -> DVec3 {
+        if factor <= 0.0 {
+            return world;
+        }
+        world.lerp(self.to_globe(world), factor)
+    }
+
+    /// Flat ground position that `morph` moves onto a point of the (partly) bent ground,
+    /// or `None` where no flat position lands within a millimetre of it
+    ///
+    /// There is no closed form for the blend, so this solves for the flat position with
+    /// Gauss-Newton steps, measuring how the morphed position moves with the flat one. All
+    /// three axes are matched, as seen from above the bent ground can fold over itself far
+    /// from the anchor. A step that overshoots is shortened, so the search can't run away.
+    pub fn unmorph_ground(&self, morphed: DVec3, factor: f64) -> Option<DVec3> {
+        let mut flat = DVec3::new(morphed.x, 0.0, morphed.z);
+        if factor <= 0.0 {
+            return Some(flat);
+        }
+        let error = |flat: DVec3| self.morph(flat, factor) - morphed;
+
+        let mut miss = error(flat);
+        for _ in 0..UNMORPH_ITERATIONS {
+            if miss.length() < UNMORPH_TOLERANCE {
+                return Some(flat);
+            }
+            // How the miss changes per world unit east and south
+            let east = error(flat + DVec3::X) - miss;
+            let south = error(flat + DVec3::Z) - miss;
+            let normal = DMat2::from_cols_array(&[east.dot(east), east.dot(south), east.dot(south), south.dot(south)]);
+            if normal.determinant().abs() < 1e-12 {
+                return None;
+            }
+            let change = normal.inverse() * -DVec2::new(east.dot(miss), south.dot(miss));
+
+            let mut scale = 1.0;
+            loop {
+                let next = flat + DVec3::new(change.x, 0.0, change.y) * scale;
+                let next_miss = error(next);
+                if next_miss.length() < miss.length() {
+                    flat = next;
+                    miss = next_miss;
+                    break;
+                }
+                scale /= 2.0;
+                if scale < 1e-6 {
+                    return None;
+                }
+            }
+        }
+        (miss.length() < UNMORPH_TOLERANCE).then_some(flat)
+    }
+
+    /// Radius of the globe in world units
+    pub fn radius(&self) -> f64 {
+        EARTH_RADIUS * self.scale
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::geodesy::destination_point;
+
+    fn ground(position: LatLon) -> DVec3 {
+        let (x, z) = lat_lon_to_world(position.lat, position.lon);
+        DVec3::new(x, 0.0, z)
+    }
+
+    #[test]
+    fn unmorphing_finds_the_flat_point_across_the_visible_globe() {
+        for anchor_lat in [0.0, 50.0, 75.0] {
+            let anchor = LatLon::new(anchor_lat, 10.0);
+            let frame = GlobeFrame::new(ground(anchor));
+            for distance_km in [1.0, 100.0, 1000.0, 3000.0, 5000.0] {
+                for bearing in [0.0, 45.0, 90.0, 180.0, 270.0] {
+                    // Over the pole the flat map runs out before the globe does
+                    let over_pole = bearing == 0.0 && anchor_lat + distance_km / 111.0 > 90.0;
+                    let position = destination_point(anchor, bearing, distance_km * 1000.0);
+                    if over_pole || position.lat.abs() > 80.0 {
+                        continue;
+                    }
+                    let flat = ground(position);
+                    for factor in [0.25, 0.5, 1.0] {
+                        let found = frame.unmorph_ground(frame.morph(flat, factor), factor)
+                            .unwrap_or_else(|| panic!("{anchor_lat} {distance_km} km {bearing}° at {factor}"));
+                        // On the whole globe the copies of the world east and west are the same point
+                        let mut miss = found - flat;
+                        miss.x -= WORLD_SIZE * (miss.x / WORLD_SIZE).round();
+                        assert!(miss.length() < 0.01, "{anchor_lat} {distance_km} km {bearing}° at {factor}: {miss}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unmorphing_the_flat_map_changes_nothing() {
+        let point = DVec3::new(1234.5, 0.0, -6789.0);
+        let frame = GlobeFrame::new(DVec3::ZERO);
+        assert_eq!(frame.unmorph_ground(point, 0.0), Some(point));
+        // Near the anchor the globe barely bends
+        let found = frame.unmorph_ground(frame.morph(point, 1.0), 1.0).unwrap();
+        assert!(found.distance(point) < 0.01);
+    }
+}
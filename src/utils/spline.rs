@@ -0,0 +1,166 @@
+// Catmull-Rom splines through keyframes at uneven times
+//
+// Each segment is a cubic Hermite curve whose tangents at the keyframes point from the
+// previous to the next keyframe, scaled by the time between them, so the path passes
+// through every keyframe with a continuous velocity.
+
+use bevy::math::{DVec3, DVec4, Quat, Vec4};
+
+/// Hermite basis weights for the two end points and the two (time-scaled) tangents
+fn hermite_weights(t: f64) -> [f64; 4] {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    [
+        2.0 * t3 - 3.0 * t2 + 1.0,
+        -2.0 * t3 + 3.0 * t2,
+        t3 - 2.0 * t2 + t,
+        t3 - t2,
+    ]
+}
+
+// Tangent at a keyframe from its neighbours; at the ends a neighbour is the keyframe itself
+fn tangent<T>(previous: (f64, T), next: (f64, T)) -> T
+where
+    T: std::ops::Sub<Output = T> + std::ops::Div<f64, Output = T>,
+{
+    let span = (next.0 - previous.0).max(f64::EPSILON);
+    (next.1 - previous.1) / span
+}
+
+/// Position on the spline through `points` (time, position) at `time`, clamped to the ends
+///
+/// The times must be increasing.
+pub fn catmull_rom_position(points: &[(f64, DVec3)], time: f64) -> Option<DVec3> {
+    let segment = find_segment(points.iter().map(|(t, _)| *t), time)?;
+    let Some(&(t1, p1)) = points.get(segment + 1) else {
+        return points.last().map(|(_, p)| *p);
+    };
+    let (t0, p0) = points[segment];
+    let before = points[segment.saturating_sub(1)];
+    let after = points.get(segment + 2).copied().unwrap_or((t1, p1));
+
+    let duration = (t1 - t0).max(f64::EPSILON);
+    let m0 = tangent(before, (t1, p1)) * duration;
+    let m1 = tangent((t0, p0), after) * duration;
+    let [h00, h01, h10, h11] = hermite_weights(((time - t0) / duration).clamp(0.0, 1.0));
+    Some(p0 * h00 + p1 * h01 + m0 * h10 + m1 * h11)
+}
+
+/// Rotation on the spline through `rotations` (time, rotation) at `time`, clamped to the ends
+///
+/// Interpolates the quaternion components and normalizes the result, which is smooth and
+/// close to the true spherical spline for the small turns between keyframes.
+pub fn catmull_rom_rotation(rotations: &[(f64, Quat)], time: f64) -> Option<Quat> {
+    // Put every quaternion on the same side as the one before it, so no segment takes the long way round
+    let mut aligned: Vec<(f64, DVec4)> = Vec::with_capacity(rotations.len());
+    for &(t, rotation) in rotations {
+        let mut q = Vec4::from(rotation);
+        if let Some(&(_, previous)) = aligned.last() {
+            if previous.dot(q.as_dvec4()) < 0.0 {
+                q = -q;
+            }
+        }
+        aligned.push((t, q.as_dvec4()));
+    }
+
+    let segment = find_segment(aligned.iter().map(|(t, _)| *t), time)?;
+    let Some(&(t1, q1)) = aligned.get(segment + 1) else {
+        return rotations.last().map(|(_, q)| *q);
+    };
+    let (t0, q0) = aligned[segment];
+    let before = aligned[segment.saturating_sub(1)];
+    let after = aligned.get(segment + 2).copied().unwrap_or((t1, q1));
+
+    let duration = (t1 - t0).max(f64::EPSILON);
+    let m0 = tangent(before, (t1, q1)) * duration;
+    let m1 = tangent((t0, q0), after) * duration;
+    let [h00, h01, h10, h11] = hermite_weights(((time - t0) / duration).clamp(0.0, 1.0));
+    let q = (q0 * h00 + q1 * h01 + m0 * h10 + m1 * h11).as_vec4();
+    Some(Quat::from_vec4(q).normalize())
+}
+
+// Index of the keyframe that starts the segment containing `time`
+fn find_segment(times: impl Iterator<Item = f64>, time: f64) -> Option<usize> {
+    let mut segment = None;
+    for (index, t) in times.enumerate() {
+        if segment.is_none() || t <= time {
+            segment = Some(index);
+        } else {
+            break;
+        }
+    }
+    segment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points() -> Vec<(f64, DVec3)> {
+        vec![
+            (0.0, DVec3::new(0.0, 10.0, 0.0)),
+            (0.5, DVec3::new(100.0, 20.0, -50.0)),
+            (3.0, DVec3::new(120.0, 5.0, 400.0)),
+            (3.2, DVec3::new(-30.0, 80.0, 410.0)),
+        ]
+    }
+
+    #[test]
+    fn positions_pass_through_keyframes_at_uneven_times() {
+        let points = points();
+        for &(time, position) in &points {
+            let sampled = catmull_rom_position(&points, time).unwrap();
+            assert!(sampled.distance(position) < 1e-9, "{time}: {sampled} != {position}");
+        }
+    }
+
+    #[test]
+    fn positions_clamp_to_the_ends() {
+        let points = points();
+        assert_eq!(catmull_rom_position(&points, -5.0), Some(points[0].1));
+        assert_eq!(catmull_rom_position(&points, 100.0), Some(points[3].1));
+    }
+
+    #[test]
+    fn positions_are_continuous_across_keyframes() {
+        let points = points();
+        for &(time, position) in &points[1..3] {
+            let before = catmull_rom_position(&points, time - 1e-7).unwrap();
+            let after = catmull_rom_position(&points, time + 1e-7).unwrap();
+            assert!(before.distance(position) < 1e-3 && after.distance(position) < 1e-3, "{time}");
+        }
+    }
+
+    #[test]
+    fn one_keyframe_holds_and_none_gives_nothing() {
+        let point = DVec3::new(1.0, 2.0, 3.0);
+        assert_eq!(catmull_rom_position(&[(2.0, point)], 0.0), Some(point));
+        assert_eq!(catmull_rom_position(&[(2.0, point)], 5.0), Some(point));
+        assert_eq!(catmull_rom_position(&[], 1.0), None);
+        assert_eq!(catmull_rom_rotation(&[], 1.0), None);
+    }
+
+    #[test]
+    fn rotations_pass_through_keyframes() {
+        let rotations = vec![
+            (0.0, Quat::from_rotation_y(0.0)),
+            (1.0, Quat::from_rotation_y(0.8)),
+            (4.0, Quat::from_rotation_x(-0.5) * Quat::from_rotation_y(1.5)),
+        ];
+        for &(time, rotation) in &rotations {
+            let sampled = catmull_rom_rotation(&rotations, time).unwrap();
+            assert!(sampled.angle_between(rotation) < 1e-4, "{time}");
+        }
+        assert!(catmull_rom_rotation(&rotations, -1.0).unwrap().angle_between(rotations[0].1) < 1e-4);
+        assert!(catmull_rom_rotation(&rotations, 9.0).unwrap().angle_between(rotations[2].1) < 1e-4);
+    }
+
+    #[test]
+    fn rotations_take_the_short_way_round() {
+        // The same small turn, with the second quaternion on the opposite side of the sphere
+        let start = Quat::from_rotation_y(0.1);
+        let end = -Quat::from_rotation_y(0.3);
+        let middle = catmull_rom_rotation(&[(0.0, start), (1.0, end)], 0.5).unwrap();
+        assert!(middle.angle_between(Quat::from_rotation_y(0.2)) < 0.01);
+    }
+}
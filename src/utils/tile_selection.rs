@@ -0,0 +1,224 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use bevy::math::{Affine3A, DVec3, Vec3, Vec3A};
+use bevy::render::primitives::{Aabb, Frustum};
+use crate::resources::FloatingOrigin;
+use crate::utils::coordinate_conversion::{tile_to_world, world_to_unwrapped_tile_coords};
+use crate::resources::constants::max_tile_index;
+use crate::utils::projection::{GlobeFrame, tile_size_world, TILE_SIZE_PIXELS, HALF_WORLD_SIZE};
+
+// Sample points per tile edge used to bound a (possibly bent) tile
+const BOUNDS_SAMPLES: u32 = 5;
+// Extra room around tile bounds for the z-fighting offsets of the tile meshes
+const BOUNDS_PADDING: f32 = 1.0;
+// Closest distance used for the screen-space error, avoids dividing by zero
+const MIN_ERROR_DISTANCE: f32 = 0.01;
+
+/// Everything about the current view that tile selection needs
+pub struct SelectionView<'a> {
+    pub frustum: &'a Frustum,
+    pub floating_origin: &'a FloatingOrigin,
+    /// Camera position in render space
+    pub camera_pos: Vec3,
+    /// Vertical field of view in radians
+    pub fov: f32,
+    /// Viewport height in logical pixels
+    pub viewport_height: f32,
+    /// Globe the tiles are bent onto, and how far (0.0 = flat map)
+    pub globe: &'a GlobeFrame,
+    pub morph_factor: f64,
+}
+
+impl SelectionView<'_> {
+    /// Render-space bounds of a tile where it is actually drawn, including the globe morph
+    pub fn tile_bounds(&self, x: i32, y: u32, zoom: u32) -> Aabb {
+        let (corner_x, corner_z) = tile_to_world(x, y, zoom);
+        let tile_size = tile_size_world(zoom);
+        let step = tile_size / (BOUNDS_SAMPLES - 1) as f64;
+
+        let points = (0..BOUNDS_SAMPLES).flat_map(|i| (0..BOUNDS_SAMPLES).map(move |j| (i, j))).map(|(i, j)| {
+            let flat = DVec3::new(corner_x + i as f64 * step, 0.0, corner_z + j as f64 * step);
+            let world = self.globe.morph(flat, self.morph_factor);
+            self.floating_origin.world_to_render(world)
+        });
+        let mut bounds = Aabb::enclosing(points).unwrap_or_default();
+        bounds.half_extents += Vec3A::splat(BOUNDS_PADDING);
+        bounds
+    }
+
+    /// Whether any part of the bounds is inside the camera frustum
+    pub fn is_visible(&self, bounds: &Aabb) -> bool {
+        self.frustum.intersects_obb(bounds, &Affine3A::IDENTITY, true, true)
+    }
+
+    /// Distance from the camera to the closest point of the bounds
+    pub fn distance_to(&self, bounds: &Aabb) -> f32 {
+        let offset = (Vec3A::from(self.camera_pos) - bounds.center).abs() - bounds.half_extents;
+        offset.max(Vec3A::ZERO).length()
+    }
+
+    /// Size in screen pixels of one texel of a tile at this zoom, at the closest point of its bounds
+    pub fn screen_space_error(&self, bounds: &Aabb, zoom: u32) -> f32 {
+        let distance = self.distance_to(bounds).max(MIN_ERROR_DISTANCE);
+        let texel_size = (tile_size_world(zoom) / TILE_SIZE_PIXELS) as f32;
+        texel_size * self.viewport_height / (2.0 * distance * (self.fov * 0.5).tan())
+    }
+}
+
+// Quadtree node waiting to be refined, ordered by its screen-space error
+struct Candidate {
+    x: i32,
+    y: u32,
+    zoom: u32,
+    error: f32,
+    distance: f32,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.error.total_cmp(&other.error)
+    }
+}
+
+/// Select the tiles to show for a view by walking the tile quadtree
+///
+/// Starting from the zoom 0 tiles of the world copy under the camera and its neighbours,
+/// visible nodes are refined worst-first until every node's texels are at most
+/// `max_screen_space_error` pixels on screen, `max_zoom` is reached or `max_tiles` would be
+/// exceeded. Nodes outside the frustum are dropped. The result is sorted nearest first as
+/// (x, y, zoom, priority), with unwrapped x columns.
+pub fn select_tiles(
+    view: &SelectionView,
+    min_zoom: u32,
+    max_zoom: u32,
+    max_screen_space_error: f32,
+    max_tiles: usize,
+) -> Vec<(i32, u32, u32, i32)> {
+    let mut open = BinaryHeap::new();
+    let mut leaves = Vec::new();
+
+    let push_if_visible = |open: &mut BinaryHeap<Candidate>, x: i32, y: u32, zoom: u32| {
+        let bounds = view.tile_bounds(x, y, zoom);
+        if !view.is_visible(&bounds) {
+            return;
+        }
+        open.push(Candidate {
+            x,
+            y,
+            zoom,
+            error: view.screen_space_error(&bounds, zoom),
+            distance: view.distance_to(&bounds),
+        });
+    };
+
+    // The world repeats horizontally, so start with the copies around the camera
+    let camera_world = view.floating_origin.render_to_world(view.camera_pos);
+    let world_copy = ((camera_world.x + tile_size_world(0) * 0.5) / tile_size_world(0)).floor() as i32;
+    for x in world_copy - 1..=world_copy + 1 {
+        push_if_visible(&mut open, x, 0, 0);
+    }
+
+    while let Some(node) = open.pop() {
+        // Everything below the minimum zoom is always refined, the rest only while it is too coarse
+        let must_refine = node.zoom < min_zoom;
+        let wants_refine = node.error > max_screen_space_error && node.zoom < max_zoom;
+        // Splitting a node replaces it with up to four children
+        let has_budget = leaves.len() + open.len() + 4 <= max_tiles;
+
+        if must_refine || (wants_refine && has_budget) {
+            let zoom = node.zoom + 1;
+            for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                push_if_visible(&mut open, node.x * 2 + dx, node.y * 2 + dy as u32, zoom);
+            }
+        } else {
+            leaves.push(node);
+        }
+    }
+
+    leaves.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+    leaves
+        .into_iter()
+        .enumerate()
+        .map(|(priority, node)| (node.x, node.y, node.zoom, priority as i32))
+        .collect()
+}
+
+/// Select low zoom background tiles in rings that get coarser towards the horizon
+///
+/// The innermost ring is a square of `4 * half_width + 2` tiles per edge at `inner_zoom`,
+/// aligned to the tiles one level up. Every next ring is one zoom level coarser and covers
+/// the same number of tiles minus the hole taken by the ring inside it, so the rings never
+/// overlap and each one doubles the covered distance. Rings are added until they reach
+/// `max_distance` (world units) or `min_zoom`; only tiles in the frustum are returned,
+/// innermost ring first, as (x, y, zoom, priority).
+pub fn horizon_rings(
+    view: &SelectionView,
+    inner_zoom: u32,
+    min_zoom: u32,
+    half_width: i32,
+    max_distance: f64,
+) -> Vec<(i32, u32, u32, i32)> {
+    let camera_world = view.floating_origin.render_to_world(view.camera_pos);
+    let half_width = half_width.max(1);
+    let mut tiles = Vec::new();
+    // Tiles at the current level that the previous (finer) ring already covers
+    let mut hole: Option<(i32, i32)> = None;
+
+    for (ring, zoom) in (min_zoom.max(1)..=inner_zoom).rev().enumerate() {
+        // The square is made of whole tiles one level up, so the next ring can cut it out exactly
+        let (parent_x, parent_y) = world_to_unwrapped_tile_coords(camera_world.x, camera_world.z, zoom - 1);
+        let min_x = 2 * (parent_x - half_width);
+        let min_y = 2 * (parent_y as i32 - half_width);
+        let size = 4 * half_width + 2;
+        let max_index = max_tile_index(zoom) as i32;
+
+        for x in min_x..min_x + size {
+            for y in min_y..min_y + size {
+                // Nothing exists north or south of the Mercator square
+                if y < 0 || y > max_index {
+                    continue;
+                }
+                if hole.is_some_and(|(hole_x, hole_y)| {
+                    (x - hole_x).abs() <= half_width && (y - hole_y).abs() <= half_width
+                }) {
+                    continue;
+                }
+                let bounds = view.tile_bounds(x, y as u32, zoom);
+                if view.is_visible(&bounds) {
+                    tiles.push((x, y as u32, zoom, 1000 + ring as i32));
+                }
+            }
+        }
+
+        // Distance from the camera to the nearest edge of the covered square
+        let tile_size = tile_size_world(zoom);
+        let west = min_x as f64 * tile_size - HALF_WORLD_SIZE;
+        let north = min_y as f64 * tile_size - HALF_WORLD_SIZE;
+        let extent = size as f64 * tile_size;
+        let covered = (camera_world.x - west)
+            .min(west + extent - camera_world.x)
+            .min(camera_world.z - north)
+            .min(north + extent - camera_world.z);
+        if covered >= max_distance {
+            break;
+        }
+
+        hole = Some((parent_x, parent_y as i32));
+    }
+
+    tiles
+}